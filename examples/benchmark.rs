@@ -0,0 +1,123 @@
+// Renders the egui demo headlessly with each combination of render settings and prints the time spent in
+// `EguiSoftwareRender::render()`. Run with --release, optionally with `--features rayon`.
+
+use argh::FromArgs;
+use egui::{Pos2, vec2};
+use egui_demo_lib::ColorTest;
+use egui_software_backend::{BufferMutRef, ColorFieldOrder, EguiSoftwareRender};
+use std::time::{Duration, Instant};
+
+/// Frames rendered before measuring so textures are uploaded and the cache is populated.
+const WARMUP_FRAMES: usize = 10;
+
+#[derive(FromArgs, Copy, Clone)]
+/// `benchmark` example
+struct Args {
+    /// number of measured frames per configuration.
+    #[argh(option, default = "200")]
+    frames: usize,
+
+    /// width of the rendered image in pixels.
+    #[argh(option, default = "1920")]
+    width: usize,
+
+    /// height of the rendered image in pixels.
+    #[argh(option, default = "1080")]
+    height: usize,
+
+    /// egui pixels per point.
+    #[argh(option, default = "1.0")]
+    ppp: f32,
+}
+
+fn main() {
+    let args: Args = argh::from_env();
+
+    println!(
+        "{} frames at {}x{}, pixels_per_point {}",
+        args.frames, args.width, args.height, args.ppp
+    );
+    println!(
+        "{:<8} {:<12} {:<15} {:>10} {:>10}",
+        "cache", "raster_opt", "tris_to_rects", "avg ms", "min ms"
+    );
+
+    for use_cache in [false, true] {
+        for allow_raster_opt in [false, true] {
+            for convert_tris_to_rects in [false, true] {
+                let (avg, min) = bench(args, use_cache, allow_raster_opt, convert_tris_to_rects);
+                println!(
+                    "{:<8} {:<12} {:<15} {:>10.3} {:>10.3}",
+                    use_cache,
+                    allow_raster_opt,
+                    convert_tris_to_rects,
+                    avg.as_secs_f64() * 1000.0,
+                    min.as_secs_f64() * 1000.0,
+                );
+            }
+        }
+    }
+}
+
+/// Returns the average and min render time over `args.frames` frames.
+fn bench(
+    args: Args,
+    use_cache: bool,
+    allow_raster_opt: bool,
+    convert_tris_to_rects: bool,
+) -> (Duration, Duration) {
+    let ctx = egui::Context::default();
+    let mut egui_demo = egui_demo_lib::DemoWindows::default();
+    let mut egui_color_test = ColorTest::default();
+    let mut egui_software_render = EguiSoftwareRender::new(ColorFieldOrder::Bgra)
+        .with_allow_raster_opt(allow_raster_opt)
+        .with_convert_tris_to_rects(convert_tris_to_rects)
+        .with_caching(use_cache);
+
+    let mut buffer = vec![[0u8; 4]; args.width * args.height];
+    let screen_size = vec2(args.width as f32, args.height as f32) / args.ppp;
+
+    let mut total = Duration::ZERO;
+    let mut min = Duration::MAX;
+
+    for frame in 0..WARMUP_FRAMES + args.frames {
+        let raw_input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(Pos2::ZERO, screen_size)),
+            // Advance time so animated demos keep changing
+            time: Some(frame as f64 / 60.0),
+            ..Default::default()
+        };
+        ctx.set_pixels_per_point(args.ppp);
+
+        let full_output = ctx.run_ui(raw_input, |ui| {
+            egui_demo.ui(ui);
+
+            egui::Window::new("Color Test").show(ui, |ui| {
+                egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                    egui_color_test.ui(ui);
+                });
+            });
+        });
+
+        let clipped_primitives = ctx.tessellate(full_output.shapes, full_output.pixels_per_point);
+
+        buffer.fill([0; 4]);
+        let buffer_ref = &mut BufferMutRef::new(&mut buffer, args.width, args.height);
+
+        let start = Instant::now();
+        egui_software_render.render(
+            buffer_ref,
+            &clipped_primitives,
+            &full_output.textures_delta,
+            full_output.pixels_per_point,
+        );
+        let elapsed = start.elapsed();
+
+        if frame >= WARMUP_FRAMES {
+            total += elapsed;
+            min = min.min(elapsed);
+        }
+    }
+
+    (total / args.frames.max(1) as u32, min)
+}
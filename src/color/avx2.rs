@@ -3,8 +3,14 @@
 use core::ptr::write_unaligned;
 use core::{arch::x86_64::*, ptr::read_unaligned};
 
+use egui::{Color32, TextureFilter, TextureWrapMode, Vec2};
+
 use crate::color::sse41::Sse41Impl;
-use crate::color::{GenericImpl, SelectedImpl};
+use crate::color::{
+    ColorTexels, GenericImpl, SelectedImpl, YUV_FRAC_BITS, YuvCoeffs, swizzle_rgba_bgra,
+    vec4_to_u8x4,
+};
+use crate::math::vec4::Vec4;
 
 type U8x4x4 = __m128i;
 type U16x4x2 = __m128i;
@@ -69,31 +75,32 @@ impl Avx2Impl {
     }
 
     // https://www.lgfae.com/posts/2025-09-01-AlphaBlendWithSIMD.html
-    /// dst[i] = blend(src[i], dst[i]) // As unorm
+    /// dst[i] = blend(src[i] * tints[i], dst[i]) // As unorm
     /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
     #[target_feature(enable = "avx2")]
-    fn egui_blend_u8_slice_one_src_tinted_fn_avx2(
+    fn egui_blend_u8_slice_tinted_per_px_avx2(
         self,
-        src: [u8; 4],
-        mut tint_fn: impl FnMut() -> [u8; 4],
+        src: &[[u8; 4]],
+        tints: &[[u8; 4]],
         dst: &mut [[u8; 4]],
     ) {
+        assert_eq!(src.len(), dst.len());
+        assert_eq!(tints.len(), dst.len());
         let n = dst.len();
         if n == 0 {
             return;
         }
 
-        let src8 = _mm_set1_epi32(i32::from_le_bytes(src));
-        let src16 = x8_zeroextend16(src8);
-
         let mut i = 0usize;
         while i + 3 < n {
+            // Load 4 src pixels
+            let src = unsafe { src.as_ptr().add(i) }.cast::<__m128i>();
+            let src8 = unsafe { read_unaligned(src) };
+            let src16 = x8_zeroextend16(src8);
+
             // Load 4 tint values
-            let tint_a = i32::from_le_bytes(tint_fn());
-            let tint_b = i32::from_le_bytes(tint_fn());
-            let tint_c = i32::from_le_bytes(tint_fn());
-            let tint_d = i32::from_le_bytes(tint_fn());
-            let tint8: U8x4x4 = _mm_set_epi32(tint_d, tint_c, tint_b, tint_a);
+            let tint = unsafe { tints.as_ptr().add(i) }.cast::<__m128i>();
+            let tint8: U8x4x4 = unsafe { read_unaligned(tint) };
             let tint16: U16x4x4 = x8_zeroextend16(tint8);
 
             // Load 4 dst pixels
@@ -113,7 +120,7 @@ impl Avx2Impl {
 
         // Tail: handle the last pixels (if any) in scalar
         while i < n {
-            dst[i] = self.egui_blend_u8(self.unorm_mult4x4(src, tint_fn()), dst[i]);
+            dst[i] = self.egui_blend_u8(self.unorm_mult4x4(src[i], tints[i]), dst[i]);
             i += 1;
         }
     }
@@ -210,6 +217,227 @@ impl Avx2Impl {
             i += 1;
         }
     }
+    /// dst[i] = texels.sample_bilinear(uv_start + uv_step * i)
+    #[target_feature(enable = "avx2")]
+    fn sample_bilinear_span_avx2(
+        self,
+        texels: ColorTexels,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        let n = dst.len();
+        let wrap_mode = texels.options.wrap_mode;
+        let nearest = texels.options.magnification == TextureFilter::Nearest;
+        let fsize = texels.fsize();
+        let width_extent = texels.width as i32 - 1;
+        let height_extent = texels.height as i32 - 1;
+
+        let lanes = _mm256_setr_ps(0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0);
+        let half = _mm256_set1_ps(0.5);
+        let tex_w = _mm256_set1_ps(fsize.x);
+        let tex_h = _mm256_set1_ps(fsize.y);
+        let neg_one = _mm256_set1_ps(-1.0);
+        let x_extent_f = _mm256_set1_ps(width_extent as f32);
+        let y_extent_f = _mm256_set1_ps(height_extent as f32);
+        let x_extent = _mm256_set1_epi32(width_extent);
+        let y_extent = _mm256_set1_epi32(height_extent);
+        let zero = _mm256_setzero_si256();
+        let one = _mm256_set1_epi32(1);
+        let row_len = _mm256_set1_epi32(texels.width as i32);
+        let tex_ptr = texels.data.as_ptr().cast::<i32>();
+
+        let mut i = 0;
+        while i + 7 < n {
+            let fi = _mm256_add_ps(_mm256_set1_ps(i as f32), lanes);
+            let u = _mm256_add_ps(
+                _mm256_set1_ps(uv_start.x),
+                _mm256_mul_ps(fi, _mm256_set1_ps(uv_step.x)),
+            );
+            let v = _mm256_add_ps(
+                _mm256_set1_ps(uv_start.y),
+                _mm256_mul_ps(fi, _mm256_set1_ps(uv_step.y)),
+            );
+
+            let (u, v) = match wrap_mode {
+                TextureWrapMode::ClampToEdge => (u, v),
+                TextureWrapMode::Repeat => (x_repeat(u), x_repeat(v)),
+                TextureWrapMode::MirroredRepeat => (x_mirror(u), x_mirror(v)),
+            };
+
+            let sx = _mm256_sub_ps(_mm256_mul_ps(u, tex_w), half);
+            let sy = _mm256_sub_ps(_mm256_mul_ps(v, tex_h), half);
+            let sx0 = _mm256_floor_ps(sx);
+            let sy0 = _mm256_floor_ps(sy);
+
+            let (fx, fy) = if nearest {
+                (_mm256_setzero_ps(), _mm256_setzero_ps())
+            } else {
+                (_mm256_sub_ps(sx, sx0), _mm256_sub_ps(sy, sy0))
+            };
+
+            // Limit before converting so that out of range (or NaN) uvs still result in in-bounds texel indices.
+            let x0 = _mm256_cvttps_epi32(_mm256_min_ps(_mm256_max_ps(sx0, neg_one), x_extent_f));
+            let y0 = _mm256_cvttps_epi32(_mm256_min_ps(_mm256_max_ps(sy0, neg_one), y_extent_f));
+            let x1 = _mm256_min_epi32(_mm256_add_epi32(x0, one), x_extent);
+            let y1 = _mm256_min_epi32(_mm256_add_epi32(y0, one), y_extent);
+            let x0 = _mm256_max_epi32(x0, zero);
+            let y0 = _mm256_max_epi32(y0, zero);
+
+            let row0 = _mm256_mullo_epi32(y0, row_len);
+            let row1 = _mm256_mullo_epi32(y1, row_len);
+
+            let taps = unsafe {
+                [
+                    _mm256_i32gather_epi32(tex_ptr, _mm256_add_epi32(row0, x0), 4),
+                    _mm256_i32gather_epi32(tex_ptr, _mm256_add_epi32(row1, x0), 4),
+                    _mm256_i32gather_epi32(tex_ptr, _mm256_add_epi32(row0, x1), 4),
+                    _mm256_i32gather_epi32(tex_ptr, _mm256_add_epi32(row1, x1), 4),
+                ]
+            };
+
+            let out = bilinear_filter_8(taps, fx, fy);
+
+            let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m256i>();
+            unsafe { write_unaligned(dst_ptr, out) };
+            i += 8;
+        }
+
+        while i < n {
+            dst[i] = texels.sample_bilinear(uv_start + uv_step * i as f32);
+            i += 1;
+        }
+    }
+
+    /// dst[i] = vec4_to_u8x4(col_start + col_step * i)
+    #[target_feature(enable = "avx2")]
+    fn color_gradient_span_avx2(self, col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+        let n = dst.len();
+        let Vec4 { x, y, z, w } = col_start;
+        let start = _mm256_setr_ps(x, y, z, w, x, y, z, w);
+        let Vec4 { x, y, z, w } = col_step;
+        let step = _mm256_setr_ps(x, y, z, w, x, y, z, w);
+        let pair = _mm256_setr_ps(0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0);
+        let zero = _mm256_setzero_ps();
+        let one = _mm256_set1_ps(1.0);
+        let scale = _mm256_set1_ps(255.0);
+        let half = _mm256_set1_ps(0.5);
+        // Undoes the lane interleaving of the in-lane packs below
+        let unshuffle = _mm256_setr_epi32(0, 4, 1, 5, 2, 6, 3, 7);
+
+        // Two pixels worth of channels as i32
+        let color_i32 = |i: usize| -> __m256i {
+            let fi = _mm256_add_ps(_mm256_set1_ps(i as f32), pair);
+            let c = _mm256_add_ps(start, _mm256_mul_ps(step, fi));
+            let c = _mm256_max_ps(_mm256_min_ps(c, one), zero);
+            _mm256_cvttps_epi32(_mm256_add_ps(_mm256_mul_ps(c, scale), half))
+        };
+
+        let mut i = 0;
+        while i + 7 < n {
+            // Lanes hold [p0, p2 | p1, p3] and [p4, p6 | p5, p7]
+            let c0123 = _mm256_packs_epi32(color_i32(i), color_i32(i + 2));
+            let c4567 = _mm256_packs_epi32(color_i32(i + 4), color_i32(i + 6));
+            let out = _mm256_packus_epi16(c0123, c4567);
+            let out = _mm256_permutevar8x32_epi32(out, unshuffle);
+
+            let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m256i>();
+            unsafe { write_unaligned(dst_ptr, out) };
+            i += 8;
+        }
+
+        while i < n {
+            dst[i] = vec4_to_u8x4(&(col_start + col_step * i as f32));
+            i += 1;
+        }
+    }
+
+    /// dst[i] = swizzle_rgba_bgra(src[i])
+    #[target_feature(enable = "avx2")]
+    fn swizzle_rgba_bgra_slice_avx2(self, src: &[Color32], dst: &mut [[u8; 4]]) {
+        assert_eq!(src.len(), dst.len());
+
+        let n = dst.len();
+        // _mm256_shuffle_epi8 shuffles within each 128 bit lane
+        let shuffle = _mm256_setr_epi8(
+            2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8, 11, 14, 13, 12, 15, 2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8,
+            11, 14, 13, 12, 15,
+        );
+
+        let mut i = 0;
+        while i + 7 < n {
+            // Color32 is a repr(C) [u8; 4]
+            let src_ptr = unsafe { src.as_ptr().add(i) }.cast::<__m256i>();
+            let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m256i>();
+            let px = unsafe { read_unaligned(src_ptr) };
+            unsafe { write_unaligned(dst_ptr, _mm256_shuffle_epi8(px, shuffle)) };
+            i += 8;
+        }
+
+        while i < n {
+            dst[i] = swizzle_rgba_bgra(src[i].to_array());
+            i += 1;
+        }
+    }
+
+    /// dst[i] = coeffs.convert(y[i], u[i], v[i])
+    #[target_feature(enable = "avx2")]
+    fn yuv_to_rgba_span_avx2(
+        self,
+        y: &[u8],
+        u: &[u8],
+        v: &[u8],
+        coeffs: &YuvCoeffs,
+        dst: &mut [[u8; 4]],
+    ) {
+        let n = dst.len();
+        assert!(y.len() >= n && u.len() >= n && v.len() >= n);
+
+        let y_offset = _mm256_set1_epi32(coeffs.y_offset);
+        let y_scale = _mm256_set1_epi32(coeffs.y_scale);
+        let round = _mm256_set1_epi32(1 << (YUV_FRAC_BITS - 1));
+        let chroma_offset = _mm256_set1_epi32(128);
+        let coeffs_u = coeffs.u.map(|c| _mm256_set1_epi32(c));
+        let coeffs_v = coeffs.v.map(|c| _mm256_set1_epi32(c));
+        let zero = _mm256_setzero_si256();
+        let max = _mm256_set1_epi32(255);
+        let alpha = _mm256_set1_epi32(0xFF00_0000_u32 as i32);
+
+        // 8 bytes zero extended to i32
+        let load = |p: &[u8], i: usize| {
+            let bytes = i64::from_le_bytes(p[i..i + 8].try_into().unwrap());
+            _mm256_cvtepu8_epi32(_mm_cvtsi64_si128(bytes))
+        };
+
+        let mut i = 0;
+        while i + 7 < n {
+            let y = _mm256_mullo_epi32(_mm256_sub_epi32(load(y, i), y_offset), y_scale);
+            let y = _mm256_add_epi32(y, round);
+            let u = _mm256_sub_epi32(load(u, i), chroma_offset);
+            let v = _mm256_sub_epi32(load(v, i), chroma_offset);
+            let channel = |c: usize| {
+                let x = _mm256_add_epi32(
+                    _mm256_add_epi32(y, _mm256_mullo_epi32(u, coeffs_u[c])),
+                    _mm256_mullo_epi32(v, coeffs_v[c]),
+                );
+                let x = _mm256_srai_epi32::<YUV_FRAC_BITS>(x);
+                _mm256_min_epi32(_mm256_max_epi32(x, zero), max)
+            };
+            let out = _mm256_or_si256(
+                _mm256_or_si256(channel(0), _mm256_slli_epi32::<8>(channel(1))),
+                _mm256_or_si256(_mm256_slli_epi32::<16>(channel(2)), alpha),
+            );
+
+            let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m256i>();
+            unsafe { write_unaligned(dst_ptr, out) };
+            i += 8;
+        }
+
+        while i < n {
+            dst[i] = coeffs.convert(y[i], u[i], v[i]);
+            i += 1;
+        }
+    }
 }
 
 impl SelectedImpl for Avx2Impl {
@@ -224,13 +452,13 @@ impl SelectedImpl for Avx2Impl {
     }
 
     #[inline]
-    fn egui_blend_u8_slice_one_src_tinted_fn(
+    fn egui_blend_u8_slice_tinted_per_px(
         self,
-        src: [u8; 4],
-        tint_fn: impl FnMut() -> [u8; 4],
+        src: &[[u8; 4]],
+        tints: &[[u8; 4]],
         dst: &mut [[u8; 4]],
     ) {
-        unsafe { self.egui_blend_u8_slice_one_src_tinted_fn_avx2(src, tint_fn, dst) }
+        unsafe { self.egui_blend_u8_slice_tinted_per_px_avx2(src, tints, dst) }
     }
 
     #[inline]
@@ -252,6 +480,39 @@ impl SelectedImpl for Avx2Impl {
     fn unorm_mult4x4(self, a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
         GenericImpl.unorm_mult4x4(a, b)
     }
+
+    #[inline]
+    fn sample_bilinear_span(
+        self,
+        texels: ColorTexels,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        unsafe { self.sample_bilinear_span_avx2(texels, uv_start, uv_step, dst) }
+    }
+
+    #[inline]
+    fn color_gradient_span(self, col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+        unsafe { self.color_gradient_span_avx2(col_start, col_step, dst) }
+    }
+
+    #[inline]
+    fn swizzle_rgba_bgra_slice(self, src: &[Color32], dst: &mut [[u8; 4]]) {
+        unsafe { self.swizzle_rgba_bgra_slice_avx2(src, dst) }
+    }
+
+    #[inline]
+    fn yuv_to_rgba_span(
+        self,
+        y: &[u8],
+        u: &[u8],
+        v: &[u8],
+        coeffs: &YuvCoeffs,
+        dst: &mut [[u8; 4]],
+    ) {
+        unsafe { self.yuv_to_rgba_span_avx2(y, u, v, coeffs, dst) }
+    }
 }
 
 /// src_u8x4x4 should have four 8 bit per channel rgba samples stored in the low bits
@@ -319,3 +580,62 @@ fn x16_pack8(x: U16x4x4) -> U8x4x4 {
     let lo: U16x4x2 = _mm256_castsi256_si128(x);
     _mm_packus_epi16(lo, hi)
 }
+
+/// Blends 8 sets of four rgba8 texels (00, 01, 10, 11) using the given x and y bilinear factors.
+#[inline]
+#[target_feature(enable = "avx2")]
+fn bilinear_filter_8(texels: [__m256i; 4], fx: __m256, fy: __m256) -> __m256i {
+    let one = _mm256_set1_ps(1.0);
+    let gx = _mm256_sub_ps(one, fx);
+    let gy = _mm256_sub_ps(one, fy);
+    let weights = [
+        _mm256_mul_ps(gx, gy),
+        _mm256_mul_ps(gx, fy),
+        _mm256_mul_ps(fx, gy),
+        _mm256_mul_ps(fx, fy),
+    ];
+
+    let r = bilinear_channel_8::<0>(texels, weights);
+    let g = bilinear_channel_8::<8>(texels, weights);
+    let b = bilinear_channel_8::<16>(texels, weights);
+    let a = bilinear_channel_8::<24>(texels, weights);
+    _mm256_or_si256(_mm256_or_si256(r, g), _mm256_or_si256(b, a))
+}
+
+/// Weighted sum of the 8 bit channel at SHIFT, returned in place at SHIFT.
+/// Operations are ordered to match ColorTexels::sample_bilinear() exactly.
+#[inline]
+#[target_feature(enable = "avx2")]
+fn bilinear_channel_8<const SHIFT: i32>(texels: [__m256i; 4], weights: [__m256; 4]) -> __m256i {
+    let mask = _mm256_set1_epi32(0xFF);
+    let unorm = _mm256_set1_ps(255.0);
+    let mut sum = _mm256_setzero_ps();
+    for (texel, weight) in texels.into_iter().zip(weights) {
+        let c = _mm256_cvtepi32_ps(_mm256_and_si256(_mm256_srli_epi32::<SHIFT>(texel), mask));
+        sum = _mm256_add_ps(sum, _mm256_mul_ps(_mm256_div_ps(c, unorm), weight));
+    }
+    let sum = _mm256_min_ps(_mm256_max_ps(sum, _mm256_setzero_ps()), _mm256_set1_ps(1.0));
+    let c = _mm256_cvttps_epi32(_mm256_add_ps(
+        _mm256_mul_ps(sum, unorm),
+        _mm256_set1_ps(0.5),
+    ));
+    _mm256_slli_epi32::<SHIFT>(c)
+}
+
+/// x - floor(x), matches the repeat fn in plane_taps()
+#[inline]
+#[target_feature(enable = "avx2")]
+fn x_repeat(x: __m256) -> __m256 {
+    _mm256_sub_ps(x, _mm256_floor_ps(x))
+}
+
+/// Matches the mirror fn in plane_taps()
+#[inline]
+#[target_feature(enable = "avx2")]
+fn x_mirror(x: __m256) -> __m256 {
+    let half = _mm256_set1_ps(0.5);
+    let f = _mm256_sub_ps(x_repeat(_mm256_add_ps(_mm256_mul_ps(x, half), half)), half);
+    // abs(f)
+    let f = _mm256_andnot_ps(_mm256_set1_ps(-0.0), f);
+    _mm256_mul_ps(f, _mm256_set1_ps(2.0))
+}
@@ -1,4 +1,11 @@
-use crate::math::vec4::{Vec4, vec4};
+use egui::{Color32, TextureFilter, TextureOptions, Vec2, vec2};
+
+use crate::{
+    ColorFieldOrder,
+    egui_texture::{EguiTexture, YuvMatrix, YuvRange},
+    math::vec4::{Vec4, vec4},
+    raster::span::SAMPLE_CHUNK,
+};
 
 #[cfg(all(target_arch = "x86_64", feature = "std"))]
 pub(crate) mod avx2;
@@ -123,16 +130,16 @@ pub(crate) trait SelectedImpl: Copy + Sync + Send + 'static {
             *pixel = self.egui_blend_u8(*src, *pixel);
         }
     }
-    /// dst[i] = blend(src * tint_fn(), dst[i]) // As unorm
+    /// dst[i] = blend(src[i] * tints[i], dst[i]) // As unorm
     /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
-    fn egui_blend_u8_slice_one_src_tinted_fn(
+    fn egui_blend_u8_slice_tinted_per_px(
         self,
-        src: [u8; 4],
-        mut tint_fn: impl FnMut() -> [u8; 4],
+        src: &[[u8; 4]],
+        tints: &[[u8; 4]],
         dst: &mut [[u8; 4]],
     ) {
-        for pixel in dst.iter_mut() {
-            *pixel = self.egui_blend_u8(self.unorm_mult4x4(tint_fn(), src), *pixel);
+        for ((pixel, tex_color), tint) in dst.iter_mut().zip(src).zip(tints) {
+            *pixel = self.egui_blend_u8(self.unorm_mult4x4(*tint, *tex_color), *pixel);
         }
     }
 
@@ -192,7 +199,286 @@ pub(crate) trait SelectedImpl: Copy + Sync + Send + 'static {
             unorm_mult(a[3] as u32, b[3] as u32) as u8,
         ]
     }
+
+    /// dst[i] = texels.sample_bilinear(uv_start + uv_step * i)
+    fn sample_bilinear_span(
+        self,
+        texels: ColorTexels,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        for (i, pixel) in dst.iter_mut().enumerate() {
+            *pixel = texels.sample_bilinear(uv_start + uv_step * i as f32);
+        }
+    }
+
+    /// dst[i] = vec4_to_u8x4(col_start + col_step * i)
+    fn color_gradient_span(self, col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+        for (i, pixel) in dst.iter_mut().enumerate() {
+            *pixel = vec4_to_u8x4(&(col_start + col_step * i as f32));
+        }
+    }
+
+    /// dst[i] = blend(color * coverage[i], dst[i]) // As unorm
+    /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
+    fn egui_blend_u8_slice_coverage(self, color: [u8; 4], coverage: &[u8], dst: &mut [[u8; 4]]) {
+        // Expanding to [a, a, a, a] texels gives the same result as the texture having been stored that way
+        let mut texels = [[0u8; 4]; SAMPLE_CHUNK];
+        for (coverage, dst) in coverage
+            .chunks(SAMPLE_CHUNK)
+            .zip(dst.chunks_mut(SAMPLE_CHUNK))
+        {
+            let texels = &mut texels[..coverage.len()];
+            for (texel, &a) in texels.iter_mut().zip(coverage) {
+                *texel = [a; 4];
+            }
+            self.egui_blend_u8_slice_tinted(texels, color, dst);
+        }
+    }
+
+    /// dst[i] = blend(colors[i] * coverage[i], dst[i]) // As unorm
+    /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
+    fn egui_blend_u8_slice_coverage_per_px(
+        self,
+        colors: &[[u8; 4]],
+        coverage: &[u8],
+        dst: &mut [[u8; 4]],
+    ) {
+        let mut texels = [[0u8; 4]; SAMPLE_CHUNK];
+        for ((coverage, colors), dst) in coverage
+            .chunks(SAMPLE_CHUNK)
+            .zip(colors.chunks(SAMPLE_CHUNK))
+            .zip(dst.chunks_mut(SAMPLE_CHUNK))
+        {
+            let texels = &mut texels[..coverage.len()];
+            for (texel, &a) in texels.iter_mut().zip(coverage) {
+                *texel = [a; 4];
+            }
+            self.egui_blend_u8_slice_tinted_per_px(texels, colors, dst);
+        }
+    }
+
+    /// Subpixel blend of glyph coverage. `coverage[i]` holds a coverage per color channel, in the output field order,
+    /// and the grayscale coverage in place of alpha. Opaque dst pixels are blended per channel:
+    /// dst.c = color.c * coverage.c + dst.c * (1 - color.a * coverage.c) // As unorm
+    /// Other dst pixels fall back to grayscale, like `egui_blend_u8_slice_coverage()` with the grayscale coverage.
+    fn egui_blend_u8_slice_lcd(self, color: [u8; 4], coverage: &[[u8; 4]], dst: &mut [[u8; 4]]) {
+        for (pixel, coverage) in dst.iter_mut().zip(coverage) {
+            if pixel[3] == 255 {
+                for c in 0..3 {
+                    let src = unorm_mult(color[c] as u32, coverage[c] as u32);
+                    let src_a = unorm_mult(color[3] as u32, coverage[c] as u32);
+                    let dst = unorm_mult(pixel[c] as u32, 255 - src_a);
+                    pixel[c] = (src + dst).min(255) as u8;
+                }
+            } else {
+                *pixel = self.egui_blend_u8(self.unorm_mult4x4(color, [coverage[3]; 4]), *pixel);
+            }
+        }
+    }
+
+    /// dst[i] = texture.sample_coverage(uv_start + uv_step * i)
+    fn sample_coverage_span(
+        self,
+        texture: &EguiTexture,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [u8],
+    ) {
+        for (i, a) in dst.iter_mut().enumerate() {
+            *a = texture.sample_coverage(uv_start + uv_step * i as f32);
+        }
+    }
+
+    /// dst[i] = swizzle_rgba_bgra(src[i])
+    fn swizzle_rgba_bgra_slice(self, src: &[Color32], dst: &mut [[u8; 4]]) {
+        for (pixel, src) in dst.iter_mut().zip(src) {
+            *pixel = swizzle_rgba_bgra(src.to_array());
+        }
+    }
+
+    /// dst[i] = coeffs.convert(y[i], u[i], v[i])
+    fn yuv_to_rgba_span(
+        self,
+        y: &[u8],
+        u: &[u8],
+        v: &[u8],
+        coeffs: &YuvCoeffs,
+        dst: &mut [[u8; 4]],
+    ) {
+        for (((pixel, &y), &u), &v) in dst.iter_mut().zip(y).zip(u).zip(v) {
+            *pixel = coeffs.convert(y, u, v);
+        }
+    }
+}
+
+/// Texels of a color texture and how they're sampled, the plain inputs of `SelectedImpl::sample_bilinear_span()`.
+/// Procedural, alpha and YUV textures are resolved by `EguiTexture::sample_span()` before reaching the kernels.
+#[derive(Clone, Copy)]
+pub struct ColorTexels<'a> {
+    /// `[u8; 4]` per texel, row after row
+    pub data: &'a [[u8; 4]],
+    pub width: usize,
+    pub height: usize,
+    pub options: TextureOptions,
 }
+
+impl ColorTexels<'_> {
+    #[inline(always)]
+    pub fn fsize(&self) -> Vec2 {
+        vec2(self.width as f32, self.height as f32)
+    }
+
+    /// Bilinear sample at `uv`, filtered according to the options
+    pub fn sample_bilinear(&self, uv: Vec2) -> [u8; 4] {
+        // Every tap of a sample at uv 0 is the first texel
+        if uv == Vec2::ZERO {
+            return self.data[0];
+        }
+
+        let taps = plane_taps(&self.options, [self.width, self.height], uv);
+        let c00 = self.data[taps.idx[0]];
+
+        if taps.nearest {
+            return c00;
+        }
+
+        let c10 = self.data[taps.idx[1]];
+        let c01 = self.data[taps.idx[2]];
+        let c11 = self.data[taps.idx[3]];
+
+        let v00 = u8x4_to_vec4(&c00);
+        let v10 = u8x4_to_vec4(&c10);
+        let v01 = u8x4_to_vec4(&c01);
+        let v11 = u8x4_to_vec4(&c11);
+
+        let (fx, fy) = (taps.fx, taps.fy);
+        let w00 = (1.0 - fx) * (1.0 - fy);
+        let w10 = fx * (1.0 - fy);
+        let w01 = (1.0 - fx) * fy;
+        let w11 = fx * fy;
+
+        vec4_to_u8x4(&(v00 * w00 + v01 * w01 + v10 * w10 + v11 * w11))
+    }
+}
+
+/// Texel indices and weights of a bilinear sample.
+pub(crate) struct BilinearTaps {
+    /// Indices of the texels at (x0, y0), (x1, y0), (x0, y1), (x1, y1)
+    pub idx: [usize; 4],
+    pub fx: f32,
+    pub fy: f32,
+    /// Only the first texel contributes
+    pub nearest: bool,
+}
+
+/// Taps of a bilinear sample at `uv` of a plane of texels of the given size, wrapped according to `options`.
+#[inline(always)]
+pub(crate) fn plane_taps(options: &TextureOptions, size: [usize; 2], uv: Vec2) -> BilinearTaps {
+    let w = size[0] as f32;
+    let h = size[1] as f32;
+    let width_extent = size[0] as i32 - 1;
+    let height_extent = size[1] as i32 - 1;
+
+    #[inline(always)]
+    fn repeat(v: f32) -> f32 {
+        v - v.floor()
+    }
+
+    #[inline(always)]
+    fn mirror(v: f32) -> f32 {
+        (repeat(v * 0.5 + 0.5) - 0.5).abs() * 2.0
+    }
+
+    let uv = match options.wrap_mode {
+        egui::TextureWrapMode::ClampToEdge => uv,
+        egui::TextureWrapMode::Repeat => vec2(repeat(uv.x), repeat(uv.y)),
+        egui::TextureWrapMode::MirroredRepeat => vec2(mirror(uv.x), mirror(uv.y)),
+    };
+
+    let sx = uv.x * w - 0.5;
+    let sy = uv.y * h - 0.5;
+
+    let x0 = sx.floor() as i32;
+    let y0 = sy.floor() as i32;
+    let x1 = x0 + 1;
+    let y1 = y0 + 1;
+
+    let fx = sx - x0 as f32;
+    let fy = sy - y0 as f32;
+
+    let x0c = x0.max(0).min(width_extent) as usize;
+    let y0c = y0.max(0).min(height_extent) as usize;
+    let x1c = x1.max(0).min(width_extent) as usize;
+    let y1c = y1.max(0).min(height_extent) as usize;
+
+    BilinearTaps {
+        idx: [
+            x0c + y0c * size[0],
+            x1c + y0c * size[0],
+            x0c + y1c * size[0],
+            x1c + y1c * size[0],
+        ],
+        fx,
+        fy,
+        // if these are 0 the px at 0,0 will have full influence. Equivalent to nearest sampling.
+        nearest: options.magnification == TextureFilter::Nearest || (fx == 0.0 && fy == 0.0),
+    }
+}
+
+/// Fractional bits of the fixed point `YuvCoeffs`
+pub const YUV_FRAC_BITS: i32 = 12;
+
+/// Fixed point YUV to color conversion for a `YuvMatrix` and `YuvRange`. The color channels are in the output field
+/// order, SIMD implementations of `SelectedImpl::yuv_to_rgba_span()` must match `YuvCoeffs::convert()` exactly.
+#[derive(Clone, Copy, Default)]
+pub struct YuvCoeffs {
+    /// Subtracted from Y before it's scaled
+    pub y_offset: i32,
+    pub y_scale: i32,
+    /// Contribution of U - 128 and of V - 128 to each color channel
+    pub u: [i32; 3],
+    pub v: [i32; 3],
+}
+
+impl YuvCoeffs {
+    pub fn new(matrix: YuvMatrix, range: YuvRange, field_order: ColorFieldOrder) -> Self {
+        let (kr, kb) = match matrix {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+        };
+        let kg = 1.0 - kr - kb;
+        let (y_offset, y_scale, c_scale) = match range {
+            YuvRange::Limited => (16, 255.0 / 219.0, 255.0 / 224.0),
+            YuvRange::Full => (0, 1.0, 1.0),
+        };
+        let fixed = |x: f32| (x * (1 << YUV_FRAC_BITS) as f32).round() as i32;
+        let u_rgb = [0.0, -2.0 * kb * (1.0 - kb) / kg, 2.0 * (1.0 - kb)];
+        let v_rgb = [2.0 * (1.0 - kr), -2.0 * kr * (1.0 - kr) / kg, 0.0];
+        let order = match field_order {
+            ColorFieldOrder::Rgba => [0, 1, 2],
+            ColorFieldOrder::Bgra => [2, 1, 0],
+        };
+        YuvCoeffs {
+            y_offset,
+            y_scale: fixed(y_scale),
+            u: order.map(|c| fixed(u_rgb[c] * c_scale)),
+            v: order.map(|c| fixed(v_rgb[c] * c_scale)),
+        }
+    }
+
+    /// Opaque color of a Y, U, V texel
+    #[inline(always)]
+    pub fn convert(&self, y: u8, u: u8, v: u8) -> [u8; 4] {
+        let y = (y as i32 - self.y_offset) * self.y_scale + (1 << (YUV_FRAC_BITS - 1));
+        let (u, v) = (u as i32 - 128, v as i32 - 128);
+        let channel =
+            |c: usize| ((y + u * self.u[c] + v * self.v[c]) >> YUV_FRAC_BITS).clamp(0, 255) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct GenericImpl;
 
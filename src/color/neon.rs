@@ -2,7 +2,12 @@
 
 use core::arch::aarch64::*;
 
-use crate::color::SelectedImpl;
+use egui::{Color32, TextureFilter, TextureWrapMode, Vec2};
+
+use crate::{
+    color::{ColorTexels, SelectedImpl, YUV_FRAC_BITS, YuvCoeffs, swizzle_rgba_bgra, vec4_to_u8x4},
+    math::vec4::Vec4,
+};
 
 #[derive(Clone, Copy)]
 pub(crate) struct NeonImpl(());
@@ -32,13 +37,13 @@ impl SelectedImpl for NeonImpl {
     }
 
     #[inline]
-    fn egui_blend_u8_slice_one_src_tinted_fn(
+    fn egui_blend_u8_slice_tinted_per_px(
         self,
-        src: [u8; 4],
-        tint_fn: impl FnMut() -> [u8; 4],
+        src: &[[u8; 4]],
+        tints: &[[u8; 4]],
         dst: &mut [[u8; 4]],
     ) {
-        unsafe { egui_blend_u8_slice_one_src_tinted_fn(src, tint_fn, dst) }
+        unsafe { egui_blend_u8_slice_tinted_per_px(src, tints, dst) }
     }
 
     #[inline]
@@ -60,6 +65,39 @@ impl SelectedImpl for NeonImpl {
     fn unorm_mult4x4(self, a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
         unsafe { unorm_mult4x4(a, b) }
     }
+
+    #[inline]
+    fn sample_bilinear_span(
+        self,
+        texels: ColorTexels,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        unsafe { sample_bilinear_span(texels, uv_start, uv_step, dst) }
+    }
+
+    #[inline]
+    fn color_gradient_span(self, col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+        unsafe { color_gradient_span(col_start, col_step, dst) }
+    }
+
+    #[inline]
+    fn swizzle_rgba_bgra_slice(self, src: &[Color32], dst: &mut [[u8; 4]]) {
+        unsafe { swizzle_rgba_bgra_slice(src, dst) }
+    }
+
+    #[inline]
+    fn yuv_to_rgba_span(
+        self,
+        y: &[u8],
+        u: &[u8],
+        v: &[u8],
+        coeffs: &YuvCoeffs,
+        dst: &mut [[u8; 4]],
+    ) {
+        unsafe { yuv_to_rgba_span(y, u, v, coeffs, dst) }
+    }
 }
 
 /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
@@ -244,33 +282,30 @@ fn egui_blend_u8_slice_tinted(src: &[[u8; 4]], tint: [u8; 4], dst: &mut [[u8; 4]
 }
 
 // https://www.lgfae.com/posts/2025-09-01-AlphaBlendWithSIMD.html
-/// dst[i] = blend(src * tint_fn(), dst[i]) // As unorm
+/// dst[i] = blend(src[i] * tints[i], dst[i]) // As unorm
 /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
 #[target_feature(enable = "neon")]
-fn egui_blend_u8_slice_one_src_tinted_fn(
-    src: [u8; 4],
-    mut tint_fn: impl FnMut() -> [u8; 4],
-    dst: &mut [[u8; 4]],
-) {
+fn egui_blend_u8_slice_tinted_per_px(src: &[[u8; 4]], tints: &[[u8; 4]], dst: &mut [[u8; 4]]) {
+    assert_eq!(src.len(), dst.len());
+    assert_eq!(tints.len(), dst.len());
     let n = dst.len();
     if n == 0 {
         return;
     }
 
-    let src32 = u32::from_le_bytes(src);
-    let src8 = vreinterpret_u8_u32(vdup_n_u32(src32));
-    let src16 = vmovl_u8(src8);
-
     let e1 = vdupq_n_u16(0x0080);
 
     let mut i = 0usize;
     while i + 1 < n {
-        // Load two tint values
+        let src_p = unsafe { src.as_ptr().add(i) } as *mut u8;
+        // Load two src pixels
+        let src8 = unsafe { vld1_u8(src_p) };
+        // [0,0,0,0,rg,ba,rg,ba] -> [r,g,b,a,r,g,b,a]
+        let src16 = vmovl_u8(src8);
 
-        let mut t32x2 = vdup_n_u32(0);
-        t32x2 = vset_lane_u32(u32::from_le_bytes(tint_fn()), t32x2, 0);
-        t32x2 = vset_lane_u32(u32::from_le_bytes(tint_fn()), t32x2, 1);
-        let tint8 = vreinterpret_u8_u32(t32x2);
+        // Load two tint values
+        let tint_p = unsafe { tints.as_ptr().add(i) } as *mut u8;
+        let tint8 = unsafe { vld1_u8(tint_p) };
         let tint16 = vmovl_u8(tint8);
 
         // Load two dst pixels
@@ -293,7 +328,7 @@ fn egui_blend_u8_slice_one_src_tinted_fn(
 
     // Tail: handle the last pixel (if any) in scalar
     if i < n {
-        dst[i] = egui_blend_u8(unorm_mult4x4(src, tint_fn()), dst[i]);
+        dst[i] = egui_blend_u8(unorm_mult4x4(src[i], tints[i]), dst[i]);
     }
 }
 
@@ -347,3 +382,263 @@ fn egui_blend_two_u16x4(src8: uint8x8_t, src16: uint16x8_t, dst16: uint16x8_t) -
     // dst.saturating_add(src)
     vqadd_u8(dst8, src8)
 }
+
+/// dst[i] = texels.sample_bilinear(uv_start + uv_step * i)
+#[target_feature(enable = "neon")]
+fn sample_bilinear_span(texels: ColorTexels, uv_start: Vec2, uv_step: Vec2, dst: &mut [[u8; 4]]) {
+    let n = dst.len();
+    let wrap_mode = texels.options.wrap_mode;
+    let nearest = texels.options.magnification == TextureFilter::Nearest;
+    let fsize = texels.fsize();
+    let width_extent = texels.width as i32 - 1;
+    let height_extent = texels.height as i32 - 1;
+
+    let lanes = unsafe { vld1q_f32([0.0, 1.0, 2.0, 3.0].as_ptr()) };
+    let half = vdupq_n_f32(0.5);
+    let tex_w = vdupq_n_f32(fsize.x);
+    let tex_h = vdupq_n_f32(fsize.y);
+    let neg_one = vdupq_n_f32(-1.0);
+    let x_extent_f = vdupq_n_f32(width_extent as f32);
+    let y_extent_f = vdupq_n_f32(height_extent as f32);
+    let x_extent = vdupq_n_s32(width_extent);
+    let y_extent = vdupq_n_s32(height_extent);
+    let zero = vdupq_n_s32(0);
+    let one = vdupq_n_s32(1);
+    let row_len = vdupq_n_s32(texels.width as i32);
+    let data = texels.data;
+
+    let gather = |idx: int32x4_t| -> uint32x4_t {
+        let words = [
+            u32::from_le_bytes(data[vgetq_lane_s32(idx, 0) as usize]),
+            u32::from_le_bytes(data[vgetq_lane_s32(idx, 1) as usize]),
+            u32::from_le_bytes(data[vgetq_lane_s32(idx, 2) as usize]),
+            u32::from_le_bytes(data[vgetq_lane_s32(idx, 3) as usize]),
+        ];
+        unsafe { vld1q_u32(words.as_ptr()) }
+    };
+
+    let mut i = 0;
+    while i + 3 < n {
+        let fi = vaddq_f32(vdupq_n_f32(i as f32), lanes);
+        let u = vaddq_f32(
+            vdupq_n_f32(uv_start.x),
+            vmulq_f32(fi, vdupq_n_f32(uv_step.x)),
+        );
+        let v = vaddq_f32(
+            vdupq_n_f32(uv_start.y),
+            vmulq_f32(fi, vdupq_n_f32(uv_step.y)),
+        );
+
+        let (u, v) = match wrap_mode {
+            TextureWrapMode::ClampToEdge => (u, v),
+            TextureWrapMode::Repeat => (x_repeat(u), x_repeat(v)),
+            TextureWrapMode::MirroredRepeat => (x_mirror(u), x_mirror(v)),
+        };
+
+        let sx = vsubq_f32(vmulq_f32(u, tex_w), half);
+        let sy = vsubq_f32(vmulq_f32(v, tex_h), half);
+        let sx0 = vrndmq_f32(sx);
+        let sy0 = vrndmq_f32(sy);
+
+        let (fx, fy) = if nearest {
+            (vdupq_n_f32(0.0), vdupq_n_f32(0.0))
+        } else {
+            (vsubq_f32(sx, sx0), vsubq_f32(sy, sy0))
+        };
+
+        // Limit before converting so that out of range (or NaN) uvs still result in in-bounds texel indices.
+        let x0 = vcvtq_s32_f32(vminnmq_f32(vmaxnmq_f32(sx0, neg_one), x_extent_f));
+        let y0 = vcvtq_s32_f32(vminnmq_f32(vmaxnmq_f32(sy0, neg_one), y_extent_f));
+        let x1 = vminq_s32(vaddq_s32(x0, one), x_extent);
+        let y1 = vminq_s32(vaddq_s32(y0, one), y_extent);
+        let x0 = vmaxq_s32(x0, zero);
+        let y0 = vmaxq_s32(y0, zero);
+
+        let taps = [
+            gather(vmlaq_s32(x0, y0, row_len)),
+            gather(vmlaq_s32(x0, y1, row_len)),
+            gather(vmlaq_s32(x1, y0, row_len)),
+            gather(vmlaq_s32(x1, y1, row_len)),
+        ];
+
+        let out = bilinear_filter_4(taps, fx, fy);
+
+        let dst_p = unsafe { dst.as_mut_ptr().add(i) }.cast::<u32>();
+        unsafe { vst1q_u32(dst_p, out) };
+        i += 4;
+    }
+
+    while i < n {
+        dst[i] = texels.sample_bilinear(uv_start + uv_step * i as f32);
+        i += 1;
+    }
+}
+
+/// dst[i] = vec4_to_u8x4(col_start + col_step * i)
+#[target_feature(enable = "neon")]
+fn color_gradient_span(col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+    let n = dst.len();
+    let start = unsafe { vld1q_f32(<[f32; 4]>::from(col_start).as_ptr()) };
+    let step = unsafe { vld1q_f32(<[f32; 4]>::from(col_step).as_ptr()) };
+    let zero = vdupq_n_f32(0.0);
+    let one = vdupq_n_f32(1.0);
+    let scale = vdupq_n_f32(255.0);
+    let half = vdupq_n_f32(0.5);
+
+    // One pixel worth of channels as u16
+    let color_u16 = |i: usize| -> uint16x4_t {
+        let c = vaddq_f32(start, vmulq_f32(step, vdupq_n_f32(i as f32)));
+        let c = vmaxnmq_f32(vminnmq_f32(c, one), zero);
+        vqmovun_s32(vcvtq_s32_f32(vaddq_f32(vmulq_f32(c, scale), half)))
+    };
+
+    let mut i = 0;
+    while i + 3 < n {
+        let c01 = vqmovn_u16(vcombine_u16(color_u16(i), color_u16(i + 1)));
+        let c23 = vqmovn_u16(vcombine_u16(color_u16(i + 2), color_u16(i + 3)));
+        let out = vcombine_u8(c01, c23);
+
+        let dst_p = unsafe { dst.as_mut_ptr().add(i) }.cast::<u8>();
+        unsafe { vst1q_u8(dst_p, out) };
+        i += 4;
+    }
+
+    while i < n {
+        dst[i] = vec4_to_u8x4(&(col_start + col_step * i as f32));
+        i += 1;
+    }
+}
+
+/// dst[i] = swizzle_rgba_bgra(src[i])
+#[target_feature(enable = "neon")]
+fn swizzle_rgba_bgra_slice(src: &[Color32], dst: &mut [[u8; 4]]) {
+    assert_eq!(src.len(), dst.len());
+
+    let n = dst.len();
+    let mut i = 0;
+    while i + 15 < n {
+        // Color32 is a repr(C) [u8; 4]. Deinterleave 16 pixels into r, g, b and a registers.
+        let src_p = unsafe { src.as_ptr().add(i) }.cast::<u8>();
+        let px = unsafe { vld4q_u8(src_p) };
+        let swizzled = uint8x16x4_t(px.2, px.1, px.0, px.3);
+
+        let dst_p = unsafe { dst.as_mut_ptr().add(i) }.cast::<u8>();
+        unsafe { vst4q_u8(dst_p, swizzled) };
+        i += 16;
+    }
+
+    while i < n {
+        dst[i] = swizzle_rgba_bgra(src[i].to_array());
+        i += 1;
+    }
+}
+
+/// dst[i] = coeffs.convert(y[i], u[i], v[i])
+#[target_feature(enable = "neon")]
+fn yuv_to_rgba_span(y: &[u8], u: &[u8], v: &[u8], coeffs: &YuvCoeffs, dst: &mut [[u8; 4]]) {
+    let n = dst.len();
+    assert!(y.len() >= n && u.len() >= n && v.len() >= n);
+
+    let y_offset = vdupq_n_s32(coeffs.y_offset);
+    let y_scale = vdupq_n_s32(coeffs.y_scale);
+    let round = vdupq_n_s32(1 << (YUV_FRAC_BITS - 1));
+    let chroma_offset = vdupq_n_s32(128);
+    let coeffs_u = coeffs.u.map(|c| vdupq_n_s32(c));
+    let coeffs_v = coeffs.v.map(|c| vdupq_n_s32(c));
+    let zero = vdupq_n_s32(0);
+    let max = vdupq_n_s32(255);
+    let alpha = vdupq_n_u32(0xFF00_0000);
+
+    // 8 bytes zero extended to two i32x4
+    let load = |p: &[u8], i: usize| {
+        let wide = vmovl_u8(unsafe { vld1_u8(p.as_ptr().add(i)) });
+        [vget_low_u16(wide), vget_high_u16(wide)].map(|half| vreinterpretq_s32_u32(vmovl_u16(half)))
+    };
+
+    let mut i = 0;
+    while i + 7 < n {
+        let [y, u, v] = [load(y, i), load(u, i), load(v, i)];
+        for half in 0..2 {
+            let y = vaddq_s32(vmulq_s32(vsubq_s32(y[half], y_offset), y_scale), round);
+            let u = vsubq_s32(u[half], chroma_offset);
+            let v = vsubq_s32(v[half], chroma_offset);
+            let channel = |c: usize| {
+                let x = vmlaq_s32(vmlaq_s32(y, u, coeffs_u[c]), v, coeffs_v[c]);
+                let x = vminq_s32(vmaxq_s32(vshrq_n_s32::<YUV_FRAC_BITS>(x), zero), max);
+                vreinterpretq_u32_s32(x)
+            };
+            let out = vorrq_u32(
+                vorrq_u32(channel(0), vshlq_n_u32::<8>(channel(1))),
+                vorrq_u32(vshlq_n_u32::<16>(channel(2)), alpha),
+            );
+
+            let dst_p = unsafe { dst.as_mut_ptr().add(i + 4 * half) }.cast::<u32>();
+            unsafe { vst1q_u32(dst_p, out) };
+        }
+        i += 8;
+    }
+
+    while i < n {
+        dst[i] = coeffs.convert(y[i], u[i], v[i]);
+        i += 1;
+    }
+}
+
+/// Blends 4 sets of four rgba8 texels (00, 01, 10, 11) using the given x and y bilinear factors.
+#[inline]
+#[target_feature(enable = "neon")]
+fn bilinear_filter_4(texels: [uint32x4_t; 4], fx: float32x4_t, fy: float32x4_t) -> uint32x4_t {
+    let one = vdupq_n_f32(1.0);
+    let gx = vsubq_f32(one, fx);
+    let gy = vsubq_f32(one, fy);
+    let weights = [
+        vmulq_f32(gx, gy),
+        vmulq_f32(gx, fy),
+        vmulq_f32(fx, gy),
+        vmulq_f32(fx, fy),
+    ];
+
+    let r = bilinear_channel_4(texels, weights, 0);
+    let g = bilinear_channel_4(texels, weights, 8);
+    let b = bilinear_channel_4(texels, weights, 16);
+    let a = bilinear_channel_4(texels, weights, 24);
+    vorrq_u32(vorrq_u32(r, g), vorrq_u32(b, a))
+}
+
+/// Weighted sum of the 8 bit channel at shift, returned in place at shift.
+/// Operations are ordered to match ColorTexels::sample_bilinear() exactly.
+#[inline]
+#[target_feature(enable = "neon")]
+fn bilinear_channel_4(
+    texels: [uint32x4_t; 4],
+    weights: [float32x4_t; 4],
+    shift: i32,
+) -> uint32x4_t {
+    let mask = vdupq_n_u32(0xFF);
+    let unorm = vdupq_n_f32(255.0);
+    let shift_right = vdupq_n_s32(-shift);
+    let mut sum = vdupq_n_f32(0.0);
+    for (texel, weight) in texels.into_iter().zip(weights) {
+        let c = vcvtq_f32_u32(vandq_u32(vshlq_u32(texel, shift_right), mask));
+        sum = vaddq_f32(sum, vmulq_f32(vdivq_f32(c, unorm), weight));
+    }
+    let sum = vminnmq_f32(vmaxnmq_f32(sum, vdupq_n_f32(0.0)), vdupq_n_f32(1.0));
+    let c = vcvtq_u32_f32(vaddq_f32(vmulq_f32(sum, unorm), vdupq_n_f32(0.5)));
+    vshlq_u32(c, vdupq_n_s32(shift))
+}
+
+/// x - floor(x), matches the repeat fn in plane_taps()
+#[inline]
+#[target_feature(enable = "neon")]
+fn x_repeat(x: float32x4_t) -> float32x4_t {
+    vsubq_f32(x, vrndmq_f32(x))
+}
+
+/// Matches the mirror fn in plane_taps()
+#[inline]
+#[target_feature(enable = "neon")]
+fn x_mirror(x: float32x4_t) -> float32x4_t {
+    let half = vdupq_n_f32(0.5);
+    let f = x_repeat(vaddq_f32(vmulq_f32(x, half), half));
+    vmulq_f32(vabsq_f32(vsubq_f32(f, half)), vdupq_n_f32(2.0))
+}
@@ -2,7 +2,12 @@
 
 use core::{arch::x86_64::*, ptr::read_unaligned};
 
-use crate::color::SelectedImpl;
+use egui::{Color32, TextureFilter, TextureWrapMode, Vec2};
+
+use crate::{
+    color::{ColorTexels, SelectedImpl, YUV_FRAC_BITS, YuvCoeffs, swizzle_rgba_bgra, vec4_to_u8x4},
+    math::vec4::Vec4,
+};
 
 #[derive(Clone, Copy)]
 pub struct Sse41Impl(());
@@ -32,13 +37,13 @@ impl SelectedImpl for Sse41Impl {
     }
 
     #[inline]
-    fn egui_blend_u8_slice_one_src_tinted_fn(
+    fn egui_blend_u8_slice_tinted_per_px(
         self,
-        src: [u8; 4],
-        tint_fn: impl FnMut() -> [u8; 4],
+        src: &[[u8; 4]],
+        tints: &[[u8; 4]],
         dst: &mut [[u8; 4]],
     ) {
-        unsafe { egui_blend_u8_slice_one_src_tinted_fn(src, tint_fn, dst) }
+        unsafe { egui_blend_u8_slice_tinted_per_px(src, tints, dst) }
     }
 
     #[inline]
@@ -60,6 +65,63 @@ impl SelectedImpl for Sse41Impl {
     fn unorm_mult4x4(self, a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
         unsafe { unorm_mult4x4(a, b) }
     }
+
+    #[inline]
+    fn sample_bilinear_span(
+        self,
+        texels: ColorTexels,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        unsafe { sample_bilinear_span(texels, uv_start, uv_step, dst) }
+    }
+
+    #[inline]
+    fn color_gradient_span(self, col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+        unsafe { color_gradient_span(col_start, col_step, dst) }
+    }
+
+    #[inline]
+    fn swizzle_rgba_bgra_slice(self, src: &[Color32], dst: &mut [[u8; 4]]) {
+        unsafe { swizzle_rgba_bgra_slice(src, dst) }
+    }
+
+    #[inline]
+    fn yuv_to_rgba_span(
+        self,
+        y: &[u8],
+        u: &[u8],
+        v: &[u8],
+        coeffs: &YuvCoeffs,
+        dst: &mut [[u8; 4]],
+    ) {
+        unsafe { yuv_to_rgba_span(y, u, v, coeffs, dst) }
+    }
+}
+
+/// dst[i] = swizzle_rgba_bgra(src[i])
+#[target_feature(enable = "sse4.1")]
+pub(crate) fn swizzle_rgba_bgra_slice(src: &[Color32], dst: &mut [[u8; 4]]) {
+    assert_eq!(src.len(), dst.len());
+
+    let n = dst.len();
+    let shuffle = _mm_setr_epi8(2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8, 11, 14, 13, 12, 15);
+
+    let mut i = 0;
+    while i + 3 < n {
+        // Color32 is a repr(C) [u8; 4]
+        let src_ptr = unsafe { src.as_ptr().add(i) }.cast::<__m128i>();
+        let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m128i>();
+        let px = unsafe { _mm_loadu_si128(src_ptr) };
+        unsafe { _mm_storeu_si128(dst_ptr, _mm_shuffle_epi8(px, shuffle)) };
+        i += 4;
+    }
+
+    while i < n {
+        dst[i] = swizzle_rgba_bgra(src[i].to_array());
+        i += 1;
+    }
 }
 
 /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
@@ -240,37 +302,33 @@ fn egui_blend_u8_slice_tinted(src: &[[u8; 4]], tint: [u8; 4], dst: &mut [[u8; 4]
 }
 
 // https://www.lgfae.com/posts/2025-09-01-AlphaBlendWithSIMD.html
-/// dst[i] = blend(src * tint_fn(), dst[i]) // As unorm
+/// dst[i] = blend(src[i] * tints[i], dst[i]) // As unorm
 /// blend fn is (ONE, ONE_MINUS_SRC_ALPHA)
 #[target_feature(enable = "sse4.1")]
-fn egui_blend_u8_slice_one_src_tinted_fn(
-    src: [u8; 4],
-    mut tint_fn: impl FnMut() -> [u8; 4],
-    dst: &mut [[u8; 4]],
-) {
+fn egui_blend_u8_slice_tinted_per_px(src: &[[u8; 4]], tints: &[[u8; 4]], dst: &mut [[u8; 4]]) {
+    assert_eq!(src.len(), dst.len());
+    assert_eq!(tints.len(), dst.len());
     let n = dst.len();
     if n == 0 {
         return;
     }
 
-    let src32 = u32::from_le_bytes(src);
-    let src64 = (src32 as u64) | ((src32 as u64) << 32);
-
     let e1 = _mm_set1_epi16(0x0080);
     let e2 = _mm_set1_epi16(0x0101);
 
     let mut i = 0usize;
     while i + 1 < n {
-        // Load two tint values
-        let tint_a = u32::from_le_bytes(tint_fn()) as i64;
-        let tint_b = u32::from_le_bytes(tint_fn()) as i64;
-        let tint_simd = _mm_cvtsi64_si128((tint_b << 32) | tint_a);
-        let tint16 = _mm_cvtepu8_epi16(tint_simd);
-
-        let src8 = _mm_cvtsi64_si128(src64 as i64);
+        // Load two src pixels
+        let src = unsafe { src.as_ptr().add(i) }.cast::<u64>();
+        let src8 = _mm_cvtsi64_si128(unsafe { read_unaligned(src) } as i64);
         // [0,0,0,0,rg,ba,rg,ba] -> [r,g,b,a,r,g,b,a]
         let src16 = _mm_cvtepu8_epi16(src8);
 
+        // Load two tint values
+        let tint = unsafe { tints.as_ptr().add(i) }.cast::<u64>();
+        let tint8 = _mm_cvtsi64_si128(unsafe { read_unaligned(tint) } as i64);
+        let tint16 = _mm_cvtepu8_epi16(tint8);
+
         // Load two dst pixels
         let dst = unsafe { dst.as_mut_ptr().add(i) }.cast::<u64>();
         let dst8 = _mm_cvtsi64_si128(unsafe { read_unaligned(dst) } as i64);
@@ -291,7 +349,7 @@ fn egui_blend_u8_slice_one_src_tinted_fn(
 
     // Tail: handle the last pixel (if any) in scalar
     if i < n {
-        dst[i] = egui_blend_u8(unorm_mult4x4(src, tint_fn()), dst[i]);
+        dst[i] = egui_blend_u8(unorm_mult4x4(src[i], tints[i]), dst[i]);
     }
 }
 
@@ -344,3 +402,238 @@ fn egui_blend_two_u16x4(src8: __m128i, src16: __m128i, dst16: __m128i) -> __m128
     // dst.saturating_add(src)
     _mm_adds_epu8(dst8, src8)
 }
+
+/// dst[i] = texels.sample_bilinear(uv_start + uv_step * i)
+#[target_feature(enable = "sse4.1")]
+fn sample_bilinear_span(texels: ColorTexels, uv_start: Vec2, uv_step: Vec2, dst: &mut [[u8; 4]]) {
+    let n = dst.len();
+    let wrap_mode = texels.options.wrap_mode;
+    let nearest = texels.options.magnification == TextureFilter::Nearest;
+    let fsize = texels.fsize();
+    let width_extent = texels.width as i32 - 1;
+    let height_extent = texels.height as i32 - 1;
+
+    let lanes = _mm_setr_ps(0.0, 1.0, 2.0, 3.0);
+    let half = _mm_set1_ps(0.5);
+    let tex_w = _mm_set1_ps(fsize.x);
+    let tex_h = _mm_set1_ps(fsize.y);
+    let neg_one = _mm_set1_ps(-1.0);
+    let x_extent_f = _mm_set1_ps(width_extent as f32);
+    let y_extent_f = _mm_set1_ps(height_extent as f32);
+    let x_extent = _mm_set1_epi32(width_extent);
+    let y_extent = _mm_set1_epi32(height_extent);
+    let zero = _mm_setzero_si128();
+    let one = _mm_set1_epi32(1);
+    let row_len = _mm_set1_epi32(texels.width as i32);
+    let data = texels.data;
+
+    let gather = |idx: __m128i| -> __m128i {
+        _mm_setr_epi32(
+            i32::from_le_bytes(data[_mm_extract_epi32(idx, 0) as usize]),
+            i32::from_le_bytes(data[_mm_extract_epi32(idx, 1) as usize]),
+            i32::from_le_bytes(data[_mm_extract_epi32(idx, 2) as usize]),
+            i32::from_le_bytes(data[_mm_extract_epi32(idx, 3) as usize]),
+        )
+    };
+
+    let mut i = 0;
+    while i + 3 < n {
+        let fi = _mm_add_ps(_mm_set1_ps(i as f32), lanes);
+        let u = _mm_add_ps(
+            _mm_set1_ps(uv_start.x),
+            _mm_mul_ps(fi, _mm_set1_ps(uv_step.x)),
+        );
+        let v = _mm_add_ps(
+            _mm_set1_ps(uv_start.y),
+            _mm_mul_ps(fi, _mm_set1_ps(uv_step.y)),
+        );
+
+        let (u, v) = match wrap_mode {
+            TextureWrapMode::ClampToEdge => (u, v),
+            TextureWrapMode::Repeat => (x_repeat(u), x_repeat(v)),
+            TextureWrapMode::MirroredRepeat => (x_mirror(u), x_mirror(v)),
+        };
+
+        let sx = _mm_sub_ps(_mm_mul_ps(u, tex_w), half);
+        let sy = _mm_sub_ps(_mm_mul_ps(v, tex_h), half);
+        let sx0 = _mm_floor_ps(sx);
+        let sy0 = _mm_floor_ps(sy);
+
+        let (fx, fy) = if nearest {
+            (_mm_setzero_ps(), _mm_setzero_ps())
+        } else {
+            (_mm_sub_ps(sx, sx0), _mm_sub_ps(sy, sy0))
+        };
+
+        // Limit before converting so that out of range (or NaN) uvs still result in in-bounds texel indices.
+        let x0 = _mm_cvttps_epi32(_mm_min_ps(_mm_max_ps(sx0, neg_one), x_extent_f));
+        let y0 = _mm_cvttps_epi32(_mm_min_ps(_mm_max_ps(sy0, neg_one), y_extent_f));
+        let x1 = _mm_min_epi32(_mm_add_epi32(x0, one), x_extent);
+        let y1 = _mm_min_epi32(_mm_add_epi32(y0, one), y_extent);
+        let x0 = _mm_max_epi32(x0, zero);
+        let y0 = _mm_max_epi32(y0, zero);
+
+        let row0 = _mm_mullo_epi32(y0, row_len);
+        let row1 = _mm_mullo_epi32(y1, row_len);
+
+        let taps = [
+            gather(_mm_add_epi32(row0, x0)),
+            gather(_mm_add_epi32(row1, x0)),
+            gather(_mm_add_epi32(row0, x1)),
+            gather(_mm_add_epi32(row1, x1)),
+        ];
+
+        let out = bilinear_filter_4(taps, fx, fy);
+
+        let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m128i>();
+        unsafe { _mm_storeu_si128(dst_ptr, out) };
+        i += 4;
+    }
+
+    while i < n {
+        dst[i] = texels.sample_bilinear(uv_start + uv_step * i as f32);
+        i += 1;
+    }
+}
+
+/// dst[i] = vec4_to_u8x4(col_start + col_step * i)
+#[target_feature(enable = "sse4.1")]
+fn color_gradient_span(col_start: Vec4, col_step: Vec4, dst: &mut [[u8; 4]]) {
+    let n = dst.len();
+    let start = _mm_setr_ps(col_start.x, col_start.y, col_start.z, col_start.w);
+    let step = _mm_setr_ps(col_step.x, col_step.y, col_step.z, col_step.w);
+    let zero = _mm_setzero_ps();
+    let one = _mm_set1_ps(1.0);
+    let scale = _mm_set1_ps(255.0);
+    let half = _mm_set1_ps(0.5);
+
+    // One pixel worth of channels as i32
+    let color_i32 = |i: usize| -> __m128i {
+        let c = _mm_add_ps(start, _mm_mul_ps(step, _mm_set1_ps(i as f32)));
+        let c = _mm_max_ps(_mm_min_ps(c, one), zero);
+        _mm_cvttps_epi32(_mm_add_ps(_mm_mul_ps(c, scale), half))
+    };
+
+    let mut i = 0;
+    while i + 3 < n {
+        let c01 = _mm_packs_epi32(color_i32(i), color_i32(i + 1));
+        let c23 = _mm_packs_epi32(color_i32(i + 2), color_i32(i + 3));
+        let out = _mm_packus_epi16(c01, c23);
+
+        let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m128i>();
+        unsafe { _mm_storeu_si128(dst_ptr, out) };
+        i += 4;
+    }
+
+    while i < n {
+        dst[i] = vec4_to_u8x4(&(col_start + col_step * i as f32));
+        i += 1;
+    }
+}
+
+/// dst[i] = coeffs.convert(y[i], u[i], v[i])
+#[target_feature(enable = "sse4.1")]
+fn yuv_to_rgba_span(y: &[u8], u: &[u8], v: &[u8], coeffs: &YuvCoeffs, dst: &mut [[u8; 4]]) {
+    let n = dst.len();
+    assert!(y.len() >= n && u.len() >= n && v.len() >= n);
+
+    let y_offset = _mm_set1_epi32(coeffs.y_offset);
+    let y_scale = _mm_set1_epi32(coeffs.y_scale);
+    let round = _mm_set1_epi32(1 << (YUV_FRAC_BITS - 1));
+    let chroma_offset = _mm_set1_epi32(128);
+    let coeffs_u = coeffs.u.map(|c| _mm_set1_epi32(c));
+    let coeffs_v = coeffs.v.map(|c| _mm_set1_epi32(c));
+    let zero = _mm_setzero_si128();
+    let max = _mm_set1_epi32(255);
+    let alpha = _mm_set1_epi32(0xFF00_0000_u32 as i32);
+
+    // 4 bytes zero extended to i32
+    let load = |p: &[u8], i: usize| {
+        let bytes = i32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]);
+        _mm_cvtepu8_epi32(_mm_cvtsi32_si128(bytes))
+    };
+
+    let mut i = 0;
+    while i + 3 < n {
+        let y = _mm_mullo_epi32(_mm_sub_epi32(load(y, i), y_offset), y_scale);
+        let y = _mm_add_epi32(y, round);
+        let u = _mm_sub_epi32(load(u, i), chroma_offset);
+        let v = _mm_sub_epi32(load(v, i), chroma_offset);
+        let channel = |c: usize| {
+            let x = _mm_add_epi32(
+                _mm_add_epi32(y, _mm_mullo_epi32(u, coeffs_u[c])),
+                _mm_mullo_epi32(v, coeffs_v[c]),
+            );
+            _mm_min_epi32(_mm_max_epi32(_mm_srai_epi32::<YUV_FRAC_BITS>(x), zero), max)
+        };
+        let out = _mm_or_si128(
+            _mm_or_si128(channel(0), _mm_slli_epi32::<8>(channel(1))),
+            _mm_or_si128(_mm_slli_epi32::<16>(channel(2)), alpha),
+        );
+
+        let dst_ptr = unsafe { dst.as_mut_ptr().add(i) }.cast::<__m128i>();
+        unsafe { _mm_storeu_si128(dst_ptr, out) };
+        i += 4;
+    }
+
+    while i < n {
+        dst[i] = coeffs.convert(y[i], u[i], v[i]);
+        i += 1;
+    }
+}
+
+/// Blends 4 sets of four rgba8 texels (00, 01, 10, 11) using the given x and y bilinear factors.
+#[inline]
+#[target_feature(enable = "sse4.1")]
+fn bilinear_filter_4(texels: [__m128i; 4], fx: __m128, fy: __m128) -> __m128i {
+    let one = _mm_set1_ps(1.0);
+    let gx = _mm_sub_ps(one, fx);
+    let gy = _mm_sub_ps(one, fy);
+    let weights = [
+        _mm_mul_ps(gx, gy),
+        _mm_mul_ps(gx, fy),
+        _mm_mul_ps(fx, gy),
+        _mm_mul_ps(fx, fy),
+    ];
+
+    let r = bilinear_channel_4::<0>(texels, weights);
+    let g = bilinear_channel_4::<8>(texels, weights);
+    let b = bilinear_channel_4::<16>(texels, weights);
+    let a = bilinear_channel_4::<24>(texels, weights);
+    _mm_or_si128(_mm_or_si128(r, g), _mm_or_si128(b, a))
+}
+
+/// Weighted sum of the 8 bit channel at SHIFT, returned in place at SHIFT.
+/// Operations are ordered to match ColorTexels::sample_bilinear() exactly.
+#[inline]
+#[target_feature(enable = "sse4.1")]
+fn bilinear_channel_4<const SHIFT: i32>(texels: [__m128i; 4], weights: [__m128; 4]) -> __m128i {
+    let mask = _mm_set1_epi32(0xFF);
+    let unorm = _mm_set1_ps(255.0);
+    let mut sum = _mm_setzero_ps();
+    for (texel, weight) in texels.into_iter().zip(weights) {
+        let c = _mm_cvtepi32_ps(_mm_and_si128(_mm_srli_epi32::<SHIFT>(texel), mask));
+        sum = _mm_add_ps(sum, _mm_mul_ps(_mm_div_ps(c, unorm), weight));
+    }
+    let sum = _mm_min_ps(_mm_max_ps(sum, _mm_setzero_ps()), _mm_set1_ps(1.0));
+    let c = _mm_cvttps_epi32(_mm_add_ps(_mm_mul_ps(sum, unorm), _mm_set1_ps(0.5)));
+    _mm_slli_epi32::<SHIFT>(c)
+}
+
+/// x - floor(x), matches the repeat fn in plane_taps()
+#[inline]
+#[target_feature(enable = "sse4.1")]
+fn x_repeat(x: __m128) -> __m128 {
+    _mm_sub_ps(x, _mm_floor_ps(x))
+}
+
+/// Matches the mirror fn in plane_taps()
+#[inline]
+#[target_feature(enable = "sse4.1")]
+fn x_mirror(x: __m128) -> __m128 {
+    let half = _mm_set1_ps(0.5);
+    let f = _mm_sub_ps(x_repeat(_mm_add_ps(_mm_mul_ps(x, half), half)), half);
+    // abs(f)
+    let f = _mm_andnot_ps(_mm_set1_ps(-0.0), f);
+    _mm_mul_ps(f, _mm_set1_ps(2.0))
+}
@@ -1,13 +1,208 @@
-use alloc::vec::Vec;
+use alloc::{sync::Arc, vec, vec::Vec};
 use egui::{Color32, TextureFilter, TextureOptions, Vec2, vec2};
 
 use crate::{
     ColorFieldOrder,
-    color::{swizzle_rgba_bgra, u8x4_to_vec4, vec4_to_u8x4},
+    color::{
+        AvailableImpl, BilinearTaps, ColorTexels, SelectedImpl, YuvCoeffs, plane_taps,
+        u8x4_to_vec4, vec4_to_u8x4,
+    },
+    math::vec4::{Vec4, vec4},
+    raster::span::SAMPLE_CHUNK,
 };
 
+/// How an `EguiTexture` stores its texels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TexelFormat {
+    /// `[u8; 4]` per texel in `EguiTexture::data`, swizzled to the output field order.
+    Color,
+    /// White coverage masks like the font atlas, where every texel is `[a, a, a, a]`. Only `a` is stored, per texel
+    /// in `EguiTexture::alpha`.
+    Alpha,
+    /// Opaque video frames stored as the planes of `EguiTexture::yuv`, converted to color as they're sampled.
+    Yuv,
+}
+
+/// A planar YUV 4:2:0 format of decoded video frames, see `EguiSoftwareRender::register_yuv_texture()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct YuvFormat {
+    pub layout: YuvLayout,
+    pub matrix: YuvMatrix,
+    pub range: YuvRange,
+}
+
+impl YuvFormat {
+    /// Bytes of a frame of the given size, a Y plane and two chroma planes of half the width and height rounded up.
+    /// The same for every layout.
+    pub fn buffer_len(&self, size: [usize; 2]) -> usize {
+        let [chroma_width, chroma_height] = chroma_size(size);
+        size[0] * size[1] + 2 * chroma_width * chroma_height
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum YuvLayout {
+    /// A Y plane followed by one plane of interleaved U, V pairs
+    #[default]
+    Nv12,
+    /// A Y plane followed by a U plane, then a V plane
+    I420,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601, standard definition video
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, HD video
+    Bt709,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum YuvRange {
+    /// Y in 16..=235 and U, V in 16..=240, used by most video
+    #[default]
+    Limited,
+    /// Y, U and V in 0..=255, as in JPEG
+    Full,
+}
+
+/// Planes of a `TexelFormat::Yuv` texture.
+#[derive(Default)]
+pub struct YuvPlanes {
+    pub format: YuvFormat,
+    pub coeffs: YuvCoeffs,
+    /// The Y plane followed by the chroma planes as laid out by `format.layout`
+    pub data: Vec<u8>,
+    /// Size of the chroma planes, half the texture size rounded up
+    pub chroma_size: [usize; 2],
+}
+
+impl YuvPlanes {
+    /// U and V of the chroma texel at `idx`
+    #[inline(always)]
+    pub fn chroma(&self, idx: usize) -> [u8; 2] {
+        let luma_len = self.data.len() - 2 * self.chroma_size[0] * self.chroma_size[1];
+        match self.format.layout {
+            YuvLayout::Nv12 => {
+                let i = luma_len + idx * 2;
+                [self.data[i], self.data[i + 1]]
+            }
+            YuvLayout::I420 => {
+                let i = luma_len + idx;
+                let v_plane = self.chroma_size[0] * self.chroma_size[1];
+                [self.data[i], self.data[i + v_plane]]
+            }
+        }
+    }
+}
+
+fn chroma_size(size: [usize; 2]) -> [usize; 2] {
+    [size[0].div_ceil(2), size[1].div_ceil(2)]
+}
+
+/// Higher quality resampling of a texture, used instead of its `TextureOptions` filter when it's drawn on an axis aligned
+/// rect at a different size than its native one. Set per texture with `EguiSoftwareRender::set_extended_sampling()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ExtendedSampling {
+    /// Filter used when the texture is drawn smaller than its native size
+    pub minification: MinificationFilter,
+    /// Filter used when the texture is drawn larger than its native size
+    pub magnification: MagnificationFilter,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MinificationFilter {
+    /// The texture's `TextureOptions` filter
+    #[default]
+    Texture,
+    /// Area average of the texels under each pixel
+    Box,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MagnificationFilter {
+    /// The texture's `TextureOptions` filter
+    #[default]
+    Texture,
+    /// Catmull-Rom bicubic, 4x4 texels per pixel
+    Bicubic,
+    /// Lanczos with 3 lobes, 6x6 texels per pixel. Sharper than bicubic, with more ringing.
+    Lanczos3,
+}
+
+/// How a rect with extended sampling is sampled.
+#[derive(Clone, Copy)]
+pub enum Resample {
+    /// Box filter over a footprint, in texels
+    Box(Vec2),
+    Kernel(MagnificationFilter),
+}
+
+impl ExtendedSampling {
+    /// The resampling to use for a rect whose pixels step `texels_per_px` texels, None if the texture's filter is used.
+    pub fn resample(&self, texels_per_px: Vec2) -> Option<Resample> {
+        let eps = 0.01;
+        if texels_per_px.x > 1.0 + eps || texels_per_px.y > 1.0 + eps {
+            (self.minification == MinificationFilter::Box).then_some(Resample::Box(texels_per_px))
+        } else if texels_per_px.x < 1.0 - eps || texels_per_px.y < 1.0 - eps {
+            (self.magnification != MagnificationFilter::Texture)
+                .then_some(Resample::Kernel(self.magnification))
+        } else {
+            None
+        }
+    }
+}
+
+/// A texture computed on the fly instead of uploaded, for patterns that are cheaper as a function than as texels like
+/// heatmaps, transparency checkerboards or animated noise. Registered for an `egui::TextureId::User` with
+/// `EguiSoftwareRender::register_procedural_texture()`.
+///
+/// Primitives using it are rasterized again every frame, so it may return different colors over time.
+pub trait ProceduralTexture: Send + Sync {
+    /// Premultiplied RGBA color at `uv`. The uvs of the mesh are passed as is, they aren't wrapped or filtered.
+    fn sample(&self, uv: Vec2) -> [u8; 4];
+
+    /// `out[i] = self.sample(uv_start + uv_step * i)`. Can be implemented to compute a span faster than per sample.
+    fn fill_span(&self, uv_start: Vec2, uv_step: Vec2, out: &mut [[u8; 4]]) {
+        for (i, out) in out.iter_mut().enumerate() {
+            *out = self.sample(uv_start + uv_step * i as f32);
+        }
+    }
+}
+
+/// A `ProceduralTexture` along with the output field order its samples are swizzled to.
+pub struct Procedural {
+    pub texture: Arc<dyn ProceduralTexture>,
+    pub field_order: ColorFieldOrder,
+}
+
+impl Procedural {
+    #[inline(always)]
+    pub fn sample(&self, uv: Vec2) -> [u8; 4] {
+        let [r, g, b, a] = self.texture.sample(uv);
+        match self.field_order {
+            ColorFieldOrder::Rgba => [r, g, b, a],
+            ColorFieldOrder::Bgra => [b, g, r, a],
+        }
+    }
+
+    pub fn fill_span(&self, uv_start: Vec2, uv_step: Vec2, dst: &mut [[u8; 4]]) {
+        self.texture.fill_span(uv_start, uv_step, dst);
+        if self.field_order == ColorFieldOrder::Bgra {
+            for pixel in dst.iter_mut() {
+                pixel.swap(0, 2);
+            }
+        }
+    }
+}
+
 pub struct EguiTexture {
+    /// Texels of `TexelFormat::Color` textures, empty otherwise.
     pub data: Vec<[u8; 4]>,
+    /// Texels of `TexelFormat::Alpha` textures, empty otherwise.
+    pub alpha: Vec<u8>,
+    pub format: TexelFormat,
     // Common case: The default egui texture has the top-left corner pixel fully white.
     // https://github.com/emilk/egui/blob/c97c065a575ec6e657bb42872890a00d0fb391c1/crates/epaint/src/lib.rs#L92
     pub uv_zero_val: [u8; 4],
@@ -16,36 +211,292 @@ pub struct EguiTexture {
     /// height - 1
     pub height_extent: i32,
     pub width: usize,
-    #[allow(dead_code)]
     pub height: usize,
     pub fsize: Vec2,
     pub options: TextureOptions,
+    pub sampling: ExtendedSampling,
+    /// Set for procedural textures, which have no texels and are sampled from it instead.
+    pub procedural: Option<Procedural>,
+    /// Planes of `TexelFormat::Yuv` textures, empty otherwise.
+    pub yuv: YuvPlanes,
+    /// Incremented every time the texels are streamed in with `TextureStore::texture_mut()` or
+    /// `TextureStore::swap_texture_buffer()`. Cached primitives drawn with an older generation are rasterized again.
+    pub generation: u64,
 }
 
+/// Min pixels of a texture upload for its rows to be copied in parallel.
+#[cfg(feature = "rayon")]
+const PARALLEL_UPLOAD_MIN_PX: usize = 256 * 256;
+
 impl EguiTexture {
+    /// # Arguments
+    /// * `alpha_textures` - Store the texture as `TexelFormat::Alpha` if all its pixels are grayscale coverage.
     pub fn new(
+        simd_impl: AvailableImpl,
         field_order: ColorFieldOrder,
         options: TextureOptions,
         size: [usize; 2],
         pixels: &[Color32],
+        alpha_textures: bool,
+    ) -> EguiTexture {
+        let mut texture = EguiTexture {
+            data: Vec::new(),
+            alpha: Vec::new(),
+            format: TexelFormat::Color,
+            width_extent: size[0] as i32 - 1,
+            height_extent: size[1] as i32 - 1,
+            width: size[0],
+            height: size[1],
+            fsize: vec2(size[0] as f32, size[1] as f32),
+            options,
+            sampling: Default::default(),
+            procedural: None,
+            generation: 0,
+            yuv: Default::default(),
+            uv_zero_val: [0; 4],
+        };
+        if alpha_textures && is_coverage(pixels) {
+            texture.format = TexelFormat::Alpha;
+            texture.alpha = vec![0; size[0] * size[1]];
+            upload_alpha_rows(pixels, size[0], &mut texture.alpha, size[0]);
+        } else {
+            texture.data = vec![[0; 4]; size[0] * size[1]];
+            upload_rows(
+                simd_impl,
+                field_order,
+                pixels,
+                size[0],
+                &mut texture.data,
+                size[0],
+            );
+        }
+        texture.uv_zero_val = texture.texel(0);
+        texture
+    }
+
+    /// A texture without texels, sampled from `texture` instead. Its size is nominally 1x1 texel.
+    pub fn new_procedural(
+        field_order: ColorFieldOrder,
+        texture: Arc<dyn ProceduralTexture>,
     ) -> EguiTexture {
-        let data = pixels
-            .iter()
-            .map(|p| match field_order {
-                ColorFieldOrder::Rgba => p.to_array(),
-                ColorFieldOrder::Bgra => swizzle_rgba_bgra(p.to_array()),
-            })
-            .collect::<Vec<_>>();
-        let uv_zero_val = data[0];
         EguiTexture {
-            data,
+            data: Vec::new(),
+            alpha: Vec::new(),
+            format: TexelFormat::Color,
+            uv_zero_val: [0; 4],
+            width_extent: 0,
+            height_extent: 0,
+            width: 1,
+            height: 1,
+            fsize: Vec2::ONE,
+            options: TextureOptions::LINEAR,
+            sampling: Default::default(),
+            procedural: Some(Procedural {
+                texture,
+                field_order,
+            }),
+            generation: 0,
+            yuv: Default::default(),
+        }
+    }
+
+    /// An opaque black YUV texture, for streaming frames into.
+    pub fn new_yuv(
+        field_order: ColorFieldOrder,
+        options: TextureOptions,
+        size: [usize; 2],
+        format: YuvFormat,
+    ) -> EguiTexture {
+        let chroma_size = chroma_size(size);
+        let mut data = vec![0; format.buffer_len(size)];
+        // Neutral chroma
+        data[size[0] * size[1]..].fill(128);
+        if format.range == YuvRange::Limited {
+            data[..size[0] * size[1]].fill(16);
+        }
+        let mut texture = EguiTexture {
+            data: Vec::new(),
+            alpha: Vec::new(),
+            format: TexelFormat::Yuv,
+            uv_zero_val: [0; 4],
             width_extent: size[0] as i32 - 1,
             height_extent: size[1] as i32 - 1,
             width: size[0],
             height: size[1],
             fsize: vec2(size[0] as f32, size[1] as f32),
             options,
-            uv_zero_val,
+            sampling: Default::default(),
+            procedural: None,
+            generation: 0,
+            yuv: YuvPlanes {
+                format,
+                coeffs: YuvCoeffs::new(format.matrix, format.range, field_order),
+                data,
+                chroma_size,
+            },
+        };
+        texture.uv_zero_val = texture.texel(0);
+        texture
+    }
+
+    /// A transparent color texture, for streaming texels into.
+    pub fn new_streaming(options: TextureOptions, size: [usize; 2]) -> EguiTexture {
+        EguiTexture {
+            data: vec![[0; 4]; size[0] * size[1]],
+            alpha: Vec::new(),
+            format: TexelFormat::Color,
+            uv_zero_val: [0; 4],
+            width_extent: size[0] as i32 - 1,
+            height_extent: size[1] as i32 - 1,
+            width: size[0],
+            height: size[1],
+            fsize: vec2(size[0] as f32, size[1] as f32),
+            options,
+            sampling: Default::default(),
+            procedural: None,
+            generation: 0,
+            yuv: Default::default(),
+        }
+    }
+
+    /// Writes `pixels` of the given size into the texture with their top left at `pos`. An alpha texture is converted
+    /// to a color one if the new pixels aren't grayscale coverage, a YUV texture always is.
+    pub fn update(
+        &mut self,
+        simd_impl: AvailableImpl,
+        field_order: ColorFieldOrder,
+        pos: [usize; 2],
+        size: [usize; 2],
+        pixels: &[Color32],
+    ) {
+        assert!(pos[0] + size[0] <= self.width && pos[1] + size[1] <= self.height);
+        let start = pos[0] + pos[1] * self.width;
+        let end = start + size[1].saturating_sub(1) * self.width + size[0];
+
+        if self.format == TexelFormat::Yuv
+            || (self.format == TexelFormat::Alpha && !is_coverage(pixels))
+        {
+            self.convert_to_color();
+        }
+
+        if self.format == TexelFormat::Alpha {
+            upload_alpha_rows(pixels, size[0], &mut self.alpha[start..end], self.width);
+        } else {
+            upload_rows(
+                simd_impl,
+                field_order,
+                pixels,
+                size[0],
+                &mut self.data[start..end],
+                self.width,
+            );
+        }
+        self.uv_zero_val = self.texel(0);
+    }
+
+    /// Expands the texels of an alpha or YUV texture to `TexelFormat::Color`.
+    pub fn convert_to_color(&mut self) {
+        match self.format {
+            TexelFormat::Color => return,
+            TexelFormat::Alpha => self.data = self.alpha.iter().map(|&a| [a; 4]).collect(),
+            TexelFormat::Yuv => {
+                self.data = (0..self.width * self.height)
+                    .map(|idx| self.texel(idx))
+                    .collect()
+            }
+        }
+        self.alpha = Vec::new();
+        self.yuv = Default::default();
+        self.format = TexelFormat::Color;
+    }
+
+    /// The texel at the given index, expanded to `[a, a, a, a]` for alpha textures.
+    #[inline(always)]
+    pub fn texel(&self, idx: usize) -> [u8; 4] {
+        match self.format {
+            TexelFormat::Color => self.data[idx],
+            TexelFormat::Alpha => [self.alpha[idx]; 4],
+            TexelFormat::Yuv => {
+                let [y, u, v] =
+                    self.sample_yuv(self.texel_center(idx % self.width, idx / self.width));
+                self.yuv.coeffs.convert(y, u, v)
+            }
+        }
+    }
+
+    /// uv of the center of the texel at x, y
+    #[inline(always)]
+    fn texel_center(&self, x: usize, y: usize) -> Vec2 {
+        vec2(x as f32 + 0.5, y as f32 + 0.5) / self.fsize
+    }
+
+    /// Converts the texels [x, x + dst.len()) of row y of a `TexelFormat::Yuv` texture, equal to `texel()`. Chroma is
+    /// still filtered, as when the texture is sampled, so drawing it 1:1 doesn't shift colors.
+    pub fn yuv_row_span(
+        &self,
+        simd_impl: impl SelectedImpl,
+        x: usize,
+        y: usize,
+        dst: &mut [[u8; 4]],
+    ) {
+        let uv_step = vec2(1.0 / self.fsize.x, 0.0);
+        self.sample_yuv_span(simd_impl, self.texel_center(x, y), uv_step, dst);
+    }
+
+    /// Y, U and V of a `TexelFormat::Yuv` texture at `uv`, filtered according to the texture options. The chroma planes
+    /// are sampled at their own resolution, except with nearest filtering, which takes the chroma texel of the luma
+    /// texel's 2x2 block. The chroma plane's own nearest tap, the top left one of a bilinear sample, can be the block
+    /// before.
+    pub fn sample_yuv(&self, uv: Vec2) -> [u8; 3] {
+        let luma = plane_taps(&self.options, [self.width, self.height], uv);
+        if self.options.magnification == TextureFilter::Nearest {
+            let (x, y) = (luma.idx[0] % self.width, luma.idx[0] / self.width);
+            let [u, v] = self.yuv.chroma(y / 2 * self.yuv.chroma_size[0] + x / 2);
+            return [self.yuv.data[luma.idx[0]], u, v];
+        }
+        let chroma = plane_taps(&self.options, self.yuv.chroma_size, uv);
+        if luma.nearest && chroma.nearest {
+            let [u, v] = self.yuv.chroma(chroma.idx[0]);
+            return [self.yuv.data[luma.idx[0]], u, v];
+        }
+
+        #[inline(always)]
+        fn filter(taps: &BilinearTaps, texel: impl Fn(usize) -> u8) -> u8 {
+            let [t00, t10, t01, t11] = taps.idx.map(|idx| texel(idx) as f32);
+            let (fx, fy) = (taps.fx, taps.fy);
+            let top = t00 + (t10 - t00) * fx;
+            let bottom = t01 + (t11 - t01) * fx;
+            (top + (bottom - top) * fy + 0.5) as u8
+        }
+
+        [
+            filter(&luma, |idx| self.yuv.data[idx]),
+            filter(&chroma, |idx| self.yuv.chroma(idx)[0]),
+            filter(&chroma, |idx| self.yuv.chroma(idx)[1]),
+        ]
+    }
+
+    /// dst[i] = sample_bilinear(uv_start + uv_step * i) of a `TexelFormat::Yuv` texture. Samples are filtered in YUV,
+    /// then converted together.
+    pub fn sample_yuv_span(
+        &self,
+        simd_impl: impl SelectedImpl,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        let mut planes = [[0u8; SAMPLE_CHUNK]; 3];
+        for (chunk_idx, dst) in dst.chunks_mut(SAMPLE_CHUNK).enumerate() {
+            let first = chunk_idx * SAMPLE_CHUNK;
+            let [y, u, v] = &mut planes;
+            let samples = y.iter_mut().zip(u.iter_mut()).zip(v.iter_mut());
+            for (i, ((y, u), v)) in samples.take(dst.len()).enumerate() {
+                let uv = uv_start + uv_step * (first + i) as f32;
+                [*y, *u, *v] = self.sample_yuv(uv);
+            }
+            let [y, u, v] = &planes;
+            let n = dst.len();
+            simd_impl.yuv_to_rgba_span(&y[..n], &u[..n], &v[..n], &self.yuv.coeffs, dst);
         }
     }
 
@@ -55,65 +506,324 @@ impl EguiTexture {
         let ss_y = ((uv.y * self.fsize.y) as i32)
             .max(0)
             .min(self.height_extent);
-        self.data[ss_x as usize + ss_y as usize * self.width]
+        self.texel(ss_x as usize + ss_y as usize * self.width)
+    }
+
+    /// Maps an unbounded texel coordinate along an axis of the given size into 0..size according to the wrap mode.
+    #[inline(always)]
+    pub fn wrap_texel(&self, t: i64, size: usize) -> usize {
+        let size = size as i64;
+        match self.options.wrap_mode {
+            egui::TextureWrapMode::ClampToEdge => t.clamp(0, size - 1) as usize,
+            egui::TextureWrapMode::Repeat => t.rem_euclid(size) as usize,
+            egui::TextureWrapMode::MirroredRepeat => {
+                let t = t.rem_euclid(size * 2);
+                if t < size {
+                    t as usize
+                } else {
+                    (size * 2 - 1 - t) as usize
+                }
+            }
+        }
     }
 
     pub fn sample_bilinear(&self, uv: Vec2) -> [u8; 4] {
+        if let Some(procedural) = &self.procedural {
+            return procedural.sample(uv);
+        }
+        match self.format {
+            TexelFormat::Color => return self.color_texels().sample_bilinear(uv),
+            TexelFormat::Alpha => (),
+            TexelFormat::Yuv => {
+                let [y, u, v] = self.sample_yuv(uv);
+                return self.yuv.coeffs.convert(y, u, v);
+            }
+        }
         if uv == Vec2::ZERO {
             return self.uv_zero_val;
         }
 
-        let w = self.fsize.x;
-        let h = self.fsize.y;
+        let taps = self.bilinear_taps(uv);
+        let c00 = self.texel(taps.idx[0]);
 
-        #[inline(always)]
-        fn mirror(v: f32) -> f32 {
-            ((v * 0.5 + 0.5).fract() - 0.5) * 2.0
+        if taps.nearest {
+            return c00;
         }
 
-        let uv = match self.options.wrap_mode {
-            egui::TextureWrapMode::ClampToEdge => uv,
-            egui::TextureWrapMode::Repeat => vec2(uv.x.fract(), uv.y.fract()),
-            egui::TextureWrapMode::MirroredRepeat => vec2(mirror(uv.x), mirror(uv.y)),
-        };
+        let c10 = self.texel(taps.idx[1]);
+        let c01 = self.texel(taps.idx[2]);
+        let c11 = self.texel(taps.idx[3]);
 
-        let sx = uv.x * w - 0.5;
-        let sy = uv.y * h - 0.5;
+        let v00 = u8x4_to_vec4(&c00);
+        let v10 = u8x4_to_vec4(&c10);
+        let v01 = u8x4_to_vec4(&c01);
+        let v11 = u8x4_to_vec4(&c11);
 
-        let x0 = sx.floor() as i32;
-        let y0 = sy.floor() as i32;
-        let x1 = x0 + 1;
-        let y1 = y0 + 1;
+        let (fx, fy) = (taps.fx, taps.fy);
+        let w00 = (1.0 - fx) * (1.0 - fy);
+        let w10 = fx * (1.0 - fy);
+        let w01 = (1.0 - fx) * fy;
+        let w11 = fx * fy;
 
-        let fx = sx - x0 as f32;
-        let fy = sy - y0 as f32;
+        vec4_to_u8x4(&(v00 * w00 + v01 * w01 + v10 * w10 + v11 * w11))
+    }
 
-        let x0c = x0.max(0).min(self.width_extent);
-        let y0c = y0.max(0).min(self.height_extent);
-        let x1c = x1.max(0).min(self.width_extent);
-        let y1c = y1.max(0).min(self.height_extent);
+    /// dst[i] = sample_bilinear(uv_start + uv_step * i). Resolves the kind of texture here, so the SIMD kernels only
+    /// ever sample color texels.
+    pub fn sample_span(
+        &self,
+        simd_impl: impl SelectedImpl,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        if let Some(procedural) = &self.procedural {
+            return procedural.fill_span(uv_start, uv_step, dst);
+        }
+        match self.format {
+            TexelFormat::Color => {
+                simd_impl.sample_bilinear_span(self.color_texels(), uv_start, uv_step, dst)
+            }
+            TexelFormat::Alpha => {
+                for (i, pixel) in dst.iter_mut().enumerate() {
+                    *pixel = self.sample_bilinear(uv_start + uv_step * i as f32);
+                }
+            }
+            TexelFormat::Yuv => self.sample_yuv_span(simd_impl, uv_start, uv_step, dst),
+        }
+    }
 
-        let c00 = self.data[(x0c as usize) + (y0c as usize) * self.width];
+    /// Texels of a `TexelFormat::Color` texture for the color kernels
+    #[inline(always)]
+    pub fn color_texels(&self) -> ColorTexels<'_> {
+        debug_assert_eq!(self.format, TexelFormat::Color);
+        ColorTexels {
+            data: &self.data,
+            width: self.width,
+            height: self.height,
+            options: self.options,
+        }
+    }
 
-        if self.options.magnification == TextureFilter::Nearest || (fx == 0.0 && fy == 0.0) {
-            // if these are 0 the px at 0,0 will have full influence. Equivalent to nearest sampling.
-            return c00;
+    /// Alpha of `sample_bilinear()` for `TexelFormat::Alpha` textures, only filtering the one channel.
+    pub fn sample_coverage(&self, uv: Vec2) -> u8 {
+        debug_assert_eq!(self.format, TexelFormat::Alpha);
+        if uv == Vec2::ZERO {
+            return self.uv_zero_val[3];
         }
 
-        let c10 = self.data[(x1c as usize) + (y0c as usize) * self.width];
-        let c01 = self.data[(x0c as usize) + (y1c as usize) * self.width];
-        let c11 = self.data[(x1c as usize) + (y1c as usize) * self.width];
+        let taps = self.bilinear_taps(uv);
+        let a00 = self.alpha[taps.idx[0]];
 
-        let v00 = u8x4_to_vec4(&c00);
-        let v10 = u8x4_to_vec4(&c10);
-        let v01 = u8x4_to_vec4(&c01);
-        let v11 = u8x4_to_vec4(&c11);
+        if taps.nearest {
+            return a00;
+        }
 
+        let [a10, a01, a11] = [1, 2, 3].map(|i| self.alpha[taps.idx[i]] as f32 / 255.0);
+        let a00 = a00 as f32 / 255.0;
+
+        let (fx, fy) = (taps.fx, taps.fy);
         let w00 = (1.0 - fx) * (1.0 - fy);
         let w10 = fx * (1.0 - fy);
         let w01 = (1.0 - fx) * fy;
         let w11 = fx * fy;
 
-        vec4_to_u8x4(&(v00 * w00 + v01 * w01 + v10 * w10 + v11 * w11))
+        // Same operation order as the vec4 math of sample_bilinear()
+        let a = a00 * w00 + a01 * w01 + a10 * w10 + a11 * w11;
+        (a.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+    }
+
+    /// dst[i] = sample of `resample` at uv_start + uv_step * i
+    pub fn sample_extended_span(
+        &self,
+        resample: Resample,
+        uv_start: Vec2,
+        uv_step: Vec2,
+        dst: &mut [[u8; 4]],
+    ) {
+        for (i, dst) in dst.iter_mut().enumerate() {
+            let uv = uv_start + uv_step * i as f32;
+            *dst = match resample {
+                Resample::Box(footprint) => self.sample_box(uv, footprint),
+                Resample::Kernel(filter) => self.sample_kernel(filter, uv),
+            };
+        }
+    }
+
+    /// Area average of the texels under a pixel centered at `uv` that covers `footprint` texels. Axes of the footprint
+    /// smaller than a texel are widened to one, which interpolates linearly along them.
+    pub fn sample_box(&self, uv: Vec2, footprint: Vec2) -> [u8; 4] {
+        let center = uv * self.fsize;
+        let half = footprint.max(Vec2::ONE) * 0.5;
+        let [min, max] = [center - half, center + half];
+
+        let mut sum = Vec4::ZERO;
+        let mut total = 0.0;
+        for y in min.y.floor() as i64..max.y.ceil() as i64 {
+            let wy = max.y.min(y as f32 + 1.0) - min.y.max(y as f32);
+            let row = self.wrap_texel(y, self.height) * self.width;
+            for x in min.x.floor() as i64..max.x.ceil() as i64 {
+                let w = wy * (max.x.min(x as f32 + 1.0) - min.x.max(x as f32));
+                sum += u8x4_to_vec4(&self.texel(row + self.wrap_texel(x, self.width))) * w;
+                total += w;
+            }
+        }
+        vec4_to_u8x4(&(sum / total))
     }
+
+    /// Separable sample of the `filter` kernel centered at `uv`.
+    pub fn sample_kernel(&self, filter: MagnificationFilter, uv: Vec2) -> [u8; 4] {
+        let (radius, kernel): (usize, fn(f32) -> f32) = match filter {
+            MagnificationFilter::Texture => return self.sample_bilinear(uv),
+            MagnificationFilter::Bicubic => (2, catmull_rom),
+            MagnificationFilter::Lanczos3 => (3, lanczos3),
+        };
+
+        let pos = uv * self.fsize - vec2(0.5, 0.5);
+        let base = pos.floor();
+        let first = vec2(base.x - (radius - 1) as f32, base.y - (radius - 1) as f32);
+        let mut weights = [[0.0f32; 6]; 2];
+        for (axis, weights) in weights.iter_mut().enumerate() {
+            for (i, weight) in weights[..radius * 2].iter_mut().enumerate() {
+                *weight = kernel(first[axis] + i as f32 - pos[axis]);
+            }
+        }
+
+        let mut sum = Vec4::ZERO;
+        let mut total = 0.0;
+        for (y, wy) in (first.y as i64..).zip(&weights[1][..radius * 2]) {
+            let row = self.wrap_texel(y, self.height) * self.width;
+            for (x, wx) in (first.x as i64..).zip(&weights[0][..radius * 2]) {
+                let w = wx * wy;
+                sum += u8x4_to_vec4(&self.texel(row + self.wrap_texel(x, self.width))) * w;
+                total += w;
+            }
+        }
+
+        // Negative lobes can overshoot, keep the result a valid premultiplied color
+        let v = (sum / total).clamp(Vec4::ZERO, Vec4::ONE);
+        let a = v.w;
+        vec4_to_u8x4(&vec4(v.x.min(a), v.y.min(a), v.z.min(a), a))
+    }
+
+    #[inline(always)]
+    fn bilinear_taps(&self, uv: Vec2) -> BilinearTaps {
+        plane_taps(&self.options, [self.width, self.height], uv)
+    }
+}
+
+/// Catmull-Rom cubic, a = -0.5
+fn catmull_rom(x: f32) -> f32 {
+    let x = x.abs();
+    if x < 1.0 {
+        (1.5 * x - 2.5) * x * x + 1.0
+    } else if x < 2.0 {
+        ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+    } else {
+        0.0
+    }
+}
+
+fn lanczos3(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else if x.abs() < 3.0 {
+        let pi_x = core::f32::consts::PI * x;
+        3.0 * sin_pi(x) * sin_pi(x / 3.0) / (pi_x * pi_x)
+    } else {
+        0.0
+    }
+}
+
+/// sin(pi * x), core has no trig functions without std
+fn sin_pi(x: f32) -> f32 {
+    // Reduce to [-1, 1], then to [-0.5, 0.5] with sin(pi * x) = sin(pi * (1 - x))
+    let x = x - 2.0 * (x * 0.5).round();
+    let x = if x > 0.5 {
+        1.0 - x
+    } else if x < -0.5 {
+        -1.0 - x
+    } else {
+        x
+    };
+    let t = core::f32::consts::PI * x;
+    let t2 = t * t;
+    // Taylor series to t^9, error below 1e-6 on [-pi / 2, pi / 2]
+    t * (1.0 - t2 / 6.0 * (1.0 - t2 / 20.0 * (1.0 - t2 / 42.0 * (1.0 - t2 / 72.0))))
+}
+
+/// True if every pixel is premultiplied white with some coverage, like the font atlas.
+fn is_coverage(pixels: &[Color32]) -> bool {
+    pixels
+        .iter()
+        .all(|p| p.r() == p.a() && p.g() == p.a() && p.b() == p.a())
+}
+
+/// Copies rows of `width` pixels into `dst` rows that start every `dst_stride` pixels, swizzled to `field_order`.
+fn upload_rows(
+    simd_impl: AvailableImpl,
+    field_order: ColorFieldOrder,
+    src: &[Color32],
+    width: usize,
+    dst: &mut [[u8; 4]],
+    dst_stride: usize,
+) {
+    for_each_row(
+        src,
+        width,
+        dst,
+        dst_stride,
+        |src_row, dst_row| match field_order {
+            ColorFieldOrder::Rgba => {
+                // No swizzle, the compiler vectorizes this into a copy
+                for (pixel, src) in dst_row.iter_mut().zip(src_row) {
+                    *pixel = src.to_array();
+                }
+            }
+            ColorFieldOrder::Bgra => crate::dispatch_simd_impl!(simd_impl, |simd_impl| {
+                simd_impl.swizzle_rgba_bgra_slice(src_row, dst_row)
+            }),
+        },
+    );
+}
+
+/// Copies the alpha of rows of `width` pixels into `dst` rows that start every `dst_stride` texels.
+fn upload_alpha_rows(src: &[Color32], width: usize, dst: &mut [u8], dst_stride: usize) {
+    for_each_row(src, width, dst, dst_stride, |src_row, dst_row| {
+        for (texel, src) in dst_row.iter_mut().zip(src_row) {
+            *texel = src.a();
+        }
+    });
+}
+
+/// Runs `f` on each row of `width` pixels of `src` along with the matching `dst` row, rows of which start every
+/// `dst_stride` texels. Large uploads are split into rows processed in parallel.
+fn for_each_row<T: Send>(
+    src: &[Color32],
+    width: usize,
+    dst: &mut [T],
+    dst_stride: usize,
+    f: impl Fn(&[Color32], &mut [T]) + Sync,
+) {
+    if width == 0 {
+        return;
+    }
+
+    let row = |(src_row, dst_row): (&[Color32], &mut [T])| f(src_row, &mut dst_row[..width]);
+
+    #[cfg(feature = "rayon")]
+    if src.len() >= PARALLEL_UPLOAD_MIN_PX {
+        use rayon::{
+            iter::{IndexedParallelIterator, ParallelIterator},
+            slice::{ParallelSlice, ParallelSliceMut},
+        };
+        src.par_chunks(width)
+            .zip(dst.par_chunks_mut(dst_stride))
+            .for_each(row);
+        return;
+    }
+
+    src.chunks(width)
+        .zip(dst.chunks_mut(dst_stride))
+        .for_each(row);
 }
@@ -37,3 +37,44 @@ fn hash32(x: u32) -> u32 {
     x = x ^ (x >> 16);
     x
 }
+
+/// 64 bit variant of `Hash32`. Values are also rotated into the state so the order of the values hashed between calls
+/// to `fnv_wrap()` matters.
+pub struct Hash64(pub u64);
+
+impl Hash64 {
+    #[inline(always)]
+    pub fn new_fnv() -> Self {
+        Hash64(0xcbf29ce484222325) // FNV offset basis
+    }
+
+    #[inline(always)]
+    pub fn hash_wrap(&mut self, v: u64) {
+        self.hash(v);
+        self.fnv_wrap();
+    }
+
+    #[inline(always)]
+    pub fn hash(&mut self, v: u64) {
+        self.0 = self.0.rotate_left(23) ^ hash64(v);
+    }
+
+    #[inline(always)]
+    pub fn fnv_wrap(&mut self) {
+        self.0 = self.0.wrapping_mul(0x00000100000001b3); // FNV prime
+    }
+
+    pub fn finalize(&self) -> u64 {
+        hash64(self.0)
+    }
+}
+
+#[inline(always)]
+fn hash64(x: u64) -> u64 {
+    // splitmix64 finalizer
+    let mut x = x ^ (x >> 30);
+    x = x.wrapping_mul(0xbf58476d1ce4e5b9);
+    x = x ^ (x >> 27);
+    x = x.wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
@@ -76,19 +76,27 @@ extern crate std;
 
 use core::ops::Range;
 
-use alloc::{borrow::Cow, vec, vec::Vec};
+use alloc::{sync::Arc, vec::Vec};
 
 use egui::{Color32, Mesh, Pos2, Vec2, vec2};
 
 use ahash::HashMap;
 
+#[cfg(feature = "rayon")]
+use crate::render::{BinScratch, MeshDrawCmds, draw_binned, egui_mesh_draw_cmds};
 #[cfg(feature = "raster_stats")]
 use crate::stats::RasterStats;
 use crate::{
     color::{AvailableImpl, SelectedImpl, swizzle_rgba_bgra},
     egui_texture::EguiTexture,
-    hash::Hash32,
-    render::{draw_egui_mesh, egui_orient2df},
+    hash::{Hash32, Hash64},
+    math::i64vec2::{I64Vec2, i64vec2},
+    render::{EdgeScratch, Scissor, draw_egui_mesh, draw_egui_mesh_scissored, egui_orient2df},
+    scroll::{
+        Scroll, ScrollScratch, TriKey, bounds_around, contains, find_scroll, intersect, is_empty,
+        translate, tri_keys,
+    },
+    texture_store::Textures,
 };
 
 pub(crate) mod color;
@@ -97,14 +105,26 @@ pub(crate) mod hash;
 pub(crate) mod math;
 pub(crate) mod raster;
 pub(crate) mod render;
+pub(crate) mod scroll;
 #[cfg(feature = "raster_stats")]
 pub mod stats;
 #[cfg(feature = "test_render")]
 pub mod test_render;
+pub(crate) mod texture_store;
+#[cfg(feature = "std")]
+mod threaded;
 
 #[cfg(feature = "winit")]
 mod winit;
 
+pub use egui_texture::{
+    ExtendedSampling, MagnificationFilter, MinificationFilter, ProceduralTexture, YuvFormat,
+    YuvLayout, YuvMatrix, YuvRange,
+};
+pub use texture_store::{SharedTextureStore, TextureStore};
+#[cfg(feature = "std")]
+pub use threaded::ThreadedEguiSoftwareRender;
+
 #[cfg(feature = "winit")]
 pub use winit::{
     App, SoftwareBackend, SoftwareBackendAppConfiguration, run_app_with_software_backend,
@@ -131,9 +151,16 @@ pub(crate) fn neon() -> bool {
 
 const TILE_SIZE: usize = 64;
 
+/// Transparent gaps of up to this many pixels between runs in `CachedPrimitive::row_spans` are merged into the runs
+const SPAN_MERGE_GAP: usize = 16;
+
+/// Cached primitives with at least this many pixels are rasterized with `draw_binned()`, one tile per task.
+#[cfg(feature = "rayon")]
+const BINNED_RASTER_MIN_PX: usize = TILE_SIZE * TILE_SIZE * 4;
+
 /// Used to define the color swizzle order. Some backends require Rgba and others require Bgra. The renderer swizzles
 /// textures as they are loaded so they can later be rasterized directly onto the frame buffer.
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
 pub enum ColorFieldOrder {
     #[default]
     Rgba,
@@ -142,18 +169,37 @@ pub enum ColorFieldOrder {
 
 /// Software render backend for egui.
 pub struct EguiSoftwareRender {
-    textures: HashMap<egui::TextureId, EguiTexture>,
-    cached_primitives: HashMap<u32, CachedPrimitive>,
+    textures: Textures,
+    cached_primitives: PrimCache,
+    /// pixels_per_point that `cached_primitives` were rasterized at
+    cache_pixels_per_point: f32,
+    /// Cache generation of the previous pixels_per_point, kept if `keep_previous_scale_cache` is set
+    previous_scale_cache: Option<(f32, PrimCache)>,
+    keep_previous_scale_cache: bool,
     tiles_dim: [usize; 2],
     dirty_tiles: Vec<u8>,
+    /// Number of frames rendered to the canvas
+    frames_composited: u64,
+    /// Per tile, the number of the frame it was last composited in
+    tile_frames: Vec<u64>,
     target_size: Vec2,
     prims_updated_this_frame: usize,
     output_field_order: ColorFieldOrder,
     canvas: Canvas,
     redraw_everything_this_frame: bool,
     convert_tris_to_rects: bool,
+    lcd_text: bool,
+    coverage_aa: bool,
+    #[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+    binned_raster: bool,
     allow_raster_opt: bool,
     cacheing_enabled: bool,
+    scroll_detection: bool,
+    cache_verification: bool,
+    /// Applied to primitive cache keys, tests clear bits to force keys to collide
+    #[cfg(feature = "test_render")]
+    cache_key_mask: u64,
+    scratch: FrameScratch,
     simd_impl: AvailableImpl,
     #[cfg(feature = "raster_stats")]
     pub stats: RasterStats,
@@ -165,18 +211,31 @@ impl EguiSoftwareRender {
     ///   output buffer order.
     pub fn new(output_field_order: ColorFieldOrder) -> Self {
         EguiSoftwareRender {
-            textures: Default::default(),
+            textures: Textures::Owned(TextureStore::new(output_field_order)),
             cached_primitives: Default::default(),
+            cache_pixels_per_point: Default::default(),
+            previous_scale_cache: Default::default(),
+            keep_previous_scale_cache: false,
             tiles_dim: Default::default(),
             dirty_tiles: Default::default(),
+            frames_composited: 0,
+            tile_frames: Default::default(),
             target_size: Default::default(),
             prims_updated_this_frame: Default::default(),
             output_field_order,
             canvas: Default::default(),
             redraw_everything_this_frame: Default::default(),
             convert_tris_to_rects: true,
+            lcd_text: false,
+            coverage_aa: false,
+            binned_raster: true,
             allow_raster_opt: true,
             cacheing_enabled: true,
+            scroll_detection: false,
+            cache_verification: cfg!(debug_assertions),
+            #[cfg(feature = "test_render")]
+            cache_key_mask: u64::MAX,
+            scratch: Default::default(),
             simd_impl: Default::default(),
             #[cfg(feature = "raster_stats")]
             stats: Default::default(),
@@ -190,6 +249,34 @@ impl EguiSoftwareRender {
         self
     }
 
+    /// If true: text is drawn with subpixel anti-aliasing for LCD monitors, which sharpens it on low DPI displays. The
+    /// font atlas is sampled at 3x horizontal resolution and filtered, assuming the common horizontal RGB subpixel
+    /// layout. Subpixel AA is only applied over opaque pixels, elsewhere text falls back to grayscale AA. When caching,
+    /// primitives are rasterized over a transparent background, so only text drawn in the same primitive as its
+    /// opaque background gets subpixel AA. Applies to glyphs that line up 1:1 with pixels and requires
+    /// `allow_raster_opt` and `convert_tris_to_rects`.
+    pub fn with_lcd_text(mut self, set: bool) -> Self {
+        self.lcd_text = set;
+        self
+    }
+
+    /// If true: the outline of each mesh is anti-aliased by the fraction of each pixel it covers, sampled on a 4x4 grid.
+    /// egui already feathers the shapes it tessellates, so this is meant for content drawn without feathering
+    /// (`TessellationOptions::feathering` set to false) and for custom `Mesh`es. Edges shared by two triangles of a mesh
+    /// are drawn without AA so they don't leave seams. Rects that don't line up with pixels are drawn as triangles.
+    pub fn with_coverage_aa(mut self, set: bool) -> Self {
+        self.coverage_aa = set;
+        self
+    }
+
+    /// If true (default): with the `rayon` feature, meshes rendered without caching and large cached primitives are
+    ///   binned into tiles that are rasterized in parallel. If false they're rasterized one mesh after the other like
+    ///   without `rayon`, which produces the same pixels. Has no effect without the `rayon` feature.
+    pub fn with_binned_raster(mut self, set: bool) -> Self {
+        self.binned_raster = set;
+        self
+    }
+
     /// If false: Rasterize everything with triangles, always calculate vertex colors, uvs, use bilinear
     ///   everywhere, etc... Things *should* look the same with this set to `true` while rendering faster.
     pub fn with_allow_raster_opt(mut self, set: bool) -> Self {
@@ -205,10 +292,160 @@ impl EguiSoftwareRender {
         self
     }
 
+    /// If true: when caching, a ClippedPrimitive whose mesh was moved by whole pixels since the last frame (like the
+    /// contents of a scrolled ScrollArea) reuses the still visible pixels of the previous frame's primitive and only
+    /// rasterizes the newly exposed part. The canvas tiles it covers are shifted in place rather than composited
+    /// again, unless other primitives that changed or aren't a single color there overlap them.
+    ///
+    /// Off by default because of a known limitation: at a pixels_per_point of 1.0 the output is identical to
+    /// rasterizing every frame, but at fractional pixels_per_point (like 1.0833 or 1.5) scrolled vertices only move by
+    /// whole pixels up to float precision. They can then snap to other subpixels than the reused pixels were
+    /// rasterized at, so color channels along edges may differ by 1 from a full redraw. Requiring exact subpixel
+    /// matches would disable scroll reuse at these scales entirely.
+    pub fn with_scroll_detection(mut self, set: bool) -> Self {
+        self.scroll_detection = set;
+        self
+    }
+
+    /// If true: cached primitives also store a fingerprint of their mesh that's independent from their 64 bit cache
+    /// key. A cache hit whose fingerprint doesn't match is a key collision, the primitive is rasterized and cached
+    /// under another key instead of drawing the wrong pixels. This also holds when the colliding primitives are drawn
+    /// in the same frame. Defaults to true in debug builds.
+    pub fn with_cache_verification(mut self, set: bool) -> Self {
+        self.cache_verification = set;
+        self
+    }
+
+    /// If true: when pixels_per_point changes (like when zooming with Ctrl+/-), the cached primitives of the previous
+    /// pixels_per_point are kept as a second cache generation instead of being dropped. Switching back to that
+    /// pixels_per_point then reuses them. Uses up to twice the memory for cached primitives.
+    pub fn with_keep_previous_scale_cache(mut self, set: bool) -> Self {
+        self.keep_previous_scale_cache = set;
+        if !set {
+            self.previous_scale_cache = None;
+        }
+        self
+    }
+
+    /// If true (default): textures that are only grayscale coverage, like the font atlas, are stored with a single
+    /// `u8` alpha channel and drawn with glyph specific paths. See `TextureStore::with_alpha_textures()`.
+    pub fn with_alpha_textures(mut self, set: bool) -> Self {
+        self.textures.write().set_alpha_textures(set);
+        self
+    }
+
+    /// Use a texture store shared with other renderers instead of this renderer's own, so textures are only held once
+    /// in memory. The textures already loaded into this renderer are dropped. The store must use the same
+    /// `ColorFieldOrder` as this renderer, and all renderers sharing it must render the same egui context since
+    /// textures are looked up by `egui::TextureId`. The renderer loads the textures of the deltas it's given but doesn't
+    /// free any, call `TextureStore::free_textures()` once every renderer has rendered the frame.
+    pub fn with_texture_store(mut self, store: SharedTextureStore) -> Self {
+        self.textures = Textures::Shared(store);
+        assert!(
+            self.output_field_order == self.textures.read().output_field_order(),
+            "TextureStore and EguiSoftwareRender ColorFieldOrder don't match"
+        );
+        self
+    }
+
+    /// Draws the texture `id` with higher quality filters when it's shown on an axis aligned rect at a different size
+    /// than its native one, like a large image shown much smaller. Can be set before the texture is loaded. When it
+    /// changes, only the cached primitives drawn with the texture are rasterized again. Set on the `TextureStore`, so
+    /// it applies to every renderer sharing it. See `TextureStore::set_extended_sampling()`.
+    pub fn set_extended_sampling(&mut self, id: egui::TextureId, sampling: ExtendedSampling) {
+        self.textures.write().set_extended_sampling(id, sampling);
+    }
+
+    /// Adds a texture that's computed by `texture` as it's drawn instead of uploaded, see `ProceduralTexture`.
+    /// Returns the `egui::TextureId::User` to draw it with. With a shared `TextureStore`, register it on the store.
+    pub fn register_procedural_texture(
+        &mut self,
+        texture: Arc<dyn ProceduralTexture>,
+    ) -> egui::TextureId {
+        self.textures.write().register_procedural_texture(texture)
+    }
+
+    /// Replaces the `ProceduralTexture` of a texture from `EguiSoftwareRender::register_procedural_texture()`.
+    pub fn replace_procedural_texture(
+        &mut self,
+        id: egui::TextureId,
+        texture: Arc<dyn ProceduralTexture>,
+    ) {
+        self.textures
+            .write()
+            .replace_procedural_texture(id, texture);
+    }
+
+    /// Removes a texture from `EguiSoftwareRender::register_procedural_texture()`,
+    /// `EguiSoftwareRender::register_streaming_texture()` or `EguiSoftwareRender::register_yuv_texture()`.
+    pub fn free_user_texture(&mut self, id: egui::TextureId) {
+        self.textures.write().free_user_texture(id);
+    }
+
+    /// Adds a transparent texture to stream frames into, e.g. from a video, without going through egui. Returns the
+    /// `egui::TextureId::User` to draw it with. See `TextureStore::register_streaming_texture()`.
+    pub fn register_streaming_texture(
+        &mut self,
+        size: [usize; 2],
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        self.textures
+            .write()
+            .register_streaming_texture(size, options)
+    }
+
+    /// The texels of a loaded texture, to write a new frame of it directly without a `TexturesDelta`. Texels are
+    /// premultiplied and in the output field order. Only the cached primitives drawn with this texture are rasterized
+    /// again. None if the texture isn't loaded or is procedural, or if this renderer uses a shared `TextureStore`
+    /// whose `TextureStore::texture_mut()` can be used instead.
+    pub fn texture_mut(&mut self, id: egui::TextureId) -> Option<BufferMutRef<'_>> {
+        match &mut self.textures {
+            Textures::Owned(store) => store.texture_mut(id),
+            Textures::Shared(_) => None,
+        }
+    }
+
+    /// Swaps the texels of a loaded texture with a buffer of the same size, leaving the previous texels in `buffer`.
+    /// See `TextureStore::swap_texture_buffer()`.
+    pub fn swap_texture_buffer(&mut self, id: egui::TextureId, buffer: &mut Vec<[u8; 4]>) -> bool {
+        self.textures.write().swap_texture_buffer(id, buffer)
+    }
+
+    /// Adds a texture holding video frames in a planar YUV format, converted to color as it's drawn. Returns the
+    /// `egui::TextureId::User` to draw it with. See `TextureStore::register_yuv_texture()`.
+    pub fn register_yuv_texture(
+        &mut self,
+        size: [usize; 2],
+        format: YuvFormat,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        self.textures
+            .write()
+            .register_yuv_texture(size, format, options)
+    }
+
+    /// The planes of a YUV texture, to write a new frame of it directly. None if the texture isn't YUV, or if this
+    /// renderer uses a shared `TextureStore` whose `TextureStore::yuv_texture_mut()` can be used instead.
+    pub fn yuv_texture_mut(&mut self, id: egui::TextureId) -> Option<&mut [u8]> {
+        match &mut self.textures {
+            Textures::Owned(store) => store.yuv_texture_mut(id),
+            Textures::Shared(_) => None,
+        }
+    }
+
+    /// Swaps the planes of a YUV texture with a buffer of the same size, leaving the previous frame in `buffer`. See
+    /// `TextureStore::swap_yuv_buffer()`.
+    pub fn swap_yuv_buffer(&mut self, id: egui::TextureId, buffer: &mut Vec<u8>) -> bool {
+        self.textures.write().swap_yuv_buffer(id, buffer)
+    }
+
     /// Renders the given paint jobs to buffer_ref. Alternatively, when using caching
     /// EguiSoftwareRender::render_to_canvas() and subsequently EguiSoftwareRender::blit_canvas_to_buffer() can be run
     /// separately so that the primary rendering in render_to_canvas() can happen without a lock on the frame buffer.
     ///
+    /// With caching, rendering doesn't allocate once the UI's primitives and textures settled, their buffers are kept
+    /// between frames. This includes rasterizing primitives that changed, with `rayon` also in parallel. Rayon's
+    /// thread pool may still allocate in its job queues.
     ///
     /// # Arguments
     /// * `paint_jobs` - List of `egui::ClippedPrimitive` from egui to be rendered.
@@ -261,27 +498,34 @@ impl EguiSoftwareRender {
         assert!(pixels_per_point > 0.0);
 
         self.redraw_everything_this_frame = self.canvas.resize(width, height);
+        self.target_size = vec2(width as f32, height as f32);
+        self.tiles_dim = [width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE)];
 
-        if self.redraw_everything_this_frame {
-            self.canvas.clear();
-            self.cached_primitives.clear();
+        if pixels_per_point != self.cache_pixels_per_point {
+            self.swap_scale_cache(pixels_per_point);
         }
 
-        for (_hash, prim) in self.cached_primitives.iter_mut() {
+        for prim in self.cached_primitives.values_mut() {
             prim.seen_this_frame = false;
+            prim.scroll_shift = false;
+            // Cached pixels are relative to the primitive so they're kept across resizes (a restored cache generation
+            // may also be from another size), only the tiles they occupy change. Every tile of the canvas is
+            // composited again.
+            if self.redraw_everything_this_frame {
+                prim.update_occupied_tiles(width, height);
+            }
         }
 
-        self.target_size = vec2(width as f32, height as f32);
-        self.tiles_dim = [width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE)];
-
         self.set_textures(textures_delta);
 
         self.render_prims_to_cache(paint_jobs, pixels_per_point);
 
         self.update_dirty_tiles();
+        self.shift_scrolled_tiles();
+        let stale_tiles = self.mark_stale_tiles();
         self.clear_unused_cached_prims();
 
-        let mut reinit_canvas = self.redraw_everything_this_frame;
+        let mut reinit_canvas = self.redraw_everything_this_frame || stale_tiles;
 
         if self.prims_updated_this_frame > 0 {
             // TODO use tiles
@@ -315,82 +559,8 @@ impl EguiSoftwareRender {
             return;
         }
 
-        let width = self.canvas.width;
-        let height = self.canvas.height;
-        assert_eq!(self.canvas.data.len(), width * height);
-        assert_eq!(buffer.data.len(), width * height);
-
-        let tiles_x = self.tiles_dim[0];
-
-        #[cfg(feature = "rayon")]
-        {
-            use rayon::{
-                iter::{IndexedParallelIterator, ParallelIterator},
-                slice::ParallelSliceMut,
-            };
-            // blit rows of tiles in parallel
-
-            let width = buffer.width;
-            let px_per_row_of_tiles = width * TILE_SIZE;
-
-            buffer
-                .data
-                .par_chunks_mut(px_per_row_of_tiles)
-                .enumerate()
-                .for_each(|(tile_row, tile_height_row)| {
-                    let height = tile_height_row.len() / width; // Might be less than TILE_SIZE
-                    let buffer_tile_row = &mut BufferMutRef::new(tile_height_row, width, height);
-
-                    for (tile_idx, &mask) in self.dirty_tiles.iter().enumerate() {
-                        if mask & Self::OCCUPIED_TILE_MASK == 0 {
-                            continue;
-                        }
-
-                        let tile_y = tile_idx / tiles_x;
-                        if tile_y != tile_row {
-                            continue;
-                        }
-
-                        let tile_x = tile_idx % tiles_x;
-
-                        let x_start = tile_x * TILE_SIZE;
-                        let y_start = 0;
-                        let x_end = (x_start + TILE_SIZE).min(width);
-                        let y_end = TILE_SIZE.min(height);
-
-                        let canvas_row_offset = tile_row * TILE_SIZE;
-
-                        dispatch_simd_impl!(self.simd_impl, |simd_impl| self.blit_tile(
-                            simd_impl,
-                            buffer_tile_row,
-                            x_start,
-                            y_start,
-                            x_end,
-                            y_end,
-                            canvas_row_offset,
-                        ));
-                    }
-                });
-        }
-        #[cfg(not(feature = "rayon"))]
-        {
-            for (tile_idx, &mask) in self.dirty_tiles.iter().enumerate() {
-                if mask & Self::OCCUPIED_TILE_MASK == 0 {
-                    continue;
-                }
-
-                let tile_x = tile_idx % tiles_x;
-                let tile_y = tile_idx / tiles_x;
-
-                let x_start = tile_x * TILE_SIZE;
-                let y_start = tile_y * TILE_SIZE;
-                let x_end = (x_start + TILE_SIZE).min(width);
-                let y_end = (y_start + TILE_SIZE).min(height);
-
-                dispatch_simd_impl!(self.simd_impl, |simd_impl| self
-                    .blit_tile(simd_impl, buffer, x_start, y_start, x_end, y_end, 0));
-            }
-        }
+        self.canvas
+            .blit_tiles(self.simd_impl, &self.dirty_tiles, self.tiles_dim[0], buffer);
 
         #[cfg(feature = "raster_stats")]
         {
@@ -398,24 +568,6 @@ impl EguiSoftwareRender {
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn blit_tile(
-        &self,
-        simd_impl: impl SelectedImpl,
-        buffer: &mut BufferMutRef,
-        x_start: usize,
-        y_start: usize,
-        x_end: usize,
-        y_end: usize,
-        canvas_row_offset: usize,
-    ) {
-        for y in y_start..y_end {
-            let src_row = self.canvas.get_span(x_start, x_end, y + canvas_row_offset);
-            let dst_row = &mut buffer.get_mut_span(x_start, x_end, y);
-            simd_impl.egui_blend_u8_slice(src_row, dst_row);
-        }
-    }
-
     /// Render directly into buffer without cache. This is much slower and mainly intended for testing.
     fn render_direct(
         &mut self,
@@ -428,6 +580,8 @@ impl EguiSoftwareRender {
         self.stats.clear();
 
         self.set_textures(textures_delta);
+        let store = self.textures.read();
+        let textures = &store.textures;
 
         self.target_size = vec2(
             direct_draw_buffer.width as f32,
@@ -437,86 +591,188 @@ impl EguiSoftwareRender {
         #[cfg(feature = "raster_stats")]
         let start = std::time::Instant::now();
 
-        for egui::ClippedPrimitive {
-            clip_rect,
-            primitive,
-        } in paint_jobs.iter()
-        {
-            let input_mesh = match primitive {
-                egui::epaint::Primitive::Mesh(input_mesh) => input_mesh,
-                egui::epaint::Primitive::Callback(_) => {
-                    #[cfg(feature = "log")]
-                    log::error!("egui::epaint::Primitive::Callback(PaintCallback) not supported");
-                    continue;
-                }
-            };
+        #[cfg(feature = "rayon")]
+        let binned = self.binned_raster;
+        #[cfg(not(feature = "rayon"))]
+        let binned = false;
 
-            if input_mesh.vertices.is_empty() || input_mesh.indices.is_empty() {
+        for paint_job in paint_jobs.iter().filter(|_| !binned) {
+            let Some((clip_rect, px_mesh, render_in_low_precision)) =
+                self.prepare_direct_mesh(paint_job, pixels_per_point)
+            else {
                 continue;
-            }
-
-            let clip_rect = egui::Rect {
-                min: clip_rect.min * pixels_per_point,
-                max: clip_rect.max * pixels_per_point,
             };
 
-            let mut mesh_min = egui::Vec2::splat(f32::MAX);
-            let mut mesh_max = egui::Vec2::splat(-f32::MAX);
-
-            let px_mesh =
-                self.prepare_px_mesh(pixels_per_point, input_mesh, &mut mesh_min, &mut mesh_max);
-
-            let mesh_size = mesh_max - mesh_min;
-            if mesh_size.x > 8192.0 || mesh_size.y > 8192.0 {
-                // TODO it occasionally tries to make giant buffers in the first couple frames initially for some reason.
-                continue;
-            }
-
-            let render_in_low_precision = mesh_size.x > 4096.0 || mesh_size.y > 4096.0;
             if render_in_low_precision {
                 draw_egui_mesh::<2>(
                     self.simd_impl,
-                    &self.textures,
+                    textures,
                     direct_draw_buffer,
                     &clip_rect,
                     &px_mesh,
                     Vec2::ZERO,
                     self.allow_raster_opt,
                     self.convert_tris_to_rects,
+                    self.lcd_text.then_some(self.output_field_order),
+                    self.coverage_aa.then_some(&mut self.scratch.edges),
                     #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
                     &mut self.stats,
                 );
             } else {
                 draw_egui_mesh::<8>(
                     self.simd_impl,
-                    &self.textures,
+                    textures,
                     direct_draw_buffer,
                     &clip_rect,
                     &px_mesh,
                     Vec2::ZERO,
                     self.allow_raster_opt,
                     self.convert_tris_to_rects,
+                    self.lcd_text.then_some(self.output_field_order),
+                    self.coverage_aa.then_some(&mut self.scratch.edges),
                     #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
                     &mut self.stats,
                 );
             }
         }
 
+        #[cfg(feature = "rayon")]
+        if binned {
+            use rayon::iter::{
+                IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator,
+                ParallelIterator,
+            };
+            // Prepare the meshes of all paint jobs in parallel, then rasterize them together one tile per task
+            let buffer_size = [direct_draw_buffer.width, direct_draw_buffer.height];
+            let mut mesh_cmds = core::mem::take(&mut self.scratch.mesh_cmds);
+            if mesh_cmds.len() < paint_jobs.len() {
+                mesh_cmds.resize_with(paint_jobs.len(), Default::default);
+            }
+            let mesh_cmds_used = &mut mesh_cmds[..paint_jobs.len()];
+            mesh_cmds_used
+                .par_iter_mut()
+                .zip(paint_jobs.par_iter())
+                .for_each(|(cmds, paint_job)| {
+                    cmds.cmds.clear();
+                    if let Some((clip_rect, px_mesh, render_in_low_precision)) =
+                        self.prepare_direct_mesh(paint_job, pixels_per_point)
+                    {
+                        self.mesh_draw_cmds(
+                            textures,
+                            render_in_low_precision,
+                            buffer_size,
+                            &clip_rect,
+                            &px_mesh,
+                            Vec2::ZERO,
+                            cmds,
+                        );
+                    }
+                });
+            draw_binned(
+                self.simd_impl,
+                textures,
+                direct_draw_buffer,
+                mesh_cmds_used,
+                &mut self.scratch.bins,
+            );
+            self.scratch.mesh_cmds = mesh_cmds;
+        }
+
         #[cfg(feature = "raster_stats")]
         {
             self.stats.render_direct = start.elapsed().as_secs_f32();
         }
+        drop(store);
         self.free_textures(textures_delta);
     }
 
-    fn prepare_px_mesh(
+    /// Returns the pixel space clip rect and mesh of a paint job and whether it needs to be rendered in low precision.
+    fn prepare_direct_mesh(
         &self,
+        paint_job: &egui::ClippedPrimitive,
         pixels_per_point: f32,
-        mesh: &egui::Mesh,
-        mesh_min: &mut Vec2,
-        mesh_max: &mut Vec2,
-    ) -> Mesh {
-        let mut px_mesh = mesh.clone();
+    ) -> Option<(egui::Rect, Mesh, bool)> {
+        let input_mesh = match &paint_job.primitive {
+            egui::epaint::Primitive::Mesh(input_mesh) => input_mesh,
+            egui::epaint::Primitive::Callback(_) => {
+                #[cfg(feature = "log")]
+                log::error!("egui::epaint::Primitive::Callback(PaintCallback) not supported");
+                return None;
+            }
+        };
+
+        if input_mesh.vertices.is_empty() || input_mesh.indices.is_empty() {
+            return None;
+        }
+
+        let clip_rect = egui::Rect {
+            min: paint_job.clip_rect.min * pixels_per_point,
+            max: paint_job.clip_rect.max * pixels_per_point,
+        };
+
+        let (mesh_min, mesh_max) = px_mesh_bounds(input_mesh, pixels_per_point);
+        let mut px_mesh = Mesh::default();
+        self.prepare_px_mesh(pixels_per_point, input_mesh, &mut px_mesh);
+
+        let mesh_size = mesh_max - mesh_min;
+        if mesh_size.x > 8192.0 || mesh_size.y > 8192.0 {
+            // TODO it occasionally tries to make giant buffers in the first couple frames initially for some reason.
+            return None;
+        }
+
+        let render_in_low_precision = mesh_size.x > 4096.0 || mesh_size.y > 4096.0;
+        Some((clip_rect, px_mesh, render_in_low_precision))
+    }
+
+    /// Replaces `cmds` with the draw commands of a pixel space mesh, for rasterizing with `draw_binned()`.
+    #[cfg(feature = "rayon")]
+    #[allow(clippy::too_many_arguments)]
+    fn mesh_draw_cmds(
+        &self,
+        textures: &HashMap<egui::TextureId, EguiTexture>,
+        render_in_low_precision: bool,
+        buffer_size: [usize; 2],
+        clip_rect: &egui::Rect,
+        px_mesh: &Mesh,
+        vert_offset: Vec2,
+        cmds: &mut MeshDrawCmds,
+    ) {
+        if render_in_low_precision {
+            egui_mesh_draw_cmds::<2>(
+                textures,
+                buffer_size,
+                clip_rect,
+                px_mesh,
+                vert_offset,
+                self.allow_raster_opt,
+                self.convert_tris_to_rects,
+                self.lcd_text.then_some(self.output_field_order),
+                self.coverage_aa,
+                cmds,
+            );
+        } else {
+            egui_mesh_draw_cmds::<8>(
+                textures,
+                buffer_size,
+                clip_rect,
+                px_mesh,
+                vert_offset,
+                self.allow_raster_opt,
+                self.convert_tris_to_rects,
+                self.lcd_text.then_some(self.output_field_order),
+                self.coverage_aa,
+                cmds,
+            );
+        }
+    }
+
+    /// Writes the pixel space version of `mesh` into `px_mesh`, reusing its buffers.
+    fn prepare_px_mesh(&self, pixels_per_point: f32, mesh: &egui::Mesh, px_mesh: &mut Mesh) {
+        px_mesh.vertices.clear();
+        px_mesh.vertices.extend_from_slice(&mesh.vertices);
+        px_mesh.indices.clear();
+        px_mesh.indices.extend_from_slice(&mesh.indices);
+        px_mesh.texture_id = mesh.texture_id;
 
         for v in px_mesh.vertices.iter_mut() {
             v.pos *= pixels_per_point;
@@ -528,9 +784,6 @@ impl EguiSoftwareRender {
                     v.color = Color32::from_rgba_premultiplied(d[0], d[1], d[2], d[3]);
                 }
             }
-
-            *mesh_min = mesh_min.min(v.pos.to_vec2());
-            *mesh_max = mesh_max.max(v.pos.to_vec2());
         }
 
         // Make all the tris face forward (ccw) to simplify rasterization.
@@ -547,7 +800,6 @@ impl EguiSoftwareRender {
                 px_mesh.indices.swap(i + 1, i + 2);
             }
         }
-        px_mesh
     }
 
     fn render_prims_to_cache(
@@ -558,194 +810,113 @@ impl EguiSoftwareRender {
         #[cfg(feature = "raster_stats")]
         let start = std::time::Instant::now();
 
-        struct CacheReuse {
-            seen_this_frame: bool,
-            z_order: usize,
-            min_x: usize,
-            min_y: usize,
-            rendered_this_frame: bool,
-            hash: u32,
-        }
-
-        enum CacheUpdate {
-            CacheReuse(CacheReuse),
-            New(u32, CachedPrimitive),
-            None,
-        }
-
-        // Render paint jobs in parallel
         #[cfg(feature = "rayon")]
-        use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-        #[cfg(feature = "rayon")]
-        let iter = paint_jobs.par_iter().enumerate();
+        use rayon::iter::{
+            IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator,
+            ParallelExtend, ParallelIterator,
+        };
 
+        // Look up paint jobs in parallel. A hit doesn't need the pixel space mesh, so it doesn't allocate.
+        let mut lookups = core::mem::take(&mut self.scratch.prim_lookups);
+        let store = self.textures.read();
+        let lookup =
+            |(prim_idx, paint_job)| self.lookup_prim(&store, prim_idx, paint_job, pixels_per_point);
+        #[cfg(feature = "rayon")]
+        lookups.par_extend(paint_jobs.par_iter().enumerate().map(lookup));
         #[cfg(not(feature = "rayon"))]
-        let iter = paint_jobs.iter().enumerate();
-
-        let updates: Vec<CacheUpdate> = iter
-            .map(
-                |(
-                    prim_idx,
-                    egui::ClippedPrimitive {
-                        clip_rect,
-                        primitive,
-                    },
-                )| {
-                    let input_mesh = match primitive {
-                        egui::epaint::Primitive::Mesh(input_mesh) => input_mesh,
-                        egui::epaint::Primitive::Callback(_) => {
-                            #[cfg(feature = "log")]
-                            log::error!(
-                                "egui::epaint::Primitive::Callback(PaintCallback) not supported"
-                            );
-                            return CacheUpdate::None;
-                        }
-                    };
-
-                    if input_mesh.vertices.is_empty() || input_mesh.indices.is_empty() {
-                        return CacheUpdate::None;
-                    }
-
-                    let clip_rect = egui::Rect {
-                        min: clip_rect.min * pixels_per_point,
-                        max: clip_rect.max * pixels_per_point,
-                    };
-
-                    let mut mesh_min = egui::Vec2::splat(f32::MAX);
-                    let mut mesh_max = egui::Vec2::splat(-f32::MAX);
+        lookups.extend(paint_jobs.iter().enumerate().map(lookup));
+
+        // Give each miss buffers from the pools, then render them in parallel
+        let mut jobs = core::mem::take(&mut self.scratch.raster_jobs);
+        for lookup in &lookups {
+            if let PrimLookup::Miss(miss) = lookup {
+                jobs.push(RasterJob {
+                    miss: *miss,
+                    buffers: self.scratch.raster_buffers.pop().unwrap_or_default(),
+                    prim: self
+                        .scratch
+                        .free_prims
+                        .pop()
+                        .unwrap_or_else(CachedPrimitive::new),
+                    scroll: None,
+                });
+            }
+        }
 
-                    let px_mesh = self.prepare_px_mesh(
-                        pixels_per_point,
-                        input_mesh,
-                        &mut mesh_min,
-                        &mut mesh_max,
-                    );
+        // Index the cached primitives that a miss may be a scrolled version of by clip rect and texture
+        self.scratch.scroll_candidates.clear();
+        if self.scroll_detection && !jobs.is_empty() {
+            self.scratch.scroll_candidates.extend(
+                self.cached_primitives
+                    .values()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, prim)| !prim.tri_keys.is_empty())
+                    .map(|(idx, prim)| (prim.scroll_candidate_key(), idx)),
+            );
+            self.scratch.scroll_candidates.sort_unstable();
+        }
 
-                    let cropped_min = mesh_min.max(clip_rect.min.to_vec2());
-                    let cropped_max = mesh_max.min(clip_rect.max.to_vec2());
-                    let clip_rect = egui::Rect {
-                        min: Pos2::ZERO,
-                        max: (cropped_max - cropped_min).to_pos2() + egui::Vec2::splat(0.5),
-                    };
-
-                    let hash = {
-                        let mut hasher = Hash32::new_fnv();
-
-                        hasher.hash_wrap(clip_rect.min.x.to_bits());
-                        hasher.hash_wrap(clip_rect.min.y.to_bits());
-                        hasher.hash_wrap(clip_rect.max.x.to_bits());
-                        hasher.hash_wrap(clip_rect.max.y.to_bits());
-                        hasher.hash_wrap(match px_mesh.texture_id {
-                            egui::TextureId::Managed(id) => id as u32,
-                            egui::TextureId::User(id) => id as u32 + 9358476,
-                        });
-                        for ind in &px_mesh.indices {
-                            let v = px_mesh.vertices[*ind as usize];
-
-                            // Tried to do this to avoid full redraws when moving a window but it was resulting in some
-                            // meshes to be matches incorrectly in the ui gradient portion of the egui color test:
-                            //let pos = v.pos - cropped_min;
-
-                            // It's much faster to not wrap for every field. General ordering should be sufficiently preserved.
-                            hasher.hash(v.pos.x.to_bits());
-                            hasher.hash(v.pos.y.to_bits());
-                            hasher.hash(v.uv.x.to_bits());
-                            hasher.hash(v.uv.y.to_bits());
-                            hasher.hash(u32::from_le_bytes(v.color.to_array()));
-                            hasher.fnv_wrap();
-                        }
-                        hasher.hash_wrap(px_mesh.indices.len() as u32);
-                        hasher.finalize()
-                    };
-
-                    if self.cached_primitives.contains_key(&hash) {
-                        CacheUpdate::CacheReuse(CacheReuse {
-                            hash,
-                            seen_this_frame: true,
-                            z_order: prim_idx,
-                            min_x: cropped_min.x as usize,
-                            min_y: cropped_min.y as usize,
-                            rendered_this_frame: false,
-                        })
-                    } else {
-                        let min_x = cropped_min.x as usize;
-                        let min_y = cropped_min.y as usize;
-                        let width = (cropped_max.x.ceil() as usize) - min_x;
-                        let height = (cropped_max.y.ceil() as usize) - min_y;
-
-                        if width > 8192 || height > 8192 {
-                            // TODO it occasionally tries to make giant buffers in the first couple frames initially for some reason.
-                            return CacheUpdate::None;
-                        }
+        let textures = &store.textures;
 
-                        if width == 0 || height == 0 {
-                            return CacheUpdate::None;
-                        }
+        #[cfg(feature = "rayon")]
+        jobs.par_iter_mut()
+            .for_each(|job| self.render_prim(textures, job, paint_jobs, pixels_per_point));
 
-                        let render_in_low_precision = width > 4096 || height > 4096;
-
-                        let mut prim = CachedPrimitive::new(min_x, min_y, width, height, prim_idx);
-                        let mut buffer_ref = BufferMutRef {
-                            data: &mut prim.buffer,
-                            width,
-                            height,
-                            width_extent: width - 1,
-                            height_extent: height - 1,
-                        };
-
-                        let offset = -vec2(cropped_min.x.floor(), cropped_min.y.floor());
-
-                        if render_in_low_precision {
-                            // Seems to not be an issue in direct draw? Seems like a bug.
-                            draw_egui_mesh::<2>(
-                                self.simd_impl,
-                                &self.textures,
-                                &mut buffer_ref,
-                                &clip_rect,
-                                &px_mesh,
-                                offset,
-                                self.allow_raster_opt,
-                                self.convert_tris_to_rects,
-                                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
-                                &mut self.stats,
-                            );
-                        } else {
-                            draw_egui_mesh::<8>(
-                                self.simd_impl,
-                                &self.textures,
-                                &mut buffer_ref,
-                                &clip_rect,
-                                &px_mesh,
-                                offset,
-                                self.allow_raster_opt,
-                                self.convert_tris_to_rects,
-                                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
-                                &mut self.stats,
-                            );
-                        }
-                        prim.update_occupied_tiles(self.tiles_dim[0], self.tiles_dim[1]);
-                        CacheUpdate::New(hash, prim)
-                    }
-                },
-            )
-            .collect::<Vec<_>>();
-
-        updates.into_iter().for_each(|update| match update {
-            CacheUpdate::CacheReuse(cache_reuse) => {
-                if let Some(cached_primitive) = self.cached_primitives.get_mut(&cache_reuse.hash) {
-                    cached_primitive.seen_this_frame = cache_reuse.seen_this_frame;
-                    cached_primitive.z_order = cache_reuse.z_order;
-                    cached_primitive.min_x = cache_reuse.min_x;
-                    cached_primitive.min_y = cache_reuse.min_y;
-                    cached_primitive.rendered_this_frame = cache_reuse.rendered_this_frame;
+        #[cfg(not(feature = "rayon"))]
+        {
+            #[cfg(feature = "raster_stats")]
+            let mut stats = core::mem::take(&mut self.stats);
+            for job in jobs.iter_mut() {
+                self.render_prim(
+                    textures,
+                    job,
+                    paint_jobs,
+                    pixels_per_point,
+                    #[cfg(feature = "raster_stats")]
+                    &mut stats,
+                );
+            }
+            #[cfg(feature = "raster_stats")]
+            {
+                self.stats = stats;
+            }
+        }
+        drop(store);
+
+        for lookup in lookups.drain(..) {
+            if let PrimLookup::Hit {
+                hash,
+                z_order,
+                min_x,
+                min_y,
+            } = lookup
+            {
+                if let Some(cached_primitive) = self.cached_primitives.get_mut(&hash) {
+                    cached_primitive.seen_this_frame = true;
+                    cached_primitive.prev_z_order = cached_primitive.z_order;
+                    cached_primitive.z_order = z_order;
+                    cached_primitive.min_x = min_x;
+                    cached_primitive.min_y = min_y;
+                    cached_primitive.rendered_this_frame = false;
                 }
             }
-            CacheUpdate::New(hash, prim) => {
-                self.prims_updated_this_frame += 1;
-                self.cached_primitives.insert(hash, prim);
+        }
+        for job in jobs.drain(..) {
+            let key = self.insert_cached_prim(job.miss.hash, job.miss.collided, job.prim);
+            if let Some((prev_key, scroll)) = job.scroll {
+                #[cfg(feature = "raster_stats")]
+                {
+                    self.stats.scrolled_prims += 1;
+                }
+                self.register_scroll_shift(key, prev_key, scroll);
             }
-            CacheUpdate::None => (),
-        });
+            if self.scratch.raster_buffers.len() < MAX_POOLED_RASTER_BUFFERS {
+                self.scratch.raster_buffers.push(job.buffers);
+            }
+        }
+        self.scratch.prim_lookups = lookups;
+        self.scratch.raster_jobs = jobs;
 
         #[cfg(feature = "raster_stats")]
         {
@@ -753,19 +924,446 @@ impl EguiSoftwareRender {
         }
     }
 
-    fn update_canvas_from_cached(&mut self) {
-        let simd_impl = self.simd_impl;
-        #[cfg(feature = "raster_stats")]
-        let start = std::time::Instant::now();
+    /// Looks up a paint job in the primitive cache without building its pixel space mesh.
+    fn lookup_prim(
+        &self,
+        store: &TextureStore,
+        prim_idx: usize,
+        paint_job: &egui::ClippedPrimitive,
+        pixels_per_point: f32,
+    ) -> PrimLookup {
+        let input_mesh = match &paint_job.primitive {
+            egui::epaint::Primitive::Mesh(input_mesh) => input_mesh,
+            egui::epaint::Primitive::Callback(_) => {
+                #[cfg(feature = "log")]
+                log::error!("egui::epaint::Primitive::Callback(PaintCallback) not supported");
+                return PrimLookup::None;
+            }
+        };
 
-        let mut sorted_prim_cache = self.cached_primitives.values().collect::<Vec<_>>();
-        sorted_prim_cache.sort_unstable_by_key(|prim| prim.z_order);
+        if input_mesh.vertices.is_empty() || input_mesh.indices.is_empty() {
+            return PrimLookup::None;
+        }
 
-        #[allow(unused_mut)]
-        let mut canvas =
-            BufferMutRef::new(&mut self.canvas.data, self.canvas.width, self.canvas.height);
+        let px_clip_rect = egui::Rect {
+            min: paint_job.clip_rect.min * pixels_per_point,
+            max: paint_job.clip_rect.max * pixels_per_point,
+        };
 
-        #[cfg(feature = "rayon")]
+        let (mesh_min, mesh_max) = px_mesh_bounds(input_mesh, pixels_per_point);
+
+        let cropped_min = mesh_min.max(px_clip_rect.min.to_vec2());
+        let cropped_max = mesh_max.min(px_clip_rect.max.to_vec2());
+        let clip_rect = egui::Rect {
+            min: Pos2::ZERO,
+            max: (cropped_max - cropped_min).to_pos2() + egui::Vec2::splat(0.5),
+        };
+
+        let key = primitive_cache_key(&clip_rect, input_mesh, pixels_per_point);
+        #[cfg(feature = "test_render")]
+        let key = key & self.cache_key_mask;
+        let fingerprint = if self.cache_verification {
+            MeshFingerprint::new(&clip_rect, input_mesh)
+        } else {
+            Default::default()
+        };
+        let hash = self.probe_cache_key(key, &fingerprint);
+
+        let min_x = cropped_min.x as usize;
+        let min_y = cropped_min.y as usize;
+
+        // Procedural textures can change every frame, so their primitives are always dirty. Primitives of streamed
+        // textures are dirty when the texture was streamed into since they were rasterized.
+        let texture = store.textures.get(&input_mesh.texture_id);
+        let procedural = texture.is_some_and(|texture| texture.procedural.is_some());
+        let texture_generation = texture.map_or(0, |texture| texture.generation);
+
+        if !procedural
+            && self.cached_primitives.get(&hash).is_some_and(|prim| {
+                prim.fingerprint == fingerprint && prim.texture_generation == texture_generation
+            })
+        {
+            return PrimLookup::Hit {
+                hash,
+                z_order: prim_idx,
+                min_x,
+                min_y,
+            };
+        }
+
+        let width = (cropped_max.x.ceil() as usize) - min_x;
+        let height = (cropped_max.y.ceil() as usize) - min_y;
+
+        if width > 8192 || height > 8192 {
+            // TODO it occasionally tries to make giant buffers in the first couple frames initially for some reason.
+            return PrimLookup::None;
+        }
+
+        if width == 0 || height == 0 {
+            return PrimLookup::None;
+        }
+
+        PrimLookup::Miss(PrimMiss {
+            hash,
+            fingerprint,
+            collided: hash != key,
+            z_order: prim_idx,
+            px_clip_rect,
+            clip_rect,
+            mesh_rect: egui::Rect::from_min_max(mesh_min.to_pos2(), mesh_max.to_pos2()),
+            cropped_min,
+            procedural,
+            texture_generation,
+            min_x,
+            min_y,
+            width,
+            height,
+        })
+    }
+
+    /// Rasterizes a paint job that missed the cache into the buffers of `job`.
+    fn render_prim(
+        &self,
+        textures: &HashMap<egui::TextureId, EguiTexture>,
+        job: &mut RasterJob,
+        paint_jobs: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+        #[cfg(all(feature = "raster_stats", not(feature = "rayon")))] stats: &mut RasterStats,
+    ) {
+        let RasterJob {
+            miss,
+            buffers:
+                RasterBuffers {
+                    px_mesh,
+                    pixels,
+                    scroll: scroll_scratch,
+                    edges,
+                    #[cfg(feature = "rayon")]
+                    mesh_cmds,
+                    #[cfg(feature = "rayon")]
+                    bins,
+                },
+            prim,
+            scroll: scroll_source,
+        } = job;
+        // Only meshes are looked up as misses
+        let egui::epaint::Primitive::Mesh(input_mesh) = &paint_jobs[miss.z_order].primitive else {
+            return;
+        };
+        self.prepare_px_mesh(pixels_per_point, input_mesh, px_mesh);
+
+        let PrimMiss {
+            min_x,
+            min_y,
+            width,
+            height,
+            ..
+        } = *miss;
+        let clip_rect = miss.clip_rect;
+
+        let render_in_low_precision = width > 4096 || height > 4096;
+
+        prim.reset(min_x, min_y, width, height, miss.z_order);
+        prim.clip_rect = miss.px_clip_rect;
+        prim.texture_id = px_mesh.texture_id;
+        prim.texture_generation = miss.texture_generation;
+        prim.fingerprint = miss.fingerprint;
+        // Only meshes that are cut off by their clip rect are likely to be scrolled, like the contents of a ScrollArea
+        if self.scroll_detection
+            && !miss.procedural
+            && !miss.px_clip_rect.contains_rect(miss.mesh_rect)
+        {
+            tri_keys(px_mesh, self.cache_verification, &mut prim.tri_keys);
+        }
+
+        // Rendered densely, then only the non-transparent runs are kept in the cache
+        pixels.clear();
+        pixels.resize(width * height, [0; 4]);
+        let mut buffer_ref = BufferMutRef {
+            data: pixels,
+            width,
+            height,
+            width_extent: width - 1,
+            height_extent: height - 1,
+        };
+
+        let offset = -vec2(miss.cropped_min.x.floor(), miss.cropped_min.y.floor());
+
+        if let Some((prev_key, prev, scroll)) = self.find_scrolled_prim(prim, scroll_scratch) {
+            // Copy the pixels that are still visible into this primitive's buffer, then only rasterize the newly
+            // exposed part
+            let prim_min = i64vec2(min_x as i64, min_y as i64);
+            prev.copy_shifted_pixels(&scroll, &mut buffer_ref, prim_min);
+
+            let local_bounds = [I64Vec2::default(), i64vec2(width as i64, height as i64)];
+            let local_overlap = [scroll.overlap[0] - prim_min, scroll.overlap[1] - prim_min];
+            let mut scissors = [Scissor::default(); 4];
+            let mut scissor_count = 0;
+            for bounds in bounds_around(local_bounds, local_overlap) {
+                scissors[scissor_count].bounds = bounds;
+                scissor_count += 1;
+            }
+            let scissors = &scissors[..scissor_count];
+
+            if render_in_low_precision {
+                draw_egui_mesh_scissored::<2>(
+                    self.simd_impl,
+                    textures,
+                    &mut buffer_ref,
+                    &clip_rect,
+                    px_mesh,
+                    offset,
+                    self.allow_raster_opt,
+                    self.convert_tris_to_rects,
+                    self.lcd_text.then_some(self.output_field_order),
+                    self.coverage_aa.then_some(&mut *edges),
+                    #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                    stats,
+                    scissors,
+                );
+            } else {
+                draw_egui_mesh_scissored::<8>(
+                    self.simd_impl,
+                    textures,
+                    &mut buffer_ref,
+                    &clip_rect,
+                    px_mesh,
+                    offset,
+                    self.allow_raster_opt,
+                    self.convert_tris_to_rects,
+                    self.lcd_text.then_some(self.output_field_order),
+                    self.coverage_aa.then_some(&mut *edges),
+                    #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                    stats,
+                    scissors,
+                );
+            }
+            prim.set_pixels(pixels, self.canvas.width, self.canvas.height);
+            *scroll_source = Some((prev_key, scroll));
+            return;
+        }
+
+        #[cfg(feature = "rayon")]
+        if self.binned_raster && width * height >= BINNED_RASTER_MIN_PX {
+            // Large enough to be worth splitting into tiles rasterized in parallel
+            self.mesh_draw_cmds(
+                textures,
+                render_in_low_precision,
+                [width, height],
+                &clip_rect,
+                px_mesh,
+                offset,
+                mesh_cmds,
+            );
+            draw_binned(
+                self.simd_impl,
+                textures,
+                &mut buffer_ref,
+                core::slice::from_ref(mesh_cmds),
+                bins,
+            );
+            prim.set_pixels(pixels, self.canvas.width, self.canvas.height);
+            return;
+        }
+
+        if render_in_low_precision {
+            // Seems to not be an issue in direct draw? Seems like a bug.
+            draw_egui_mesh::<2>(
+                self.simd_impl,
+                textures,
+                &mut buffer_ref,
+                &clip_rect,
+                px_mesh,
+                offset,
+                self.allow_raster_opt,
+                self.convert_tris_to_rects,
+                self.lcd_text.then_some(self.output_field_order),
+                self.coverage_aa.then_some(&mut *edges),
+                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                stats,
+            );
+        } else {
+            draw_egui_mesh::<8>(
+                self.simd_impl,
+                textures,
+                &mut buffer_ref,
+                &clip_rect,
+                px_mesh,
+                offset,
+                self.allow_raster_opt,
+                self.convert_tris_to_rects,
+                self.lcd_text.then_some(self.output_field_order),
+                self.coverage_aa.then_some(&mut *edges),
+                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                stats,
+            );
+        }
+        prim.set_pixels(pixels, self.canvas.width, self.canvas.height);
+    }
+
+    /// Returns the cache key of a cached primitive that `prim` is a scrolled version of and the primitive, along with
+    /// the offset and the region of `prim` whose pixels can be copied from it. Only the primitives with the same clip
+    /// rect and texture in `FrameScratch::scroll_candidates` are compared.
+    fn find_scrolled_prim(
+        &self,
+        prim: &CachedPrimitive,
+        scratch: &mut ScrollScratch,
+    ) -> Option<(u64, &CachedPrimitive, Scroll)> {
+        if prim.tri_keys.is_empty() {
+            return None;
+        }
+        let candidates = &self.scratch.scroll_candidates;
+        let key = prim.scroll_candidate_key();
+        let start = candidates.partition_point(|&(candidate_key, _)| candidate_key < key);
+        candidates[start..]
+            .iter()
+            .take_while(|&&(candidate_key, _)| candidate_key == key)
+            .map(|&(_, idx)| {
+                (
+                    self.cached_primitives.keys[idx],
+                    &self.cached_primitives.prims[idx],
+                )
+            })
+            // The candidate keys may collide
+            .filter(|(_, prev)| {
+                prev.clip_rect == prim.clip_rect
+                    && prev.texture_id == prim.texture_id
+                    && prev.texture_generation == prim.texture_generation
+            })
+            .find_map(|(prev_key, prev)| {
+                let scroll = find_scroll(
+                    &prim.tri_keys,
+                    prim.bounds(),
+                    &prev.tri_keys,
+                    prev.bounds(),
+                    scratch,
+                )?;
+                Some((prev_key, prev, scroll))
+            })
+    }
+
+    /// Lets the canvas tiles of a scrolled primitive be shifted by `shift_scrolled_tiles()` instead of composited
+    /// again. Only if the primitive it was scrolled from isn't drawn this frame and isn't the source of another scroll.
+    fn register_scroll_shift(&mut self, key: u64, prev_key: u64, scroll: Scroll) {
+        if key == prev_key {
+            return;
+        }
+        let Some(prev) = self
+            .cached_primitives
+            .get_mut(&prev_key)
+            .filter(|prev| !prev.seen_this_frame && !prev.scroll_shift)
+        else {
+            return;
+        };
+        prev.scroll_shift = true;
+        if let Some(prim) = self.cached_primitives.get_mut(&key) {
+            prim.scroll_shift = true;
+        }
+        self.scratch.scroll_shifts.push(ScrollShift {
+            key,
+            prev_key,
+            scroll,
+        });
+    }
+
+    /// Returns the key a primitive with `fingerprint` is cached under. With cache verification, a primitive whose key
+    /// is taken by a primitive with another fingerprint is kept under the next key of a sequence derived from both, so
+    /// primitives whose keys collide are each cached instead of drawing each other's pixels.
+    fn probe_cache_key(&self, key: u64, fingerprint: &MeshFingerprint) -> u64 {
+        let mut hash = key;
+        if self.cache_verification {
+            for _ in 0..MAX_CACHE_KEY_PROBES {
+                match self.cached_primitives.get(&hash) {
+                    Some(prim) if prim.fingerprint != *fingerprint => {
+                        hash = next_cache_key(hash, fingerprint);
+                    }
+                    _ => break,
+                }
+            }
+        }
+        hash
+    }
+
+    /// Caches a rasterized primitive under `hash` from its lookup and returns the key it's cached under. A primitive
+    /// rasterized earlier this frame may have taken that key since, then the next key is probed again.
+    fn insert_cached_prim(&mut self, hash: u64, collided: bool, mut prim: CachedPrimitive) -> u64 {
+        self.prims_updated_this_frame += 1;
+        let probed = self.probe_cache_key(hash, &prim.fingerprint);
+        if collided || probed != hash {
+            #[cfg(feature = "log")]
+            log::warn!("Primitive cache key collision, cached under {probed:#018x}");
+            #[cfg(feature = "raster_stats")]
+            {
+                self.stats.cache_collisions += 1;
+            }
+        }
+        prim.replaced_prim = self.cached_primitives.get(&probed).is_some();
+        // An identical mesh drawn twice in the same frame replaces the first one
+        if let Some(replaced) = self.cached_primitives.insert(probed, prim) {
+            self.free_prim(replaced);
+        }
+        probed
+    }
+
+    /// Keeps the buffers of a primitive that's no longer cached for reuse by a new one.
+    fn free_prim(&mut self, prim: CachedPrimitive) {
+        if self.scratch.free_prims.len() < MAX_FREE_PRIMS {
+            self.scratch.free_prims.push(prim);
+        }
+    }
+
+    /// Empties a primitive cache, keeping the buffers of its primitives for reuse by new ones.
+    fn flush_cache(&mut self, cache: &mut PrimCache) {
+        for prim in cache.drain() {
+            self.free_prim(prim);
+        }
+    }
+
+    /// Cached primitives are only valid for the pixels_per_point they were rasterized at. Flushes them, or keeps them as
+    /// the previous cache generation and restores that generation if it matches the new pixels_per_point. The buffers
+    /// of flushed primitives are kept for reuse by new ones.
+    fn swap_scale_cache(&mut self, pixels_per_point: f32) {
+        let mut cached_primitives = core::mem::take(&mut self.cached_primitives);
+        let previous = self.previous_scale_cache.take();
+        if self.keep_previous_scale_cache {
+            match previous {
+                Some((previous_ppp, previous)) if previous_ppp == pixels_per_point => {
+                    self.cached_primitives = previous;
+                }
+                Some((_, mut stale)) => {
+                    self.flush_cache(&mut stale);
+                    self.cached_primitives = stale;
+                }
+                None => (),
+            }
+            self.previous_scale_cache = Some((self.cache_pixels_per_point, cached_primitives));
+        } else {
+            if let Some((_, mut stale)) = previous {
+                self.flush_cache(&mut stale);
+            }
+            self.flush_cache(&mut cached_primitives);
+            self.cached_primitives = cached_primitives;
+        }
+        self.cache_pixels_per_point = pixels_per_point;
+        self.redraw_everything_this_frame = true;
+    }
+
+    fn update_canvas_from_cached(&mut self) {
+        let simd_impl = self.simd_impl;
+        #[cfg(feature = "raster_stats")]
+        let start = std::time::Instant::now();
+
+        let prims = self.cached_primitives.values();
+        let mut sorted_prims = core::mem::take(&mut self.scratch.sorted_prims);
+        sorted_prims.clear();
+        sorted_prims.extend(0..prims.len());
+        sorted_prims.sort_unstable_by_key(|&idx| prims[idx].z_order);
+
+        #[allow(unused_mut)]
+        let mut canvas =
+            BufferMutRef::new(&mut self.canvas.data, self.canvas.width, self.canvas.height);
+
+        #[cfg(feature = "rayon")]
         {
             use rayon::{
                 iter::{IndexedParallelIterator, ParallelIterator},
@@ -808,7 +1406,8 @@ impl EguiSoftwareRender {
 
                             update_canvas_tile(
                                 simd_impl,
-                                &sorted_prim_cache,
+                                prims,
+                                &sorted_prims,
                                 canvas_tile_row,
                                 tile_x,
                                 tile_y,
@@ -833,7 +1432,8 @@ impl EguiSoftwareRender {
                 let full_height = canvas.height;
                 update_canvas_tile(
                     simd_impl,
-                    &sorted_prim_cache,
+                    prims,
+                    &sorted_prims,
                     &mut canvas,
                     tile_x,
                     tile_y,
@@ -842,6 +1442,8 @@ impl EguiSoftwareRender {
                 );
             }
         }
+        self.scratch.sorted_prims = sorted_prims;
+
         #[cfg(feature = "raster_stats")]
         {
             self.stats.update_canvas_from_cached = start.elapsed().as_secs_f32();
@@ -849,23 +1451,41 @@ impl EguiSoftwareRender {
     }
 
     fn clear_unused_cached_prims(&mut self) {
-        self.cached_primitives
-            .retain(|_hash, prim| prim.seen_this_frame);
+        let mut unused = core::mem::take(&mut self.scratch.unused_prims);
+        unused.extend(
+            self.cached_primitives
+                .iter()
+                .filter(|(_hash, prim)| !prim.seen_this_frame)
+                .map(|(hash, _prim)| *hash),
+        );
+        for hash in unused.drain(..) {
+            if let Some(prim) = self.cached_primitives.remove(&hash) {
+                self.free_prim(prim);
+            }
+        }
+        self.scratch.unused_prims = unused;
     }
 
     const DIRTY_TILE_MASK: u8 = 0b00000001;
     const OCCUPIED_TILE_MASK: u8 = 0b000000010;
+    /// Shifted along with a scrolled primitive instead of composited, see `shift_scrolled_tiles()`
+    const SHIFTED_TILE_MASK: u8 = 0b00000100;
     fn update_dirty_tiles(&mut self) {
         #[cfg(feature = "raster_stats")]
         let start = std::time::Instant::now();
         self.dirty_tiles
             .resize(self.tiles_dim[0] * self.tiles_dim[1], 0);
-        self.dirty_tiles.fill(0);
+        if self.redraw_everything_this_frame {
+            self.dirty_tiles.fill(Self::DIRTY_TILE_MASK);
+        } else {
+            self.dirty_tiles.fill(0);
+        }
         for prim in self.cached_primitives.values() {
             for tile in &prim.occupied_tiles {
                 let mask =
                     &mut self.dirty_tiles[tile[0] as usize + tile[1] as usize * self.tiles_dim[0]];
-                if !prim.seen_this_frame || prim.rendered_this_frame {
+                // The tiles of scrolled primitives are marked by `shift_scrolled_tiles()`
+                if (!prim.seen_this_frame || prim.rendered_this_frame) && !prim.scroll_shift {
                     *mask |= Self::DIRTY_TILE_MASK;
                 }
                 *mask |= Self::OCCUPIED_TILE_MASK;
@@ -877,62 +1497,186 @@ impl EguiSoftwareRender {
         }
     }
 
-    fn set_textures(&mut self, textures_delta: &egui::TexturesDelta) {
-        #[cfg(feature = "raster_stats")]
-        let start = std::time::Instant::now();
-        for (id, delta) in &textures_delta.set {
-            if delta.options.magnification != delta.options.minification {
-                // Would need helper lanes to impl?
-                #[cfg(feature = "log")]
-                log::warn!(
-                    "TextureOptions magnification and minification not matching is unsupported."
-                );
-            }
-            let pixels = match &delta.image {
-                egui::ImageData::Color(image) => {
-                    assert_eq!(image.width() * image.height(), image.pixels.len());
-                    Cow::Borrowed(&image.pixels)
+    /// Shifts the canvas tiles of each scrolled primitive from `register_scroll_shift()` in place, instead of
+    /// compositing them again. A tile of the primitive or of the one it was scrolled from is shifted if it's inside the
+    /// part whose pixels were reused, the canvas still holds the last frame there and where the pixels come from, and
+    /// every other primitive over those pixels was kept from the last frame, in the same order relative to the scrolled
+    /// primitive, and is a single color there (like the background of a panel). The other tiles, including the newly
+    /// exposed part, are marked dirty. Primitives only composited in one of the frames must be transparent there.
+    fn shift_scrolled_tiles(&mut self) {
+        let mut shifts = core::mem::take(&mut self.scratch.scroll_shifts);
+        if self.redraw_everything_this_frame {
+            // Every tile is already dirty
+            shifts.clear();
+        }
+        let mut tiles = core::mem::take(&mut self.scratch.shifted_tiles);
+        let mut overlapping = core::mem::take(&mut self.scratch.overlapping_prims);
+        let tiles_x = self.tiles_dim[0];
+        let canvas_bounds = [
+            I64Vec2::default(),
+            i64vec2(self.canvas.width as i64, self.canvas.height as i64),
+        ];
+
+        for ScrollShift {
+            key,
+            prev_key,
+            scroll,
+        } in shifts.drain(..)
+        {
+            let cache = &self.cached_primitives;
+            // Either may have been replaced by a primitive with the same key since
+            let new_idx = cache.indices.get(&key).copied();
+            let prev_idx = cache.indices.get(&prev_key).copied();
+            let (Some(new_idx), Some(prev_idx)) = (
+                new_idx.filter(|&idx| cache.prims[idx].scroll_shift),
+                prev_idx.filter(|&idx| cache.prims[idx].scroll_shift),
+            ) else {
+                for prim in [new_idx, prev_idx]
+                    .into_iter()
+                    .flatten()
+                    .map(|idx| &cache.prims[idx])
+                {
+                    for tile in prim.occupied_tiles.iter().filter(|_| prim.scroll_shift) {
+                        self.dirty_tiles[tile[0] as usize + tile[1] as usize * tiles_x] |=
+                            Self::DIRTY_TILE_MASK;
+                    }
                 }
+                continue;
             };
-            let size = delta.image.size();
-            if let Some(pos) = delta.pos {
-                if let Some(texture) = self.textures.get_mut(id) {
-                    for y in 0..size[1] {
-                        for x in 0..size[0] {
-                            let src_pos = x + y * size[0];
-                            let dest_pos = (x + pos[0]) + (y + pos[1]) * texture.width;
-                            texture.data[dest_pos] = match self.output_field_order {
-                                ColorFieldOrder::Rgba => pixels[src_pos].to_array(),
-                                ColorFieldOrder::Bgra => {
-                                    swizzle_rgba_bgra(pixels[src_pos].to_array())
-                                }
-                            };
-                        }
+            let prims = cache.values();
+            let (new, prev) = (&prims[new_idx], &prims[prev_idx]);
+
+            let Scroll { offset, overlap } = scroll;
+            let source = translate(overlap, I64Vec2::default() - offset);
+            let hull = [overlap[0].min(source[0]), overlap[1].max(source[1])];
+            overlapping.clear();
+            overlapping.extend((0..prims.len()).filter(|&idx| {
+                idx != new_idx
+                    && idx != prev_idx
+                    && !is_empty(&intersect(prims[idx].bounds(), hull))
+            }));
+
+            let unchanged_since_canvas = |tile_idx: usize| {
+                self.tile_frames
+                    .get(tile_idx)
+                    .is_some_and(|&tile_frame| tile_frame <= self.canvas.frame)
+            };
+            let can_shift = |tile: [u16; 2]| {
+                let min = i64vec2(tile[0] as i64, tile[1] as i64) * TILE_SIZE as i64;
+                let tile_bounds = intersect([min, min + TILE_SIZE as i64], canvas_bounds);
+                let src = translate(tile_bounds, I64Vec2::default() - offset);
+                if !contains(overlap, tile_bounds) || !contains(canvas_bounds, src) {
+                    return false;
+                }
+                let src_tiles_x =
+                    src[0].x as usize / TILE_SIZE..=(src[1].x - 1) as usize / TILE_SIZE;
+                let src_tiles_y =
+                    src[0].y as usize / TILE_SIZE..=(src[1].y - 1) as usize / TILE_SIZE;
+                let src_unchanged = src_tiles_y.into_iter().all(|y| {
+                    src_tiles_x
+                        .clone()
+                        .all(|x| unchanged_since_canvas(x + y * tiles_x))
+                });
+                if !src_unchanged
+                    || !unchanged_since_canvas(tile[0] as usize + tile[1] as usize * tiles_x)
+                {
+                    return false;
+                }
+                const TRANSPARENT: Option<[u8; 4]> = Some([0; 4]);
+                overlapping.iter().map(|&idx| &prims[idx]).all(|prim| {
+                    let touches = |bounds| !is_empty(&intersect(prim.bounds(), bounds));
+                    if !touches(tile_bounds) && !touches(src) {
+                        true
+                    } else if prim.scroll_shift {
+                        false
+                    } else if !prim.seen_this_frame {
+                        // Only composited in the last frame
+                        prim.uniform_color(src) == TRANSPARENT
+                    } else if prim.rendered_this_frame {
+                        // Only composited in this frame, unless it replaced one with the same key
+                        !prim.replaced_prim && prim.uniform_color(tile_bounds) == TRANSPARENT
+                    } else {
+                        let color = prim.uniform_color(tile_bounds);
+                        (prim.z_order < new.z_order) == (prim.prev_z_order < prev.z_order)
+                            && color.is_some()
+                            && color == prim.uniform_color(src)
                     }
+                })
+            };
+
+            tiles.clear();
+            for &tile in new.occupied_tiles.iter().chain(&prev.occupied_tiles) {
+                let tile_idx = tile[0] as usize + tile[1] as usize * tiles_x;
+                let mask = self.dirty_tiles[tile_idx];
+                // Occupied by both, or composited anyway
+                if mask & (Self::DIRTY_TILE_MASK | Self::SHIFTED_TILE_MASK) != 0 {
+                    continue;
                 }
-            } else {
-                let new_texture =
-                    EguiTexture::new(self.output_field_order, delta.options, size, &pixels);
+                if can_shift(tile) {
+                    self.dirty_tiles[tile_idx] |= Self::SHIFTED_TILE_MASK;
+                    tiles.push(tile_idx);
+                } else {
+                    self.dirty_tiles[tile_idx] |= Self::DIRTY_TILE_MASK;
+                }
+            }
+            tiles.sort_unstable();
+            self.canvas.shift_tiles(&tiles, tiles_x, offset);
+            #[cfg(feature = "raster_stats")]
+            {
+                self.stats.shifted_tiles += tiles.len() as u32;
+            }
+        }
+
+        self.scratch.scroll_shifts = shifts;
+        self.scratch.shifted_tiles = tiles;
+        self.scratch.overlapping_prims = overlapping;
+    }
 
-                self.textures.insert(*id, new_texture);
+    /// Marks the tiles composited since the frame the canvas holds as dirty, returns true if there were any. Only a
+    /// canvas swapped in by `ThreadedEguiSoftwareRender`, which hands off every composited canvas, can hold an older
+    /// frame than the last one.
+    fn mark_stale_tiles(&mut self) -> bool {
+        self.frames_composited += 1;
+        self.tile_frames.resize(self.dirty_tiles.len(), 0);
+        let mut stale = false;
+        for (mask, tile_frame) in self.dirty_tiles.iter_mut().zip(&mut self.tile_frames) {
+            if *tile_frame > self.canvas.frame {
+                *mask |= Self::DIRTY_TILE_MASK;
+                stale = true;
+            }
+            if *mask & (Self::DIRTY_TILE_MASK | Self::SHIFTED_TILE_MASK) != 0 {
+                *tile_frame = self.frames_composited;
             }
         }
+        self.canvas.frame = self.frames_composited;
+        stale
+    }
+
+    fn set_textures(&mut self, textures_delta: &egui::TexturesDelta) {
+        #[cfg(feature = "raster_stats")]
+        let start = std::time::Instant::now();
+        self.textures.write().set_textures(textures_delta);
         #[cfg(feature = "raster_stats")]
         {
             self.stats.set_textures = start.elapsed().as_secs_f32();
         }
     }
 
+    /// Frees the textures of the delta from this renderer's own store. A shared store is freed by its owner once every
+    /// renderer using it has rendered the frame, see `TextureStore::free_textures()`.
     fn free_textures(&mut self, textures_delta: &egui::TexturesDelta) {
-        for free in &textures_delta.free {
-            self.textures.remove(free);
+        if let Textures::Owned(store) = &mut self.textures {
+            store.free_textures(textures_delta);
         }
     }
 }
 
+/// Composites the cached primitives over a tile of the canvas, `sorted_prims` are their indices in z order.
+#[allow(clippy::too_many_arguments)]
 fn update_canvas_tile(
     simd_impl: AvailableImpl,
-    sorted_prim_cache: &[&CachedPrimitive],
+    prims: &[CachedPrimitive],
+    sorted_prims: &[usize],
     canvas: &mut BufferMutRef,
     tile_x: usize,
     tile_y: usize,
@@ -944,17 +1688,29 @@ fn update_canvas_tile(
     let tile_x_end = (tile_x_start + TILE_SIZE).min(canvas.width);
     let tile_y_end = (tile_y_start + TILE_SIZE).min(full_height);
 
-    // clear tile
-    for y in (tile_y_start - canvas_row_offset)..(tile_y_end - canvas_row_offset) {
-        let row_start = y * canvas.width;
-        let start = row_start + tile_x_start;
-        let end = row_start + tile_x_end;
-        canvas.data[start..end].fill([0; 4]);
+    let tile_n = [tile_x as u16, tile_y as u16];
+
+    // Prims below the topmost one that's opaque over the whole tile are hidden. Blending that one replaces whatever
+    // is in the tile, so the tile only needs to be cleared when there isn't one.
+    let first_visible = sorted_prims
+        .iter()
+        .rposition(|&idx| prims[idx].opaque_tiles.contains(&tile_n));
+
+    if first_visible.is_none() {
+        // clear tile
+        for y in (tile_y_start - canvas_row_offset)..(tile_y_end - canvas_row_offset) {
+            let row_start = y * canvas.width;
+            let start = row_start + tile_x_start;
+            let end = row_start + tile_x_end;
+            canvas.data[start..end].fill([0; 4]);
+        }
     }
 
-    let tile_n = [tile_x as u16, tile_y as u16];
     // redraw cached prims on tile
-    for prim in sorted_prim_cache {
+    for prim in sorted_prims[first_visible.unwrap_or(0)..]
+        .iter()
+        .map(|&idx| &prims[idx])
+    {
         if !prim.occupied_tiles.contains(&tile_n) {
             continue;
         }
@@ -971,33 +1727,34 @@ fn update_canvas_tile(
         max_x = max_x.min(tile_x_end).min(canvas.width);
         max_y = max_y.min(tile_y_end).min(canvas.height + canvas_row_offset);
 
-        let prim_buf = prim.get_buffer_ref();
-
         if max_x <= min_x || max_y <= min_y {
             continue;
         }
-        let prim_x_min = (min_x - prim.min_x).min(prim_buf.width);
-        let prim_x_max = (max_x - prim.min_x).min(prim_buf.width);
-
-        let get_ranges = |y: usize| -> (Range<usize>, Range<usize>) {
-            let canvas_row_start = (y - canvas_row_offset).min(canvas.height) * canvas.width;
-            let canvas_start = canvas_row_start + min_x;
-            let canvas_end = canvas_row_start + max_x;
-
-            let prim_y = (y - prim.min_y).min(prim_buf.height);
-            let prim_row_start = prim_y * prim_buf.width;
-            let prim_start = prim_row_start + prim_x_min;
-            let prim_end = prim_row_start + prim_x_max;
-
-            (canvas_start..canvas_end, prim_start..prim_end)
-        };
 
         dispatch_simd_impl!(simd_impl, |simd_impl| {
             for y in min_y..max_y {
-                let (canvas_slice, prim_slice) = get_ranges(y);
-                let src_row = &prim_buf.data[prim_slice];
-                let dst_row = &mut canvas.data[canvas_slice];
-                simd_impl.egui_blend_u8_slice(src_row, dst_row);
+                let canvas_row_start = (y - canvas_row_offset) * canvas.width;
+                let prim_y = y - prim.min_y;
+                let mut span_pixels_start = prim.row_pixel_starts[prim_y] as usize;
+
+                // Only the non-transparent runs of the row are stored and need to be blended
+                for &[start, end] in prim.row_spans(prim_y) {
+                    let span_start = prim.min_x + start as usize;
+                    let span_end = prim.min_x + end as usize;
+                    let span_pixels =
+                        span_pixels_start..span_pixels_start + (span_end - span_start);
+                    span_pixels_start = span_pixels.end;
+
+                    let start = span_start.max(min_x);
+                    let end = span_end.min(max_x);
+                    if start >= end {
+                        continue;
+                    }
+                    let src_row = &prim.pixels[span_pixels][start - span_start..end - span_start];
+                    let dst_row =
+                        &mut canvas.data[canvas_row_start + start..canvas_row_start + end];
+                    simd_impl.egui_blend_u8_slice(src_row, dst_row);
+                }
             }
         });
     }
@@ -1010,13 +1767,11 @@ struct Canvas {
     height: usize,
     width_extent: usize,
     height_extent: usize,
+    /// Number of the frame this holds the composite of, see `EguiSoftwareRender::tile_frames`
+    frame: u64,
 }
 
 impl Canvas {
-    fn clear(&mut self) {
-        self.data.iter_mut().for_each(|p| *p = [0; 4]);
-    }
-
     /// returns true if wasn't already the given size
     fn resize(&mut self, width: usize, height: usize) -> bool {
         if width != self.width || height != self.height {
@@ -1044,49 +1799,667 @@ impl Canvas {
         let range = self.get_range(start, end, y);
         &self.data[range]
     }
+
+    /// Moves the pixels of the given tiles (sorted indices) over by `offset`, each pixel of the tiles takes the pixel
+    /// `offset` away from it. Rows and tiles are moved in the order that reads every pixel before it's overwritten.
+    fn shift_tiles(&mut self, tiles: &[usize], tiles_x: usize, offset: I64Vec2) {
+        let width = self.width;
+        let height = self.height;
+        let data = &mut self.data;
+        let mut shift_tile_row = |row_tiles: &[usize]| {
+            let tile_y = row_tiles[0] / tiles_x;
+            let rows = tile_y * TILE_SIZE..((tile_y + 1) * TILE_SIZE).min(height);
+            let mut shift_row = |y: usize| {
+                let src_y = (y as i64 - offset.y) as usize;
+                let mut shift_span = |&tile_idx: &usize| {
+                    let x_start = (tile_idx % tiles_x) * TILE_SIZE;
+                    let x_end = (x_start + TILE_SIZE).min(width);
+                    let src_start = src_y * width + (x_start as i64 - offset.x) as usize;
+                    data.copy_within(
+                        src_start..src_start + (x_end - x_start),
+                        y * width + x_start,
+                    );
+                };
+                if offset.x > 0 {
+                    row_tiles.iter().rev().for_each(&mut shift_span);
+                } else {
+                    row_tiles.iter().for_each(&mut shift_span);
+                }
+            };
+            if offset.y > 0 {
+                rows.rev().for_each(&mut shift_row);
+            } else {
+                rows.for_each(&mut shift_row);
+            }
+        };
+        let tile_rows = tiles.chunk_by(|a, b| a / tiles_x == b / tiles_x);
+        if offset.y > 0 {
+            tile_rows.rev().for_each(&mut shift_tile_row);
+        } else {
+            tile_rows.for_each(&mut shift_tile_row);
+        }
+    }
+
+    /// Draw canvas alpha over buffer, only writing tiles that have `OCCUPIED_TILE_MASK` set in `tile_masks`.
+    fn blit_tiles(
+        &self,
+        simd_impl: AvailableImpl,
+        tile_masks: &[u8],
+        tiles_x: usize,
+        buffer: &mut BufferMutRef,
+    ) {
+        let width = self.width;
+        let height = self.height;
+        assert_eq!(self.data.len(), width * height);
+        assert_eq!(buffer.data.len(), width * height);
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::{
+                iter::{IndexedParallelIterator, ParallelIterator},
+                slice::ParallelSliceMut,
+            };
+            // blit rows of tiles in parallel
+
+            let width = buffer.width;
+            let px_per_row_of_tiles = width * TILE_SIZE;
+
+            buffer
+                .data
+                .par_chunks_mut(px_per_row_of_tiles)
+                .enumerate()
+                .for_each(|(tile_row, tile_height_row)| {
+                    let height = tile_height_row.len() / width; // Might be less than TILE_SIZE
+                    let buffer_tile_row = &mut BufferMutRef::new(tile_height_row, width, height);
+
+                    for (tile_idx, &mask) in tile_masks.iter().enumerate() {
+                        if mask & EguiSoftwareRender::OCCUPIED_TILE_MASK == 0 {
+                            continue;
+                        }
+
+                        let tile_y = tile_idx / tiles_x;
+                        if tile_y != tile_row {
+                            continue;
+                        }
+
+                        let tile_x = tile_idx % tiles_x;
+
+                        let x_start = tile_x * TILE_SIZE;
+                        let y_start = 0;
+                        let x_end = (x_start + TILE_SIZE).min(width);
+                        let y_end = TILE_SIZE.min(height);
+
+                        let canvas_row_offset = tile_row * TILE_SIZE;
+
+                        dispatch_simd_impl!(simd_impl, |simd_impl| self.blit_tile(
+                            simd_impl,
+                            buffer_tile_row,
+                            x_start,
+                            y_start,
+                            x_end,
+                            y_end,
+                            canvas_row_offset,
+                        ));
+                    }
+                });
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (tile_idx, &mask) in tile_masks.iter().enumerate() {
+                if mask & EguiSoftwareRender::OCCUPIED_TILE_MASK == 0 {
+                    continue;
+                }
+
+                let tile_x = tile_idx % tiles_x;
+                let tile_y = tile_idx / tiles_x;
+
+                let x_start = tile_x * TILE_SIZE;
+                let y_start = tile_y * TILE_SIZE;
+                let x_end = (x_start + TILE_SIZE).min(width);
+                let y_end = (y_start + TILE_SIZE).min(height);
+
+                dispatch_simd_impl!(simd_impl, |simd_impl| self
+                    .blit_tile(simd_impl, buffer, x_start, y_start, x_end, y_end, 0));
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn blit_tile(
+        &self,
+        simd_impl: impl SelectedImpl,
+        buffer: &mut BufferMutRef,
+        x_start: usize,
+        y_start: usize,
+        x_end: usize,
+        y_end: usize,
+        canvas_row_offset: usize,
+    ) {
+        for y in y_start..y_end {
+            let src_row = self.get_span(x_start, x_end, y + canvas_row_offset);
+            let dst_row = &mut buffer.get_mut_span(x_start, x_end, y);
+            simd_impl.egui_blend_u8_slice(src_row, dst_row);
+        }
+    }
+}
+
+/// Max number of buffers of rasterized primitives kept in `FrameScratch::raster_buffers`. Each holds the dense pixels
+/// of a whole primitive, so only a few are kept.
+const MAX_POOLED_RASTER_BUFFERS: usize = 16;
+
+/// Max number of evicted primitives kept in `FrameScratch::free_prims`
+const MAX_FREE_PRIMS: usize = 64;
+
+/// Max number of keys tried for a primitive whose cache key collides, see `EguiSoftwareRender::probe_cache_key()`
+const MAX_CACHE_KEY_PROBES: usize = 8;
+
+/// Allocations kept between frames, so rendering a UI that doesn't change doesn't allocate.
+#[derive(Default)]
+struct FrameScratch {
+    prim_lookups: Vec<PrimLookup>,
+    raster_jobs: Vec<RasterJob>,
+    /// Buffers of finished `RasterJob`s
+    raster_buffers: Vec<RasterBuffers>,
+    /// Primitives that were evicted from the cache, reused for new ones
+    free_prims: Vec<CachedPrimitive>,
+    unused_prims: Vec<u64>,
+    /// Indices of the cached primitives in z order, for compositing
+    sorted_prims: Vec<usize>,
+    /// `CachedPrimitive::scroll_candidate_key()` and index of the cached primitives that have tri keys, sorted
+    scroll_candidates: Vec<(u64, usize)>,
+    /// Scrolled primitives whose tiles may be shifted, see `EguiSoftwareRender::shift_scrolled_tiles()`
+    scroll_shifts: Vec<ScrollShift>,
+    shifted_tiles: Vec<usize>,
+    /// Indices of the cached primitives near a scrolled one
+    overlapping_prims: Vec<usize>,
+    /// Edges of a mesh drawn with coverage AA when rendering without caching
+    edges: EdgeScratch,
+    /// Draw commands of each paint job when rendering without caching
+    #[cfg(feature = "rayon")]
+    mesh_cmds: Vec<MeshDrawCmds>,
+    #[cfg(feature = "rayon")]
+    bins: BinScratch,
+}
+
+/// Result of looking up a ClippedPrimitive in the primitive cache
+enum PrimLookup {
+    /// The cached primitive is reused at a new position and z order
+    Hit {
+        hash: u64,
+        z_order: usize,
+        min_x: usize,
+        min_y: usize,
+    },
+    Miss(PrimMiss),
+    /// Nothing to render
+    None,
+}
+
+/// A ClippedPrimitive that needs to be rasterized into a new cached primitive
+#[derive(Clone, Copy)]
+struct PrimMiss {
+    hash: u64,
+    fingerprint: MeshFingerprint,
+    /// The primitive's own cache key was taken by a primitive with another fingerprint
+    collided: bool,
+    /// Index of the paint job
+    z_order: usize,
+    px_clip_rect: egui::Rect,
+    /// Clip rect relative to `cropped_min`
+    clip_rect: egui::Rect,
+    /// Pixel space bounds of the mesh
+    mesh_rect: egui::Rect,
+    cropped_min: Vec2,
+    /// Uses a procedural texture, so pixels of a previous frame can't be reused by scrolling
+    procedural: bool,
+    texture_generation: u64,
+    min_x: usize,
+    min_y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Buffers for rasterizing a new cached primitive
+#[derive(Default)]
+struct RasterBuffers {
+    px_mesh: Mesh,
+    /// Dense pixels of the primitive, only the non-transparent runs are kept in the cached primitive
+    pixels: Vec<[u8; 4]>,
+    scroll: ScrollScratch,
+    edges: EdgeScratch,
+    /// Draw commands and bins of a primitive large enough to be rasterized with `draw_binned()`
+    #[cfg(feature = "rayon")]
+    mesh_cmds: MeshDrawCmds,
+    #[cfg(feature = "rayon")]
+    bins: BinScratch,
+}
+
+/// Rasterizes a `PrimMiss` into buffers taken from the `FrameScratch` pools
+struct RasterJob {
+    miss: PrimMiss,
+    buffers: RasterBuffers,
+    prim: CachedPrimitive,
+    /// Cache key of the previous primitive that this one was scrolled from and mostly copied from
+    scroll: Option<(u64, Scroll)>,
+}
+
+/// A scrolled primitive whose canvas tiles may be shifted instead of composited
+struct ScrollShift {
+    key: u64,
+    /// Cache key of the primitive it was scrolled from
+    prev_key: u64,
+    scroll: Scroll,
+}
+
+/// Pixel space bounds of the vertices of a mesh
+fn px_mesh_bounds(mesh: &Mesh, pixels_per_point: f32) -> (Vec2, Vec2) {
+    mesh.vertices.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(-f32::MAX)),
+        |(min, max), v| {
+            let pos = (v.pos * pixels_per_point).to_vec2();
+            (min.min(pos), max.max(pos))
+        },
+    )
+}
+
+/// Key of a ClippedPrimitive in the primitive cache, from its clip rect relative to the cropped pixel space mesh, its
+/// texture and its mesh scaled to pixel space.
+fn primitive_cache_key(clip_rect: &egui::Rect, mesh: &Mesh, pixels_per_point: f32) -> u64 {
+    let mut hasher = Hash64::new_fnv();
+
+    hasher.hash_wrap(clip_rect.min.x.to_bits() as u64 | (clip_rect.min.y.to_bits() as u64) << 32);
+    hasher.hash_wrap(clip_rect.max.x.to_bits() as u64 | (clip_rect.max.y.to_bits() as u64) << 32);
+    match mesh.texture_id {
+        egui::TextureId::Managed(id) => hasher.hash_wrap(id),
+        egui::TextureId::User(id) => hasher.hash_wrap(!id),
+    }
+    for ind in &mesh.indices {
+        let v = mesh.vertices[*ind as usize];
+        let pos = v.pos * pixels_per_point;
+
+        // Tried to do this to avoid full redraws when moving a window but it was resulting in some
+        // meshes to be matches incorrectly in the ui gradient portion of the egui color test:
+        //let pos = v.pos - cropped_min;
+
+        // It's much faster to not wrap for every field. Hash64 rotates so the order of the fields is preserved.
+        hasher.hash(pos.x.to_bits() as u64 | (pos.y.to_bits() as u64) << 32);
+        hasher.hash(v.uv.x.to_bits() as u64 | (v.uv.y.to_bits() as u64) << 32);
+        hasher.hash(u32::from_le_bytes(v.color.to_array()) as u64);
+        hasher.fnv_wrap();
+    }
+    hasher.hash_wrap(mesh.indices.len() as u64);
+    hasher.finalize()
+}
+
+/// Key following `key` in the probe sequence of a primitive whose key collided with another primitive's
+fn next_cache_key(key: u64, fingerprint: &MeshFingerprint) -> u64 {
+    let mut hasher = Hash64::new_fnv();
+    hasher.hash_wrap(key);
+    hasher.hash_wrap(fingerprint.vertices as u64 | (fingerprint.indices as u64) << 32);
+    hasher.hash_wrap(fingerprint.hash as u64);
+    hasher.finalize()
+}
+
+/// Compact summary of a ClippedPrimitive that's computed independently from its cache key, so two different primitives
+/// whose keys collide are very unlikely to also have the same fingerprint. Uses the mesh in points, as the cache only
+/// holds primitives of a single pixels_per_point.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct MeshFingerprint {
+    vertices: u32,
+    indices: u32,
+    hash: u32,
+}
+
+impl MeshFingerprint {
+    fn new(clip_rect: &egui::Rect, mesh: &Mesh) -> Self {
+        let mut hasher = Hash32::new_fnv();
+        hasher.hash_wrap(clip_rect.max.x.to_bits());
+        hasher.hash_wrap(clip_rect.max.y.to_bits());
+        let (egui::TextureId::Managed(id) | egui::TextureId::User(id)) = mesh.texture_id;
+        hasher.hash_wrap(matches!(mesh.texture_id, egui::TextureId::User(_)) as u32);
+        hasher.hash_wrap(id as u32);
+        hasher.hash_wrap((id >> 32) as u32);
+        for v in &mesh.vertices {
+            hasher.hash_wrap(v.pos.x.to_bits());
+            hasher.hash_wrap(v.pos.y.to_bits());
+            hasher.hash_wrap(v.uv.x.to_bits());
+            hasher.hash_wrap(v.uv.y.to_bits());
+            hasher.hash_wrap(u32::from_le_bytes(v.color.to_array()));
+        }
+        for ind in &mesh.indices {
+            hasher.hash_wrap(*ind);
+        }
+        MeshFingerprint {
+            vertices: mesh.vertices.len() as u32,
+            indices: mesh.indices.len() as u32,
+            hash: hasher.finalize(),
+        }
+    }
+}
+
+/// Cached primitives by their cache key. They're stored densely so compositing can refer to them by index.
+#[derive(Default)]
+struct PrimCache {
+    prims: Vec<CachedPrimitive>,
+    /// Cache key of each of `prims`
+    keys: Vec<u64>,
+    /// Index into `prims` of each cache key
+    indices: HashMap<u64, usize>,
+}
+
+impl PrimCache {
+    fn get(&self, key: &u64) -> Option<&CachedPrimitive> {
+        self.indices.get(key).map(|&idx| &self.prims[idx])
+    }
+
+    fn get_mut(&mut self, key: &u64) -> Option<&mut CachedPrimitive> {
+        self.indices.get(key).map(|&idx| &mut self.prims[idx])
+    }
+
+    /// Inserts the primitive, returning the one it replaced
+    fn insert(&mut self, key: u64, prim: CachedPrimitive) -> Option<CachedPrimitive> {
+        match self.indices.get(&key) {
+            Some(&idx) => Some(core::mem::replace(&mut self.prims[idx], prim)),
+            None => {
+                self.indices.insert(key, self.prims.len());
+                self.prims.push(prim);
+                self.keys.push(key);
+                None
+            }
+        }
+    }
+
+    /// Removes the primitive, the last primitive takes its index
+    fn remove(&mut self, key: &u64) -> Option<CachedPrimitive> {
+        let idx = self.indices.remove(key)?;
+        self.keys.swap_remove(idx);
+        if let Some(&moved) = self.keys.get(idx) {
+            self.indices.insert(moved, idx);
+        }
+        Some(self.prims.swap_remove(idx))
+    }
+
+    /// Removes every primitive, keeping the allocations of the cache
+    fn drain(&mut self) -> impl Iterator<Item = CachedPrimitive> + '_ {
+        self.indices.clear();
+        self.keys.clear();
+        self.prims.drain(..)
+    }
+
+    fn values(&self) -> &[CachedPrimitive] {
+        &self.prims
+    }
+
+    fn values_mut(&mut self) -> &mut [CachedPrimitive] {
+        &mut self.prims
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&u64, &CachedPrimitive)> {
+        self.keys.iter().zip(&self.prims)
+    }
 }
 
 /// A region of cached rendered image data that corresponds to a ClippedPrimitive.
 pub struct CachedPrimitive {
-    buffer: Vec<[u8; 4]>,
+    /// Pixels of the `row_spans`, packed one after the other. Fully transparent pixels outside the spans aren't stored.
+    pixels: Vec<[u8; 4]>,
     min_x: usize,
     min_y: usize,
     width: usize,
     height: usize,
     z_order: usize,
+    /// z_order in the previous frame, set when the primitive is reused
+    prev_z_order: usize,
     seen_this_frame: bool,
     rendered_this_frame: bool,
+    /// Its canvas tiles are marked by `EguiSoftwareRender::shift_scrolled_tiles()` this frame
+    scroll_shift: bool,
+    /// Rasterized this frame to replace a primitive with the same cache key, whose pixels may differ
+    replaced_prim: bool,
     occupied_tiles: Vec<[u16; 2]>,
+    /// Occupied tiles that this primitive fully covers with opaque pixels
+    opaque_tiles: Vec<[u16; 2]>,
+    /// Runs of pixels that aren't fully transparent as [start, end) x relative to min_x. The runs of row y are
+    /// `row_spans[row_span_starts[y]..row_span_starts[y + 1]]`
+    row_spans: Vec<[u16; 2]>,
+    row_span_starts: Vec<u32>,
+    /// Index into `pixels` of the first pixel of each row's spans
+    row_pixel_starts: Vec<u32>,
+    /// Pixel space clip rect, texture and tris of the mesh, used to detect when a new primitive is a scrolled version
+    /// of this one. `tri_keys` is empty if scroll detection is disabled or the mesh fits inside its clip rect.
+    clip_rect: egui::Rect,
+    texture_id: egui::TextureId,
+    tri_keys: Vec<TriKey>,
+    /// `EguiTexture::generation` of the texture when it was rasterized, it's rasterized again once the texture is
+    /// streamed into
+    texture_generation: u64,
+    /// Only set with cache verification, see `EguiSoftwareRender::with_cache_verification()`
+    fingerprint: MeshFingerprint,
 }
 
 impl CachedPrimitive {
-    fn get_buffer_ref(&self) -> BufferRef<'_> {
-        BufferRef {
-            data: &self.buffer,
-            width: self.width,
-            height: self.height,
-            width_extent: self.width - 1,
-            height_extent: self.height - 1,
-        }
-    }
-
-    fn new(min_x: usize, min_y: usize, width: usize, height: usize, z_order: usize) -> Self {
+    /// An empty primitive, see `CachedPrimitive::reset()`
+    fn new() -> Self {
         CachedPrimitive {
-            buffer: vec![[0; 4]; width * height],
-            min_x,
-            min_y,
-            width,
-            height,
-            z_order,
+            pixels: Vec::new(),
+            min_x: 0,
+            min_y: 0,
+            width: 0,
+            height: 0,
+            z_order: 0,
+            prev_z_order: 0,
             seen_this_frame: true,
             rendered_this_frame: true,
-            occupied_tiles: Vec::with_capacity(64),
+            scroll_shift: false,
+            replaced_prim: false,
+            occupied_tiles: Vec::new(),
+            opaque_tiles: Vec::new(),
+            row_spans: Vec::new(),
+            row_span_starts: Vec::new(),
+            row_pixel_starts: Vec::new(),
+            clip_rect: egui::Rect::NOTHING,
+            texture_id: Default::default(),
+            tri_keys: Vec::new(),
+            texture_generation: 0,
+            fingerprint: Default::default(),
         }
     }
 
-    fn update_occupied_tiles(&mut self, tiles_wide: usize, tiles_tall: usize) {
-        // list which tiles contain a pixel with that isn't fully transparent (also containing not color info)
+    /// Makes this a new primitive with the given bounds and no pixels, keeping the allocations of its buffers.
+    fn reset(&mut self, min_x: usize, min_y: usize, width: usize, height: usize, z_order: usize) {
+        self.min_x = min_x;
+        self.min_y = min_y;
+        self.width = width;
+        self.height = height;
+        self.z_order = z_order;
+        self.prev_z_order = z_order;
+        self.seen_this_frame = true;
+        self.rendered_this_frame = true;
+        self.scroll_shift = false;
+        self.replaced_prim = false;
+        self.pixels.clear();
         self.occupied_tiles.clear();
+        self.opaque_tiles.clear();
+        self.row_spans.clear();
+        self.row_span_starts.clear();
+        self.row_pixel_starts.clear();
+        self.clip_rect = egui::Rect::NOTHING;
+        self.texture_id = Default::default();
+        self.tri_keys.clear();
+        self.texture_generation = 0;
+        self.fingerprint = Default::default();
+    }
+
+    /// Canvas pixel bounds of the primitive (max exclusive)
+    fn bounds(&self) -> [I64Vec2; 2] {
+        [
+            i64vec2(self.min_x as i64, self.min_y as i64),
+            i64vec2(
+                (self.min_x + self.width) as i64,
+                (self.min_y + self.height) as i64,
+            ),
+        ]
+    }
+
+    /// Key shared by the primitives with the same clip rect and texture, which a scrolled primitive has in common with
+    /// the one it was scrolled from.
+    fn scroll_candidate_key(&self) -> u64 {
+        let mut hasher = Hash64::new_fnv();
+        let rect = self.clip_rect;
+        hasher.hash_wrap(rect.min.x.to_bits() as u64 | (rect.min.y.to_bits() as u64) << 32);
+        hasher.hash_wrap(rect.max.x.to_bits() as u64 | (rect.max.y.to_bits() as u64) << 32);
+        match self.texture_id {
+            egui::TextureId::Managed(id) => hasher.hash_wrap(id),
+            egui::TextureId::User(id) => hasher.hash_wrap(!id),
+        }
+        hasher.hash_wrap(self.texture_generation);
+        hasher.finalize()
+    }
+
+    /// The color of every pixel of this primitive within `rect` (canvas pixels, max exclusive), pixels outside of its
+    /// runs are transparent. None if they aren't all the same.
+    fn uniform_color(&self, rect: [I64Vec2; 2]) -> Option<[u8; 4]> {
+        let mut color = None;
+        let mut same = |px: [u8; 4]| *color.get_or_insert(px) == px;
+        let [min, max] = rect;
+        for y in min.y..max.y {
+            let prim_y = y - self.min_y as i64;
+            if prim_y < 0 || prim_y >= self.height as i64 {
+                if !same([0; 4]) {
+                    return None;
+                }
+                continue;
+            }
+            let mut x = min.x;
+            for (start, span) in self.row_span_pixels(prim_y as usize) {
+                let span_start = (self.min_x + start) as i64;
+                let span_end = span_start + span.len() as i64;
+                if span_end <= x {
+                    continue;
+                }
+                if span_start >= max.x {
+                    break;
+                }
+                if span_start > x && !same([0; 4]) {
+                    return None;
+                }
+                let start = span_start.max(x);
+                let end = span_end.min(max.x);
+                if !span[(start - span_start) as usize..(end - span_start) as usize]
+                    .iter()
+                    .all(|&px| same(px))
+                {
+                    return None;
+                }
+                x = end;
+            }
+            if x < max.x && !same([0; 4]) {
+                return None;
+            }
+        }
+        color
+    }
+
+    /// Copies the pixels of this primitive that land in `scroll.overlap` once shifted by `scroll.offset` into `dst`,
+    /// which covers the canvas starting at `dst_min`.
+    fn copy_shifted_pixels(&self, scroll: &Scroll, dst: &mut BufferMutRef, dst_min: I64Vec2) {
+        let [min, max] = scroll.overlap;
+        for y in min.y..max.y {
+            let src_y = (y - scroll.offset.y) as usize - self.min_y;
+            let dst_y = (y - dst_min.y) as usize;
+            let mut span_pixels_start = self.row_pixel_starts[src_y] as usize;
+            for &[start, end] in self.row_spans(src_y) {
+                let span_len = (end - start) as usize;
+                let span_pixels = span_pixels_start..span_pixels_start + span_len;
+                span_pixels_start = span_pixels.end;
+
+                // Canvas x of the span once shifted
+                let span_start = (self.min_x + start as usize) as i64 + scroll.offset.x;
+                let start = span_start.max(min.x);
+                let end = (span_start + span_len as i64).min(max.x);
+                if start >= end {
+                    continue;
+                }
+                let src = &self.pixels[span_pixels]
+                    [(start - span_start) as usize..(end - span_start) as usize];
+                dst.get_mut_span(
+                    (start - dst_min.x) as usize,
+                    (end - dst_min.x) as usize,
+                    dst_y,
+                )
+                .copy_from_slice(src);
+            }
+        }
+    }
+
+    /// Non-transparent runs of pixels in row y, see `CachedPrimitive::row_spans`
+    #[inline(always)]
+    fn row_spans(&self, y: usize) -> &[[u16; 2]] {
+        &self.row_spans[self.row_span_starts[y] as usize..self.row_span_starts[y + 1] as usize]
+    }
+
+    /// Stores the non-transparent runs of the rendered pixels (`width * height`) and updates which tiles they occupy.
+    fn set_pixels(&mut self, pixels: &[[u8; 4]], canvas_width: usize, canvas_height: usize) {
+        self.update_row_spans(pixels);
+        self.update_occupied_tiles(canvas_width, canvas_height);
+    }
+
+    /// Non-transparent runs of pixels in row y with their start x relative to min_x
+    #[inline(always)]
+    fn row_span_pixels(&self, y: usize) -> impl Iterator<Item = (usize, &[[u8; 4]])> {
+        let mut span_pixels_start = self.row_pixel_starts[y] as usize;
+        self.row_spans(y).iter().map(move |&[start, end]| {
+            let span_pixels = span_pixels_start..span_pixels_start + (end - start) as usize;
+            span_pixels_start = span_pixels.end;
+            (start as usize, &self.pixels[span_pixels])
+        })
+    }
+
+    fn update_row_spans(&mut self, pixels: &[[u8; 4]]) {
+        self.row_spans.clear();
+        self.row_span_starts.clear();
+        self.row_span_starts.push(0);
+        self.row_pixel_starts.clear();
+        self.pixels.clear();
+        for row in pixels.chunks_exact(self.width) {
+            let row_first_span = self.row_spans.len();
+            let mut x = 0;
+            while let Some(start) = row[x..].iter().position(|px| *px != [0; 4]) {
+                let start = x + start;
+                let end = row[start..]
+                    .iter()
+                    .position(|px| *px == [0; 4])
+                    .map_or(row.len(), |end| start + end);
+
+                // Short transparent gaps are cheaper to blend than to skip
+                if let Some(last) = self.row_spans[row_first_span..]
+                    .last_mut()
+                    .filter(|last| start - last[1] as usize <= SPAN_MERGE_GAP)
+                {
+                    last[1] = end as u16;
+                } else {
+                    self.row_spans.push([start as u16, end as u16]);
+                }
+                x = end;
+            }
+            self.row_span_starts.push(self.row_spans.len() as u32);
+
+            self.row_pixel_starts.push(self.pixels.len() as u32);
+            for &[start, end] in &self.row_spans[row_first_span..] {
+                self.pixels
+                    .extend_from_slice(&row[start as usize..end as usize]);
+            }
+        }
+    }
+
+    /// Only depends on the stored runs of pixels, so it can be updated for a new canvas size without rendering again.
+    fn update_occupied_tiles(&mut self, canvas_width: usize, canvas_height: usize) {
+        // list which tiles contain a pixel with that isn't fully transparent (also containing not color info), and
+        // which of those are entirely covered by opaque pixels
+        self.occupied_tiles.clear();
+        self.opaque_tiles.clear();
+        let tiles_wide = canvas_width.div_ceil(TILE_SIZE);
+        let tiles_tall = canvas_height.div_ceil(TILE_SIZE);
         let max_x = self.min_x + self.width;
         let max_y = self.min_y + self.height;
         let first_tile_x = (self.min_x / TILE_SIZE).min(tiles_wide);
@@ -1095,26 +2468,52 @@ impl CachedPrimitive {
         let last_tile_y = max_y.div_ceil(TILE_SIZE).min(tiles_tall);
 
         for tile_y in first_tile_y..last_tile_y {
-            let mut px_start_y = (tile_y * TILE_SIZE).max(self.min_y);
+            let tile_start_y = tile_y * TILE_SIZE;
+            let tile_end_y = (tile_start_y + TILE_SIZE).min(canvas_height);
+            let mut px_start_y = tile_start_y.max(self.min_y);
             let mut px_end_y = (px_start_y + TILE_SIZE).min(max_y);
             px_start_y -= self.min_y;
             px_end_y -= self.min_y;
             for tile_x in first_tile_x..last_tile_x {
-                let mut px_start_x = (tile_x * TILE_SIZE).max(self.min_x);
+                let tile_start_x = tile_x * TILE_SIZE;
+                let tile_end_x = (tile_start_x + TILE_SIZE).min(canvas_width);
+                let mut px_start_x = tile_start_x.max(self.min_x);
                 let mut px_end_x = (px_start_x + TILE_SIZE).min(max_x);
                 px_start_x -= self.min_x;
                 px_end_x -= self.min_x;
 
-                'px_outer: for y in px_start_y..px_end_y {
-                    for x in px_start_x..px_end_x {
-                        // Purposefully panicing when out of bounds. If it's out of bounds then the math is wrong and
-                        // the tile is not being calculated correctly.
-                        if u32::from_le_bytes(self.buffer[x + y * self.width]) > 0 {
-                            self.occupied_tiles.push([tile_x as u16, tile_y as u16]);
-                            break 'px_outer;
-                        }
+                let covers_tile = self.min_x <= tile_start_x
+                    && self.min_y <= tile_start_y
+                    && max_x >= tile_end_x
+                    && max_y >= tile_end_y;
+
+                let mut occupied = false;
+                let mut opaque = covers_tile;
+                for y in px_start_y..px_end_y {
+                    occupied |= self.row_spans(y).iter().any(|&[start, end]| {
+                        (start as usize) < px_end_x && end as usize > px_start_x
+                    });
+                    if opaque {
+                        // Opaque pixels are never transparent, so they must all be in the same run
+                        opaque = self.row_span_pixels(y).any(|(start, span)| {
+                            start <= px_start_x
+                                && start + span.len() >= px_end_x
+                                && span[px_start_x - start..px_end_x - start]
+                                    .iter()
+                                    .all(|px| px[3] == 255)
+                        });
+                    }
+                    if occupied && !opaque {
+                        break;
                     }
                 }
+
+                if occupied {
+                    self.occupied_tiles.push([tile_x as u16, tile_y as u16]);
+                }
+                if opaque {
+                    self.opaque_tiles.push([tile_x as u16, tile_y as u16]);
+                }
             }
         }
     }
@@ -19,7 +19,7 @@ pub fn raster_tri<const SUBPIX_BITS: i32>(
     mut raster: impl FnMut(i64, i64),
 ) {
     let Some((ss_min, ss_max, _sp_inv_area, mut stepper)) =
-        SingleStepper::from_ss_tri_backface_cull::<SUBPIX_BITS>(ss_bounds, ss_tri)
+        SingleStepper::from_ss_tri_backface_cull::<SUBPIX_BITS>(ss_bounds, ss_tri, false)
     else {
         return;
     };
@@ -54,10 +54,12 @@ pub struct SingleStepper {
 
 impl SingleStepper {
     /// For the given subpixel resolution, calculate the screen space bounds, subpixel inverse area, and subpixel Stepper.
+    /// If `conservative` the bounds include every pixel the tri overlaps, not just those whose center it may cover.
     /// returns: ss_min, ss_max, sp_inv_area, stepper
     pub fn from_ss_tri_backface_cull<const SUBPIX_BITS: i32>(
         ss_bounds: [I64Vec2; 2],
         ss_tri: &[Vec2; 3],
+        conservative: bool,
     ) -> Option<(I64Vec2, I64Vec2, f32, SingleStepper)> {
         let subpix_bits = SUBPIX_BITS as u32;
         let subpix: i64 = 1 << subpix_bits;
@@ -80,7 +82,12 @@ impl SingleStepper {
         let ss_min = ((sp_min - subpix_half) >> subpix_bits)
             .max(ss_bounds[0])
             .min(ss_bounds[1]);
-        let ss_max = ((sp_max + subpix_half) >> subpix_bits)
+        let sp_max_round = if conservative {
+            subpix - 1
+        } else {
+            subpix_half
+        };
+        let ss_max = ((sp_max + sp_max_round) >> subpix_bits)
             .max(ss_bounds[0])
             .min(ss_bounds[1]);
 
@@ -182,13 +189,10 @@ impl SingleStepper {
         let step_x = attr_1x - attr_tl;
         let step_y = attr_1y - attr_tl;
 
-        let row = attr_tl;
-
         AttributeStepper {
             step_x,
             step_y,
-            row,
-            attr: attr_tl,
+            row: attr_tl,
         }
     }
 }
@@ -235,7 +239,6 @@ where
     pub step_x: T,
     pub step_y: T,
     pub row: T,
-    pub attr: T,
 }
 
 impl<T> AttributeStepper<T>
@@ -247,13 +250,9 @@ where
         self.row += self.step_y;
     }
 
+    /// Attribute value `col` steps along x from the start of the current row
     #[inline(always)]
-    pub fn col_step(&mut self) {
-        self.attr += self.step_x;
-    }
-
-    #[inline(always)]
-    pub fn row_start(&mut self) {
-        self.attr = self.row;
+    pub fn at_col(&self, col: i64) -> T {
+        self.row + self.step_x * col as f32
     }
 }
@@ -0,0 +1,211 @@
+use crate::{
+    BufferMutRef, ColorFieldOrder,
+    color::SelectedImpl,
+    egui_texture::{EguiTexture, TexelFormat},
+    math::i64vec2::{I64Vec2, i64vec2},
+    raster::{
+        rect::blend_yuv_row,
+        span::{SAMPLE_CHUNK, chunk_end},
+    },
+    render::Scissor,
+};
+
+/// Max number of glyphs batched into one `GlyphRun`. Keeps runs about as wide as a tile, so binning them doesn't make
+/// many tiles iterate glyphs they don't overlap.
+pub const GLYPH_RUN_MAX: usize = 8;
+
+/// FreeType's default LCD filter, applied to subpixel coverage. Weights sum to 256.
+const LCD_FILTER: [u16; 5] = [8, 77, 86, 77, 8];
+
+/// A glyph quad whose texels line up 1:1 with pixels, so it can be blended without sampling.
+#[derive(Clone, Copy, Default)]
+pub struct Glyph {
+    /// Pixels [min, max) drawn, already clipped. With LCD text this includes a pixel to each side of the glyph, which
+    /// the filter spreads coverage into.
+    pub px_min: [u32; 2],
+    pub px_max: [u32; 2],
+    /// Texel minus pixel coordinates
+    pub tex_offset: [i32; 2],
+    /// Pixel columns [min, max) of the glyph itself, unclipped. Texels outside of them are treated as uncovered.
+    pub cols: [i32; 2],
+}
+
+/// Consecutive glyph quads of a mesh that share the font texture and a constant tint.
+pub struct GlyphRun {
+    pub color: [u8; 4],
+    /// Output field order, if the glyphs are drawn as LCD text with subpixel AA
+    pub lcd: Option<ColorFieldOrder>,
+    pub glyphs: [Glyph; GLYPH_RUN_MAX],
+    pub len: usize,
+    /// Union of the glyph pixel bounds (max exclusive)
+    pub bounds: [I64Vec2; 2],
+}
+
+impl GlyphRun {
+    pub fn push(&mut self, glyph: Glyph) {
+        let [min, max] = [glyph.px_min, glyph.px_max].map(|p| i64vec2(p[0] as i64, p[1] as i64));
+        if self.len == 0 {
+            self.bounds = [min, max];
+        } else {
+            self.bounds = [self.bounds[0].min(min), self.bounds[1].max(max)];
+        }
+        self.glyphs[self.len] = glyph;
+        self.len += 1;
+    }
+}
+
+pub fn draw_glyph_run(
+    simd_impl: impl SelectedImpl,
+    buffer: &mut BufferMutRef,
+    texture: &EguiTexture,
+    run: &GlyphRun,
+    scissor: &Scissor,
+) {
+    simd_impl.dispatch(|simd_impl| draw_glyph_run_impl(simd_impl, buffer, texture, run, scissor))
+}
+
+#[inline]
+fn draw_glyph_run_impl(
+    simd_impl: impl SelectedImpl,
+    buffer: &mut BufferMutRef,
+    texture: &EguiTexture,
+    run: &GlyphRun,
+    scissor: &Scissor,
+) {
+    let (buffer_x, buffer_y) = (scissor.buffer_x, scissor.buffer_y);
+    let [scissor_min, scissor_max] = scissor.bounds;
+
+    for glyph in &run.glyphs[..run.len] {
+        let min_x = (glyph.px_min[0] as i64).max(scissor_min.x);
+        let min_y = (glyph.px_min[1] as i64).max(scissor_min.y);
+        let max_x = (glyph.px_max[0] as i64).min(scissor_max.x);
+        let max_y = (glyph.px_max[1] as i64).min(scissor_max.y);
+        if max_x - min_x <= 0 || max_y - min_y <= 0 {
+            continue;
+        }
+        let (min_x, min_y, max_x, max_y) = (
+            min_x as usize,
+            min_y as usize,
+            max_x as usize,
+            max_y as usize,
+        );
+
+        if let Some(field_order) = run.lcd {
+            draw_lcd_glyph(
+                simd_impl,
+                buffer,
+                texture,
+                run,
+                glyph,
+                field_order,
+                [min_x, max_x],
+                [min_y, max_y],
+                [buffer_x, buffer_y],
+            );
+            continue;
+        }
+
+        let tex_x = (min_x as i64 + glyph.tex_offset[0] as i64) as usize;
+        let tex_y = (min_y as i64 + glyph.tex_offset[1] as i64) as usize;
+        for (y, tex_row) in (min_y..max_y).zip(tex_y..) {
+            let tex_start = tex_row * texture.width + tex_x;
+            let tex_end = tex_start + max_x - min_x;
+            let dst = buffer.get_mut_span(min_x - buffer_x, max_x - buffer_x, y - buffer_y);
+            match texture.format {
+                TexelFormat::Alpha => simd_impl.egui_blend_u8_slice_coverage(
+                    run.color,
+                    &texture.alpha[tex_start..tex_end],
+                    dst,
+                ),
+                TexelFormat::Color => simd_impl.egui_blend_u8_slice_tinted(
+                    &texture.data[tex_start..tex_end],
+                    run.color,
+                    dst,
+                ),
+                TexelFormat::Yuv => {
+                    blend_yuv_row(simd_impl, texture, [tex_x, tex_row], run.color, dst)
+                }
+            }
+        }
+    }
+}
+
+/// Draws the rows [min_y, max_y) of a glyph with subpixel AA. The glyph's coverage is sampled at 3x horizontal
+/// resolution, one sample per subpixel, then filtered to reduce color fringes. Red is taken to be the left subpixel.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn draw_lcd_glyph(
+    simd_impl: impl SelectedImpl,
+    buffer: &mut BufferMutRef,
+    texture: &EguiTexture,
+    run: &GlyphRun,
+    glyph: &Glyph,
+    field_order: ColorFieldOrder,
+    [min_x, max_x]: [usize; 2],
+    [min_y, max_y]: [usize; 2],
+    [buffer_x, buffer_y]: [usize; 2],
+) {
+    // Coverage of the texels landing on the pixels [x - 2, end + 2)
+    let mut texels = [0u8; SAMPLE_CHUNK + 4];
+    // Subpixel coverage of the pixels [x - 1, end + 1)
+    let mut subpixels = [0u8; 3 * (SAMPLE_CHUNK + 2)];
+    let mut coverage = [[0u8; 4]; SAMPLE_CHUNK];
+
+    for y in min_y..max_y {
+        let tex_row_start = (y as i64 + glyph.tex_offset[1] as i64) as usize * texture.width;
+        let mut x = min_x;
+        while x < max_x {
+            let end = chunk_end(x, max_x);
+            let len = end - x;
+
+            for (px, texel) in (x as i64 - 2..).zip(&mut texels[..len + 4]) {
+                *texel = if px >= glyph.cols[0] as i64 && px < glyph.cols[1] as i64 {
+                    let idx = tex_row_start + (px + glyph.tex_offset[0] as i64) as usize;
+                    match texture.format {
+                        TexelFormat::Alpha => texture.alpha[idx],
+                        TexelFormat::Color => texture.data[idx][3],
+                        TexelFormat::Yuv => 255,
+                    }
+                } else {
+                    0
+                };
+            }
+
+            // Subpixel centers are 1/3 px left of, on, and 1/3 px right of the pixel center
+            for (sub, t) in subpixels[..3 * (len + 2)]
+                .chunks_exact_mut(3)
+                .zip(texels.windows(3))
+            {
+                let [l, c, r] = [t[0], t[1], t[2]].map(u16::from);
+                sub[0] = ((l + 2 * c + 1) / 3) as u8;
+                sub[1] = c as u8;
+                sub[2] = ((2 * c + r + 1) / 3) as u8;
+            }
+
+            for (i, coverage) in coverage[..len].iter_mut().enumerate() {
+                // The subpixels of this pixel start at 3 * (i + 1), each is filtered with the 2 on either side
+                let taps = &subpixels[3 * i + 1..3 * i + 8];
+                let [left, mid, right] = [0, 1, 2].map(|sub| {
+                    let sum: u16 = taps[sub..sub + 5]
+                        .iter()
+                        .zip(LCD_FILTER)
+                        .map(|(&s, weight)| s as u16 * weight)
+                        .sum();
+                    ((sum + 128) >> 8) as u8
+                });
+                let gray = texels[i + 2];
+                *coverage = match field_order {
+                    ColorFieldOrder::Rgba => [left, mid, right, gray],
+                    ColorFieldOrder::Bgra => [right, mid, left, gray],
+                };
+            }
+
+            simd_impl.egui_blend_u8_slice_lcd(
+                run.color,
+                &coverage[..len],
+                buffer.get_mut_span(x - buffer_x, end - buffer_x, y - buffer_y),
+            );
+            x = end;
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub(crate) mod bary;
+pub(crate) mod glyph;
 pub(crate) mod rect;
 pub(crate) mod span;
 pub(crate) mod tri;
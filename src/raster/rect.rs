@@ -1,21 +1,33 @@
 use constify::constify;
 use egui::{Vec2, vec2};
 
-use crate::{BufferMutRef, color::SelectedImpl, egui_texture::EguiTexture, render::DrawInfo};
+use crate::{
+    BufferMutRef,
+    color::{GenericImpl, SelectedImpl},
+    egui_texture::{EguiTexture, TexelFormat},
+    math::{
+        i64vec2::{I64Vec2, i64vec2},
+        vec4::Vec4,
+    },
+    raster::span::{SAMPLE_CHUNK, chunk_end},
+    render::{DrawInfo, Scissor},
+};
 
 #[constify]
+#[allow(clippy::too_many_arguments)]
 pub fn draw_rect(
     simd_impl: impl SelectedImpl,
     buffer: &mut BufferMutRef,
     texture: &EguiTexture,
     draw: &DrawInfo,
+    scissor: &Scissor,
     #[constify] vert_col_vary: bool,
     #[constify] vert_uvs_vary: bool,
     #[constify] alpha_blend: bool,
 ) {
     simd_impl.dispatch(|simd_impl| {
         draw_rect_impl::<vert_col_vary, vert_uvs_vary, alpha_blend>(
-            simd_impl, buffer, texture, draw,
+            simd_impl, buffer, texture, draw, scissor,
         )
     })
 }
@@ -27,108 +39,377 @@ fn draw_rect_impl<const vert_col_vary: bool, const vert_uvs_vary: bool, const al
     buffer: &mut BufferMutRef,
     texture: &EguiTexture,
     draw: &DrawInfo,
+    scissor: &Scissor,
 ) {
     let const_tri_color_u8x4 = draw.const_tri_color_u8x4;
-    let clip_bounds = &draw.clip_bounds;
     let tri_min = draw.tri_min;
     let tri_max = draw.tri_max;
-    let min_x = ((tri_min.x + 0.5) as i64).max(clip_bounds[0].x);
-    let min_y = ((tri_min.y + 0.5) as i64).max(clip_bounds[0].y);
-    let max_x = ((tri_max.x + 0.5) as i64).min(clip_bounds[1].x);
-    let max_y = ((tri_max.y + 0.5) as i64).min(clip_bounds[1].y);
+    let [min, max] = rect_px_bounds(tri_min, tri_max, &draw.clip_bounds);
+    let (min_x, min_y, max_x, max_y) = (min.x, min.y, max.x, max.y);
 
-    let sizex = max_x - min_x;
-    let sizey = max_y - min_y;
+    // Only the part inside the scissor is drawn, but uvs are still computed relative to the whole clipped rect
+    let draw_min_x = min_x.max(scissor.bounds[0].x);
+    let draw_min_y = min_y.max(scissor.bounds[0].y);
+    let draw_max_x = max_x.min(scissor.bounds[1].x);
+    let draw_max_y = max_y.min(scissor.bounds[1].y);
 
-    if sizex <= 0 || sizey <= 0 {
+    if draw_max_x - draw_min_x <= 0 || draw_max_y - draw_min_y <= 0 {
         return;
     }
 
     let min_x = min_x as usize;
     let min_y = min_y as usize;
-    let max_x = max_x as usize;
-    let max_y = max_y as usize;
+    let draw_min_x = draw_min_x as usize;
+    let draw_min_y = draw_min_y as usize;
+    let draw_max_x = draw_max_x as usize;
+    let draw_max_y = draw_max_y as usize;
+    let (buffer_x, buffer_y) = (scissor.buffer_x, scissor.buffer_y);
 
     if !vert_uvs_vary && !vert_col_vary {
-        for y in min_y..max_y {
+        for y in draw_min_y..draw_max_y {
+            let dst =
+                buffer.get_mut_span(draw_min_x - buffer_x, draw_max_x - buffer_x, y - buffer_y);
             if alpha_blend {
-                simd_impl.egui_blend_u8_slice_one_src(
-                    const_tri_color_u8x4,
-                    buffer.get_mut_span(min_x, max_x, y),
-                );
+                simd_impl.egui_blend_u8_slice_one_src(const_tri_color_u8x4, dst);
             } else {
-                buffer
-                    .get_mut_span(min_x, max_x, y)
-                    .fill(const_tri_color_u8x4);
+                dst.fill(const_tri_color_u8x4);
+            }
+        }
+    } else if !vert_uvs_vary {
+        // Constant texture color, vertex colors vary across the rect
+        let gradient = RectGradient::new(draw);
+        let mut colors = [[0u8; 4]; SAMPLE_CHUNK];
+        for y in draw_min_y..draw_max_y {
+            let mut x = draw_min_x;
+            while x < draw_max_x {
+                let end = chunk_end(x, draw_max_x);
+                let colors = &mut colors[..end - x];
+                gradient.span(simd_impl, x, y, colors);
+
+                let dst = buffer.get_mut_span(x - buffer_x, end - buffer_x, y - buffer_y);
+                if alpha_blend {
+                    simd_impl.egui_blend_u8_slice_tinted(colors, draw.const_tex_color_u8x4, dst);
+                } else {
+                    for (pixel, vert_color) in dst.iter_mut().zip(colors.iter()) {
+                        *pixel = GenericImpl.unorm_mult4x4(*vert_color, draw.const_tex_color_u8x4);
+                    }
+                }
+                x = end;
             }
         }
     } else {
         // TODO could another level of constify make this cleaner (const use_nearest_sampling?)
-        let mut min_uv = vec2(
-            draw.uv[0].x.min(draw.uv[1].x).min(draw.uv[2].x),
-            draw.uv[0].y.min(draw.uv[1].y).min(draw.uv[2].y),
-        );
-        let max_uv = vec2(
-            draw.uv[0].x.max(draw.uv[1].x).max(draw.uv[2].x),
-            draw.uv[0].y.max(draw.uv[1].y).max(draw.uv[2].y),
-        );
+        let uvs = RectUvs::new(texture, draw.rect_uvs, tri_min, tri_max, min);
+        let (min_uv, uv_step, ts_min) = (uvs.min_uv, uvs.uv_step, uvs.ts_min);
+        let use_nearest_sampling = uvs.use_nearest_sampling(texture);
+        let no_texture_wrap_or_overflow = uvs.no_texture_wrap_or_overflow(texture);
+        let resample = texture
+            .sampling
+            .resample((uv_step * texture.fsize).abs())
+            .filter(|_| texture.procedural.is_none());
+
+        if use_nearest_sampling && no_texture_wrap_or_overflow && !vert_col_vary {
+            // Can just directly blend the texture over the dst buffer, no need to sample with uv
+            let tex_min = [
+                ts_min.x as usize + (draw_min_x - min_x),
+                ts_min.y as usize + (draw_min_y - min_y),
+            ];
+            for (y, tex_row) in (draw_min_y..draw_max_y).zip(tex_min[1]..) {
+                let tex_row_start = tex_row * texture.width;
+                let tex_start = tex_row_start + tex_min[0];
+                let tex_end = tex_start + draw_max_x - draw_min_x;
 
+                let dst = &mut buffer.get_mut_span(
+                    draw_min_x - buffer_x,
+                    draw_max_x - buffer_x,
+                    y - buffer_y,
+                );
+                match texture.format {
+                    TexelFormat::Color => simd_impl.egui_blend_u8_slice_tinted(
+                        &texture.data[tex_start..tex_end],
+                        draw.const_vert_color_u8x4,
+                        dst,
+                    ),
+                    // Glyph blit
+                    TexelFormat::Alpha => simd_impl.egui_blend_u8_slice_coverage(
+                        draw.const_vert_color_u8x4,
+                        &texture.alpha[tex_start..tex_end],
+                        dst,
+                    ),
+                    // Video at its native size
+                    TexelFormat::Yuv => blend_yuv_row(
+                        simd_impl,
+                        texture,
+                        [tex_min[0], tex_row],
+                        draw.const_vert_color_u8x4,
+                        dst,
+                    ),
+                }
+            }
+        } else if use_nearest_sampling && !vert_col_vary && texture.format == TexelFormat::Color {
+            // Texels still line up 1:1 with pixels but the uvs wrap or overflow the texture. Split each row into
+            // segments that are contiguous in the texture and blend those directly.
+            draw_nearest_wrapped_rows(
+                simd_impl,
+                buffer,
+                texture,
+                draw.const_vert_color_u8x4,
+                [
+                    ts_min.x.floor() as i64 + (draw_min_x - min_x) as i64,
+                    ts_min.y.floor() as i64 + (draw_min_y - min_y) as i64,
+                ],
+                [draw_min_x - buffer_x, draw_max_x - buffer_x],
+                [draw_min_y - buffer_y, draw_max_y - buffer_y],
+            );
+        } else {
+            // Can't use nearest or vertex colors vary. So we need to do full sample, with extended sampling if set.
+            let gradient = RectGradient::new(draw);
+            let mut samples = [[0u8; 4]; SAMPLE_CHUNK];
+            let mut coverage = [0u8; SAMPLE_CHUNK];
+            let mut colors = [[0u8; 4]; SAMPLE_CHUNK];
+            for y in draw_min_y..draw_max_y {
+                let uv_y = min_uv.y + uv_step.y * (y - min_y) as f32;
+                let mut x = draw_min_x;
+                while x < draw_max_x {
+                    let end = chunk_end(x, draw_max_x);
+                    let uv = vec2(min_uv.x + uv_step.x * (x - min_x) as f32, uv_y);
+                    let dst = buffer.get_mut_span(x - buffer_x, end - buffer_x, y - buffer_y);
+
+                    if texture.format == TexelFormat::Alpha && resample.is_none() {
+                        // Glyphs, the vertex color is multiplied by the sampled coverage
+                        let coverage = &mut coverage[..end - x];
+                        simd_impl.sample_coverage_span(texture, uv, vec2(uv_step.x, 0.0), coverage);
+                        if vert_col_vary {
+                            let colors = &mut colors[..end - x];
+                            gradient.span(simd_impl, x, y, colors);
+                            simd_impl.egui_blend_u8_slice_coverage_per_px(colors, coverage, dst);
+                        } else {
+                            simd_impl.egui_blend_u8_slice_coverage(
+                                draw.const_vert_color_u8x4,
+                                coverage,
+                                dst,
+                            );
+                        }
+                        x = end;
+                        continue;
+                    }
+
+                    let samples = &mut samples[..end - x];
+                    match resample {
+                        Some(resample) => texture.sample_extended_span(
+                            resample,
+                            uv,
+                            vec2(uv_step.x, 0.0),
+                            samples,
+                        ),
+                        None => texture.sample_span(simd_impl, uv, vec2(uv_step.x, 0.0), samples),
+                    }
+
+                    if vert_col_vary {
+                        let colors = &mut colors[..end - x];
+                        gradient.span(simd_impl, x, y, colors);
+                        simd_impl.egui_blend_u8_slice_tinted_per_px(samples, colors, dst);
+                    } else {
+                        simd_impl.egui_blend_u8_slice_tinted(
+                            samples,
+                            draw.const_vert_color_u8x4,
+                            dst,
+                        );
+                    }
+                    x = end;
+                }
+            }
+        }
+    };
+}
+
+/// Pixels [min, max) covered by a rect with the given bounds, clipped to `clip_bounds`. A pixel is covered if its center
+/// is inside the rect. May be empty.
+#[inline]
+pub fn rect_px_bounds(tri_min: Vec2, tri_max: Vec2, clip_bounds: &[I64Vec2; 2]) -> [I64Vec2; 2] {
+    [
+        i64vec2((tri_min.x + 0.5) as i64, (tri_min.y + 0.5) as i64).max(clip_bounds[0]),
+        i64vec2((tri_max.x + 0.5) as i64, (tri_max.y + 0.5) as i64).min(clip_bounds[1]),
+    ]
+}
+
+/// How the pixels of a textured rect map to its uvs.
+pub struct RectUvs {
+    /// Uv at the center of the first pixel drawn
+    pub min_uv: Vec2,
+    /// Uv step per pixel
+    pub uv_step: Vec2,
+    /// `min_uv` in texels
+    pub ts_min: Vec2,
+    /// Uv of the max corner in texels
+    pub ts_max: Vec2,
+}
+
+impl RectUvs {
+    /// `rect_uvs` are the uvs at the min and max corners of the rect, not necessarily min/max uvs since the texture may
+    /// be flipped. `px_min` is the first pixel drawn, from `rect_px_bounds()`.
+    #[inline]
+    pub fn new(
+        texture: &EguiTexture,
+        [mut min_uv, max_uv]: [Vec2; 2],
+        tri_min: Vec2,
+        tri_max: Vec2,
+        px_min: I64Vec2,
+    ) -> Self {
         let uv_step = (max_uv - min_uv) / (tri_max - tri_min);
-        min_uv += uv_step * (vec2(min_x as f32, min_y as f32) - tri_min).max(Vec2::ZERO); // Offset to account for clip
+        min_uv += uv_step * (vec2(px_min.x as f32, px_min.y as f32) - tri_min).max(Vec2::ZERO); // Offset to account for clip
         min_uv += uv_step * 0.5; // Raster at pixel centers
 
-        let ts_min = min_uv * texture.fsize;
-        let ts_max = max_uv * texture.fsize;
+        RectUvs {
+            min_uv,
+            uv_step,
+            ts_min: min_uv * texture.fsize,
+            ts_max: max_uv * texture.fsize,
+        }
+    }
+
+    /// True if texels line up 1:1 with pixels, so the texture can be blended without sampling.
+    #[inline]
+    pub fn use_nearest_sampling(&self, texture: &EguiTexture) -> bool {
+        let ss_step = self.uv_step * texture.fsize;
+        let dist_from_px_center = (self.ts_min - self.ts_min.floor() - vec2(0.5, 0.5)).abs();
+        let steps_off_from_1px = (ss_step - Vec2::ONE).abs();
+        let eps = 0.01;
+        let steps_are_1px = steps_off_from_1px.x < eps && steps_off_from_1px.y < eps;
+        let start_on_texture_px_center = dist_from_px_center.x < eps && dist_from_px_center.y < eps;
+
+        // Procedural textures have no texels to copy
+        steps_are_1px && start_on_texture_px_center && texture.procedural.is_none()
+    }
 
-        let use_nearest_sampling = {
-            let ss_step = uv_step * texture.fsize;
-            let dist_from_px_center = (ts_min - ts_min.floor() - vec2(0.5, 0.5)).abs();
-            let steps_off_from_1px = (ss_step - Vec2::ONE).abs();
-            let eps = 0.01;
-            let steps_are_1px = steps_off_from_1px.x < eps && steps_off_from_1px.y < eps;
-            let start_on_texture_px_center =
-                dist_from_px_center.x < eps && dist_from_px_center.y < eps;
+    #[inline]
+    pub fn no_texture_wrap_or_overflow(&self, texture: &EguiTexture) -> bool {
+        self.ts_min.x >= 0.0
+            && self.ts_min.y >= 0.0
+            && (self.ts_max.x as usize) < texture.width
+            && (self.ts_max.y as usize) < texture.height
+    }
+}
 
-            steps_are_1px && start_on_texture_px_center
-        };
+/// Linear interpolation of the rect's corner vertex colors, evaluated at pixel centers.
+struct RectGradient {
+    origin: Vec4,
+    step_x: Vec4,
+    step_y: Vec4,
+}
 
-        let no_texture_wrap_or_overflow =
-            (ts_max.x as usize) < texture.width && (ts_max.y as usize) < texture.height;
+impl RectGradient {
+    #[inline]
+    fn new(draw: &DrawInfo) -> Self {
+        let [c00, c10, c01, _] = draw.rect_colors;
+        let size = draw.tri_max - draw.tri_min;
+        let step_x = (c10 - c00) / size.x;
+        let step_y = (c01 - c00) / size.y;
+        Self {
+            origin: c00 - step_x * draw.tri_min.x - step_y * draw.tri_min.y,
+            step_x,
+            step_y,
+        }
+    }
 
-        if use_nearest_sampling && no_texture_wrap_or_overflow {
-            // Can just directly blend the texture over the dst buffer, no need to sample with uv
-            let min_uv = [ts_min.x as i32, ts_min.y as i32];
-            let mut tex_row = min_uv[1];
-            for y in min_y..max_y {
-                let tex_row_start = tex_row as usize * texture.width;
-                let tex_start = tex_row_start + min_uv[0] as usize;
-                let tex_end = tex_start + max_x - min_x;
-
-                let dst = &mut buffer.get_mut_span(min_x, max_x, y);
-                let src = &texture.data[tex_start..tex_end];
-
-                simd_impl.egui_blend_u8_slice_tinted(src, draw.const_vert_color_u8x4, dst);
-                tex_row += 1;
-            }
+    /// Writes the colors of the pixels starting at [x, y] into dst
+    #[inline]
+    fn span(&self, simd_impl: impl SelectedImpl, x: usize, y: usize, dst: &mut [[u8; 4]]) {
+        let start = self.origin + self.step_x * (x as f32 + 0.5) + self.step_y * (y as f32 + 0.5);
+        simd_impl.color_gradient_span(start, self.step_x, dst);
+    }
+}
+
+/// Converts the texels of a `TexelFormat::Yuv` texture starting at `tex_min` that line up 1:1 with `dst` and blends them
+/// tinted by `color`. They're opaque, so they're converted straight into `dst` when the tint is white.
+pub fn blend_yuv_row(
+    simd_impl: impl SelectedImpl,
+    texture: &EguiTexture,
+    tex_min: [usize; 2],
+    color: [u8; 4],
+    dst: &mut [[u8; 4]],
+) {
+    let mut samples = [[0u8; 4]; SAMPLE_CHUNK];
+    for (dst, tex_x) in dst
+        .chunks_mut(SAMPLE_CHUNK)
+        .zip((tex_min[0]..).step_by(SAMPLE_CHUNK))
+    {
+        if color == [255; 4] {
+            texture.yuv_row_span(simd_impl, tex_x, tex_min[1], dst);
         } else {
-            // There's overflow or can't use nearest. So we need to do full sample.
-            // TODO perf: there could be a situation where we could nearest sample but with wrapping/clipping.
-            // We don't have a fast path for that and are falling back to the more general solution below.
-            // This would be (use_nearest_sampling && !no_texture_wrap_or_overflow) which at least never occurs in the
-            // demo as far as I can tell
-            let mut uv = min_uv;
-            for y in min_y..max_y {
-                uv.x = min_uv.x;
-                let buf_y = y * buffer.width;
-                for x in min_x..max_x {
-                    let tex_color = texture.sample_bilinear(uv);
-                    let pixel = &mut buffer.data[x + buf_y];
-                    let src = simd_impl.unorm_mult4x4(draw.const_vert_color_u8x4, tex_color);
-                    *pixel = simd_impl.egui_blend_u8(src, *pixel);
-                    uv.x += uv_step.x;
+            let samples = &mut samples[..dst.len()];
+            texture.yuv_row_span(simd_impl, tex_x, tex_min[1], samples);
+            simd_impl.egui_blend_u8_slice_tinted(samples, color, dst);
+        }
+    }
+}
+
+/// Blends rows of texels that line up 1:1 with pixels, wrapping or clamping the texel coordinates according to the
+/// texture's wrap mode. `tex_min` is the (unwrapped) texel that lands on the pixel at [min_x, min_y]. Columns and
+/// rows are those of the buffer.
+fn draw_nearest_wrapped_rows(
+    simd_impl: impl SelectedImpl,
+    buffer: &mut BufferMutRef,
+    texture: &EguiTexture,
+    tint: [u8; 4],
+    tex_min: [i64; 2],
+    [min_x, max_x]: [usize; 2],
+    [min_y, max_y]: [usize; 2],
+) {
+    let width = texture.width as i64;
+    let mut reversed = [[0u8; 4]; SAMPLE_CHUNK];
+
+    for (y, tex_y) in (min_y..max_y).zip(tex_min[1]..) {
+        let tex_y = texture.wrap_texel(tex_y, texture.height);
+        let tex_row = &texture.data[tex_y * texture.width..(tex_y + 1) * texture.width];
+
+        let mut x = min_x;
+        let mut tex_x = tex_min[0];
+        while x < max_x {
+            let remaining = (max_x - x) as i64;
+            let len = match texture.options.wrap_mode {
+                egui::TextureWrapMode::ClampToEdge if tex_x < 0 || tex_x >= width => {
+                    // Run of a single clamped edge texel
+                    let len = if tex_x < 0 { -tex_x } else { remaining }.min(remaining) as usize;
+                    let texel = tex_row[texture.wrap_texel(tex_x, texture.width)];
+                    simd_impl.egui_blend_u8_slice_one_src(
+                        simd_impl.unorm_mult4x4(tint, texel),
+                        buffer.get_mut_span(x, x + len, y),
+                    );
+                    len
                 }
-                uv.y += uv_step.y;
-            }
+                egui::TextureWrapMode::MirroredRepeat if tex_x.rem_euclid(width * 2) >= width => {
+                    // Mirrored segment, texels are read in reverse
+                    let last = texture.wrap_texel(tex_x, texture.width);
+                    let len = ((last + 1) as i64).min(remaining) as usize;
+                    let len = len.min(SAMPLE_CHUNK);
+                    let src = &mut reversed[..len];
+                    for (dst, texel) in src
+                        .iter_mut()
+                        .zip(tex_row[last + 1 - len..=last].iter().rev())
+                    {
+                        *dst = *texel;
+                    }
+                    simd_impl.egui_blend_u8_slice_tinted(
+                        src,
+                        tint,
+                        buffer.get_mut_span(x, x + len, y),
+                    );
+                    len
+                }
+                _ => {
+                    let first = texture.wrap_texel(tex_x, texture.width);
+                    let len = ((texture.width - first) as i64).min(remaining) as usize;
+                    simd_impl.egui_blend_u8_slice_tinted(
+                        &tex_row[first..first + len],
+                        tint,
+                        buffer.get_mut_span(x, x + len, y),
+                    );
+                    len
+                }
+            };
+            x += len;
+            tex_x += len as i64;
         }
-    };
+    }
 }
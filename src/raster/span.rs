@@ -1,17 +1,47 @@
 use crate::raster::bary::SingleStepper;
 use strength_reduce::StrengthReducedU64;
 
+/// Max number of texels sampled at once into a stack buffer by `SelectedImpl::sample_bilinear_span()` before being
+/// blended into the destination.
+pub const SAMPLE_CHUNK: usize = 64;
+
+// Tiles must start on a chunk boundary for spans to be split into the same chunks when drawn one tile at a time
+const _: () = assert!(crate::TILE_SIZE % SAMPLE_CHUNK == 0);
+
+/// Returns the end of the chunk of a span that starts at x. Chunks are aligned to multiples of SAMPLE_CHUNK so a span
+/// is chunked the same way no matter where it's cut.
+#[inline(always)]
+pub fn chunk_end(x: usize, span_end: usize) -> usize {
+    ((x / SAMPLE_CHUNK + 1) * SAMPLE_CHUNK).min(span_end)
+}
+
 /// Returns Some((start, end)) for the current row in the triangle. The end points are defined within the aabb of the
 /// triangle so add ss_min.x to each to get the screen space coordinate. Returns None if there is no span intersecting
 /// this row.
+#[inline(always)]
 pub fn calc_row_span(
     stepper: &SingleStepper,
     max_cols: i64,
     step_rcp: &[StrengthReducedU64; 3],
+) -> Option<(i64, i64)> {
+    calc_row_span_offset(stepper, [0; 3], max_cols, step_rcp)
+}
+
+/// Like `calc_row_span()` with `offset` added to the edge weights, which moves each edge out (positive) or in
+/// (negative) by a distance proportional to the offset.
+pub fn calc_row_span_offset(
+    stepper: &SingleStepper,
+    offset: [i64; 3],
+    max_cols: i64,
+    step_rcp: &[StrengthReducedU64; 3],
 ) -> Option<(i64, i64)> {
     let mut start = 0;
     let mut end = u64::MAX;
-    let w = &stepper.sp_weight;
+    let w = [
+        stepper.sp_weight[0] + offset[0],
+        stepper.sp_weight[1] + offset[1],
+        stepper.sp_weight[2] + offset[2],
+    ];
     let sx = [
         stepper.step[0].step.x,
         stepper.step[1].step.x,
@@ -1,29 +1,34 @@
 use constify::constify;
 
+use egui::Vec2;
+
 use crate::{
     BufferMutRef,
-    color::{SelectedImpl, vec4_to_u8x4},
-    egui_texture::EguiTexture,
+    color::{GenericImpl, SelectedImpl, vec4_to_u8x4},
+    egui_texture::{EguiTexture, TexelFormat},
+    math::{i64vec2::I64Vec2, vec4::Vec4},
     raster::{
-        bary::SingleStepper,
-        span::{calc_row_span, step_rcp},
+        bary::{AttributeStepper, SingleStep, SingleStepper, orient2d},
+        span::{SAMPLE_CHUNK, calc_row_span, calc_row_span_offset, chunk_end, step_rcp},
     },
-    render::DrawInfo,
+    render::{DrawInfo, Scissor},
 };
 
 #[constify]
+#[allow(clippy::too_many_arguments)]
 pub fn draw_tri<const SUBPIX_BITS: i32>(
     simd_impl: impl SelectedImpl,
     buffer: &mut BufferMutRef,
     texture: &EguiTexture,
     draw: &DrawInfo,
+    scissor: &Scissor,
     #[constify] vert_col_vary: bool,
     #[constify] vert_uvs_vary: bool,
     #[constify] alpha_blend: bool,
 ) {
     simd_impl.dispatch(|simd_impl| {
         draw_tri_impl::<SUBPIX_BITS, vert_col_vary, vert_uvs_vary, alpha_blend>(
-            simd_impl, buffer, texture, draw,
+            simd_impl, buffer, texture, draw, scissor,
         )
     })
 }
@@ -40,14 +45,25 @@ fn draw_tri_impl<
     buffer: &mut BufferMutRef,
     texture: &EguiTexture,
     draw: &DrawInfo,
+    scissor: &Scissor,
 ) {
+    let coverage_aa = draw.aa_edges != 0;
     let Some((ss_min, ss_max, sp_inv_area, mut stepper)) =
-        SingleStepper::from_ss_tri_backface_cull::<SUBPIX_BITS>(draw.clip_bounds, &draw.ss_tri)
+        SingleStepper::from_ss_tri_backface_cull::<SUBPIX_BITS>(
+            draw.clip_bounds,
+            &draw.ss_tri,
+            coverage_aa,
+        )
     else {
         return;
     };
 
     let step_rcp = step_rcp(&stepper);
+    let mut edge_aa = if coverage_aa {
+        EdgeAa::new::<SUBPIX_BITS>(&stepper, draw, ss_min)
+    } else {
+        Default::default()
+    };
 
     let mut vert_col_stepper = if vert_col_vary {
         stepper.attr(&draw.colors, sp_inv_area)
@@ -62,81 +78,399 @@ fn draw_tri_impl<
     };
 
     let max_cols = ss_max.x - ss_min.x;
+    // Rows above the scissor are still stepped through so the attributes match drawing the whole tri
+    let row_end = ss_max.y.min(scissor.bounds[1].y);
+
+    // Columns [start, end) of the tri's aabb clipped to the scissor, in screen space
+    let clip = |(start, end): (i64, i64)| {
+        let ss_start = (ss_min.x + start).max(scissor.bounds[0].x);
+        let ss_end = (ss_min.x + end).min(scissor.bounds[1].x);
+        (ss_start < ss_end).then_some((ss_start as usize, ss_end as usize))
+    };
 
-    for ss_y in ss_min.y..ss_max.y {
-        stepper.row_start();
+    for ss_y in ss_min.y..row_end {
+        if ss_y >= scissor.bounds[0].y {
+            stepper.row_start();
+            let buffer_y = ss_y as usize - scissor.buffer_y;
+            let span = Span {
+                ss_min_x: ss_min.x,
+                buffer_x: scissor.buffer_x,
+                buffer_y,
+                vert_col_stepper: &vert_col_stepper,
+                vert_uv_stepper: &vert_uv_stepper,
+            };
+
+            if coverage_aa {
+                // Pixels the AA edges partially cover are on either side of those they fully cover
+                if let Some((outer_start, outer_end)) =
+                    calc_row_span_offset(&stepper, edge_aa.outer, max_cols, &step_rcp)
+                {
+                    let (inner_start, inner_end) =
+                        calc_row_span_offset(&stepper, edge_aa.inner, max_cols, &step_rcp)
+                            .unwrap_or((outer_end, outer_end));
+                    let inner_start = inner_start.clamp(outer_start, outer_end);
+                    let inner_end = inner_end.clamp(inner_start, outer_end);
+
+                    for (start, end) in [(outer_start, inner_start), (inner_end, outer_end)] {
+                        if let Some((ss_start, ss_end)) = clip((start, end)) {
+                            draw_partial_pixels::<vert_col_vary, vert_uvs_vary, alpha_blend>(
+                                simd_impl, buffer, texture, draw, &span, &stepper, &edge_aa,
+                                ss_start, ss_end,
+                            );
+                        }
+                    }
+                    if let Some((ss_start, ss_end)) = clip((inner_start, inner_end)) {
+                        draw_span::<vert_col_vary, vert_uvs_vary, alpha_blend>(
+                            simd_impl, buffer, texture, draw, &span, ss_start, ss_end,
+                        );
+                    }
+                }
+            } else if let Some((ss_start, ss_end)) =
+                calc_row_span(&stepper, max_cols, &step_rcp).and_then(clip)
+            {
+                draw_span::<vert_col_vary, vert_uvs_vary, alpha_blend>(
+                    simd_impl, buffer, texture, draw, &span, ss_start, ss_end,
+                );
+            }
+        }
+
+        stepper.row_step();
+        if coverage_aa {
+            edge_aa.row_step();
+        }
         if vert_col_vary {
-            vert_col_stepper.row_start();
+            vert_col_stepper.row_step();
         }
         if vert_uvs_vary {
-            vert_uv_stepper.row_start();
+            vert_uv_stepper.row_step();
         }
+    }
+}
 
-        if let Some((start, end)) = calc_row_span(&stepper, max_cols, &step_rcp) {
-            if vert_col_vary {
-                vert_col_stepper.attr += vert_col_stepper.step_x * start as f32;
-            }
-            if vert_uvs_vary {
-                vert_uv_stepper.attr += vert_uv_stepper.step_x * start as f32;
+/// The row of a tri being drawn.
+struct Span<'a> {
+    /// Left of the tri's aabb, attributes are evaluated relative to it
+    ss_min_x: i64,
+    /// Screen space x of the first column and y of the row in the buffer being drawn to
+    buffer_x: usize,
+    buffer_y: usize,
+    vert_col_stepper: &'a AttributeStepper<Vec4>,
+    vert_uv_stepper: &'a AttributeStepper<Vec2>,
+}
+
+impl Span<'_> {
+    /// Column of the tri's aabb
+    #[inline(always)]
+    fn col(&self, ss_x: usize) -> i64 {
+        ss_x as i64 - self.ss_min_x
+    }
+}
+
+/// Draws the pixels [ss_start, ss_end) of a row that are inside the tri.
+#[inline(always)]
+#[allow(non_upper_case_globals)]
+fn draw_span<const vert_col_vary: bool, const vert_uvs_vary: bool, const alpha_blend: bool>(
+    simd_impl: impl SelectedImpl,
+    buffer: &mut BufferMutRef,
+    texture: &EguiTexture,
+    draw: &DrawInfo,
+    span: &Span,
+    ss_start: usize,
+    ss_end: usize,
+) {
+    let (vert_col_stepper, vert_uv_stepper) = (span.vert_col_stepper, span.vert_uv_stepper);
+    let (buffer_x, buffer_y) = (span.buffer_x, span.buffer_y);
+    let col = |ss_x: usize| span.col(ss_x);
+
+    if !vert_uvs_vary && !vert_col_vary {
+        let dst = buffer.get_mut_span(ss_start - buffer_x, ss_end - buffer_x, buffer_y);
+        if alpha_blend {
+            simd_impl.egui_blend_u8_slice_one_src(draw.const_tri_color_u8x4, dst)
+        } else {
+            dst.fill(draw.const_tri_color_u8x4)
+        }
+    } else if !vert_uvs_vary {
+        let mut colors = [[0u8; 4]; SAMPLE_CHUNK];
+        let mut ss_x = ss_start;
+        while ss_x < ss_end {
+            let end = chunk_end(ss_x, ss_end);
+            let col_start = vert_col_stepper.at_col(col(ss_x));
+            let dst = buffer.get_mut_span(ss_x - buffer_x, end - buffer_x, buffer_y);
+            if alpha_blend {
+                let colors = &mut colors[..end - ss_x];
+                simd_impl.color_gradient_span(col_start, vert_col_stepper.step_x, colors);
+                simd_impl.egui_blend_u8_slice_tinted(colors, draw.const_tex_color_u8x4, dst);
+            } else {
+                // Opaque, so the colors can go straight into dst
+                simd_impl.color_gradient_span(col_start, vert_col_stepper.step_x, dst);
+                if draw.const_tex_color_u8x4 != [255; 4] {
+                    for pixel in dst.iter_mut() {
+                        *pixel = GenericImpl.unorm_mult4x4(*pixel, draw.const_tex_color_u8x4);
+                    }
+                }
             }
-            let ss_start = (ss_min.x + start) as usize;
-            let ss_end = (ss_min.x + end) as usize;
-
-            if alpha_blend && !vert_uvs_vary {
-                let dst = buffer.get_mut_span(ss_start, ss_end, ss_y as usize);
-                if vert_col_vary {
-                    simd_impl.egui_blend_u8_slice_one_src_tinted_fn(
-                        draw.const_tex_color_u8x4,
-                        || {
-                            let v = vec4_to_u8x4(&vert_col_stepper.attr);
-                            vert_col_stepper.col_step();
-                            v
-                        },
-                        dst,
-                    )
+            ss_x = end;
+        }
+    } else if texture.format == TexelFormat::Alpha {
+        // Glyphs, the vertex color is multiplied by the sampled coverage
+        let mut coverage = [0u8; SAMPLE_CHUNK];
+        let mut colors = [[0u8; 4]; SAMPLE_CHUNK];
+        let mut ss_x = ss_start;
+        while ss_x < ss_end {
+            let end = chunk_end(ss_x, ss_end);
+            let coverage = &mut coverage[..end - ss_x];
+            simd_impl.sample_coverage_span(
+                texture,
+                vert_uv_stepper.at_col(col(ss_x)),
+                vert_uv_stepper.step_x,
+                coverage,
+            );
+
+            let dst = buffer.get_mut_span(ss_x - buffer_x, end - buffer_x, buffer_y);
+            if vert_col_vary {
+                let colors = &mut colors[..end - ss_x];
+                simd_impl.color_gradient_span(
+                    vert_col_stepper.at_col(col(ss_x)),
+                    vert_col_stepper.step_x,
+                    colors,
+                );
+                if alpha_blend {
+                    simd_impl.egui_blend_u8_slice_coverage_per_px(colors, coverage, dst);
                 } else {
-                    simd_impl.egui_blend_u8_slice_one_src(draw.const_tri_color_u8x4, dst)
+                    for ((pixel, &a), vert_color) in
+                        dst.iter_mut().zip(coverage.iter()).zip(colors.iter())
+                    {
+                        *pixel = GenericImpl.unorm_mult4x4(*vert_color, [a; 4]);
+                    }
                 }
+            } else if alpha_blend {
+                simd_impl.egui_blend_u8_slice_coverage(draw.const_vert_color_u8x4, coverage, dst);
             } else {
-                for ss_x in ss_start..ss_end {
-                    let src = if vert_uvs_vary || vert_col_vary {
-                        let tex_color = if vert_uvs_vary {
-                            texture.sample_bilinear(vert_uv_stepper.attr)
-                        } else {
-                            draw.const_tex_color_u8x4
-                        };
-                        let vert_color = if vert_col_vary {
-                            vec4_to_u8x4(&vert_col_stepper.attr)
-                        } else {
-                            draw.const_vert_color_u8x4
-                        };
-                        crate::color::GenericImpl.unorm_mult4x4(vert_color, tex_color)
-                        //^ `simd_impl.unorm_mult4x4` is slower at least in avx2 and sse4.1
-                    } else {
-                        draw.const_tri_color_u8x4
-                    };
-                    let pixel = buffer.get_mut(ss_x, ss_y as usize);
-                    *pixel = if alpha_blend {
-                        simd_impl.egui_blend_u8(src, *pixel)
-                    } else {
-                        src
-                    };
-                    if vert_col_vary {
-                        vert_col_stepper.col_step();
-                    }
-                    if vert_uvs_vary {
-                        vert_uv_stepper.col_step();
+                for (pixel, &a) in dst.iter_mut().zip(coverage.iter()) {
+                    *pixel = GenericImpl.unorm_mult4x4(draw.const_vert_color_u8x4, [a; 4]);
+                }
+            }
+            ss_x = end;
+        }
+    } else {
+        let mut samples = [[0u8; 4]; SAMPLE_CHUNK];
+        let mut colors = [[0u8; 4]; SAMPLE_CHUNK];
+        let mut ss_x = ss_start;
+        while ss_x < ss_end {
+            let end = chunk_end(ss_x, ss_end);
+            let samples = &mut samples[..end - ss_x];
+            texture.sample_span(
+                simd_impl,
+                vert_uv_stepper.at_col(col(ss_x)),
+                vert_uv_stepper.step_x,
+                samples,
+            );
+
+            let dst = buffer.get_mut_span(ss_x - buffer_x, end - buffer_x, buffer_y);
+            if vert_col_vary {
+                let colors = &mut colors[..end - ss_x];
+                simd_impl.color_gradient_span(
+                    vert_col_stepper.at_col(col(ss_x)),
+                    vert_col_stepper.step_x,
+                    colors,
+                );
+                if alpha_blend {
+                    simd_impl.egui_blend_u8_slice_tinted_per_px(samples, colors, dst);
+                } else {
+                    for ((pixel, tex_color), vert_color) in
+                        dst.iter_mut().zip(samples.iter()).zip(colors.iter())
+                    {
+                        *pixel = GenericImpl.unorm_mult4x4(*vert_color, *tex_color);
                     }
                 }
+            } else if alpha_blend {
+                simd_impl.egui_blend_u8_slice_tinted(samples, draw.const_vert_color_u8x4, dst);
+            } else {
+                for (pixel, tex_color) in dst.iter_mut().zip(samples.iter()) {
+                    *pixel = GenericImpl.unorm_mult4x4(draw.const_vert_color_u8x4, *tex_color);
+                }
+            }
+            ss_x = end;
+        }
+    }
+}
+
+/// Draws the pixels [ss_start, ss_end) of a row that the AA edges of the tri may partially cover, blending each with its
+/// coverage. Only these pixels are supersampled, the rest of the row and the pixels found to be fully covered are drawn
+/// by `draw_span()`.
+#[inline(always)]
+#[allow(non_upper_case_globals, clippy::too_many_arguments)]
+fn draw_partial_pixels<
+    const vert_col_vary: bool,
+    const vert_uvs_vary: bool,
+    const alpha_blend: bool,
+>(
+    simd_impl: impl SelectedImpl,
+    buffer: &mut BufferMutRef,
+    texture: &EguiTexture,
+    draw: &DrawInfo,
+    span: &Span,
+    stepper: &SingleStepper,
+    edge_aa: &EdgeAa,
+    ss_start: usize,
+    ss_end: usize,
+) {
+    let mut covered_start = None;
+    for ss_x in ss_start..ss_end {
+        let col = span.col(ss_x);
+        let coverage = edge_aa.coverage(stepper, col);
+        if coverage == 255 {
+            covered_start.get_or_insert(ss_x);
+            continue;
+        }
+        if let Some(start) = covered_start.take() {
+            draw_span::<vert_col_vary, vert_uvs_vary, alpha_blend>(
+                simd_impl, buffer, texture, draw, span, start, ss_x,
+            );
+        }
+        if coverage == 0 {
+            continue;
+        }
+
+        let vert_color = if vert_col_vary {
+            vec4_to_u8x4(&span.vert_col_stepper.at_col(col))
+        } else {
+            draw.const_vert_color_u8x4
+        };
+        let src = if !vert_uvs_vary {
+            if vert_col_vary {
+                simd_impl.unorm_mult4x4(vert_color, draw.const_tex_color_u8x4)
+            } else {
+                draw.const_tri_color_u8x4
+            }
+        } else {
+            let uv = span.vert_uv_stepper.at_col(col);
+            let uv_step = span.vert_uv_stepper.step_x;
+            if texture.format == TexelFormat::Alpha {
+                let mut a = [0u8];
+                simd_impl.sample_coverage_span(texture, uv, uv_step, &mut a);
+                simd_impl.unorm_mult4x4(vert_color, [a[0]; 4])
+            } else {
+                let mut tex_color = [[0u8; 4]];
+                texture.sample_span(simd_impl, uv, uv_step, &mut tex_color);
+                simd_impl.unorm_mult4x4(vert_color, tex_color[0])
             }
         };
 
-        stepper.row_step();
-        if vert_col_vary {
-            vert_col_stepper.row_step();
+        let dst = buffer.get_mut(ss_x - span.buffer_x, span.buffer_y);
+        *dst = simd_impl.egui_blend_u8(simd_impl.unorm_mult4x4(src, [coverage; 4]), *dst);
+    }
+    if let Some(start) = covered_start {
+        draw_span::<vert_col_vary, vert_uvs_vary, alpha_blend>(
+            simd_impl, buffer, texture, draw, span, start, ss_end,
+        );
+    }
+}
+
+/// Coverage AA of the edges set in `DrawInfo::aa_edges`. Coverage is the fraction of a 4x4 grid of samples in the pixel
+/// that's inside those edges. The other edges are shared with another tri. The tri only draws the pixels whose center
+/// is on its side of them, so tris sharing an edge still meet without seams, and its samples on the other side are
+/// tested against the outline edges of that tri from `DrawInfo::aa_neighbors`.
+#[derive(Default)]
+struct EdgeAa {
+    mask: u8,
+    /// Offsets of the edge weights that move the AA edges out to the pixels they may partially cover
+    outer: [i64; 3],
+    /// Offsets of the edge weights that move the edges in to the pixels they fully cover
+    inner: [i64; 3],
+    /// Outline edges of the tri across each shared edge, stepped along with the tri's own edges
+    neighbor_edges: [[Option<SingleStep>; 2]; 3],
+}
+
+impl EdgeAa {
+    fn new<const SUBPIX_BITS: i32>(
+        stepper: &SingleStepper,
+        draw: &DrawInfo,
+        ss_min: I64Vec2,
+    ) -> Self {
+        let subpix: i64 = 1 << SUBPIX_BITS;
+        let sp_min_p = ss_min * subpix + (subpix >> 1);
+        let to_sp = |ss: Vec2| I64Vec2::from_vec2(ss * subpix as f32);
+
+        let mut edge_aa = EdgeAa {
+            mask: draw.aa_edges,
+            ..Default::default()
+        };
+        for edge in 0..3 {
+            // Samples are at most 3/8 px from the pixel center along each axis
+            let step = stepper.step[edge].step;
+            let offset = (3 * (step.x.abs() + step.y.abs()) + 7) / 8;
+            if draw.aa_edges & (1 << edge) != 0 {
+                edge_aa.outer[edge] = offset;
+                edge_aa.inner[edge] = -offset;
+                continue;
+            }
+
+            let neighbor = &draw.aa_neighbors[edge];
+            let vert = to_sp(neighbor.vert);
+            let shared = [(edge + 1) % 3, (edge + 2) % 3].map(|v| to_sp(draw.ss_tri[v]));
+            for k in 0..2 {
+                if neighbor.outline & (1 << k) == 0 {
+                    continue;
+                }
+                // Oriented so the other end of the shared edge is inside
+                let (from, other) = (shared[k], shared[1 - k]);
+                edge_aa.neighbor_edges[edge][k] = Some(if orient2d(&from, &vert, &other) > 0 {
+                    SingleStep::new(&from, &vert, &sp_min_p, subpix)
+                } else {
+                    SingleStep::new(&vert, &from, &sp_min_p, subpix)
+                });
+                edge_aa.inner[edge] = -offset;
+            }
         }
-        if vert_uvs_vary {
-            vert_uv_stepper.row_step();
+        edge_aa
+    }
+
+    #[inline(always)]
+    fn row_step(&mut self) {
+        for step in self.neighbor_edges.iter_mut().flatten().flatten() {
+            step.row += step.step.y;
+        }
+    }
+
+    /// Coverage of the pixel `col` steps along x from the start of the stepper's current row.
+    #[inline(always)]
+    fn coverage(&self, stepper: &SingleStepper, col: i64) -> u8 {
+        let mut w = [0; 3];
+        for (edge, w) in w.iter_mut().enumerate() {
+            *w = stepper.sp_weight[edge] + stepper.step[edge].step.x * col;
+            if self.mask & (1 << edge) == 0 && *w < 0 {
+                return 0;
+            }
+        }
+        let neighbor_w = self.neighbor_edges.each_ref().map(|steps| {
+            steps
+                .each_ref()
+                .map(|step| step.as_ref().map(|s| s.row + s.step.x * col))
+        });
+
+        // Edge weight at a sample, in 1/8 px steps from the pixel center
+        let at_sample = |w: i64, step: I64Vec2, [x, y]: [i64; 2]| 8 * w + step.x * x + step.y * y;
+
+        let mut count = 0u32;
+        for y in [-3, -1, 1, 3] {
+            for x in [-3, -1, 1, 3] {
+                let inside = (0..3).all(|edge| {
+                    let inside_edge = at_sample(w[edge], stepper.step[edge].step, [x, y]) >= 0;
+                    if self.mask & (1 << edge) != 0 {
+                        return inside_edge;
+                    }
+                    inside_edge
+                        || self.neighbor_edges[edge].iter().zip(neighbor_w[edge]).all(
+                            |(step, w)| match (step, w) {
+                                (Some(step), Some(w)) => at_sample(w, step.step, [x, y]) >= 0,
+                                _ => true,
+                            },
+                        )
+                });
+                count += inside as u32;
+            }
         }
+        ((count * 255 + 8) / 16) as u8
     }
 }
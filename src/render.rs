@@ -1,15 +1,23 @@
 #![allow(unsafe_code)]
 
+#[cfg(feature = "rayon")]
+use crate::TILE_SIZE;
 use crate::{
-    BufferMutRef, EguiTexture,
+    BufferMutRef, ColorFieldOrder, EguiTexture,
     color::{AvailableImpl, SelectedImpl, u8x4_to_vec4, vec4_to_u8x4},
     math::{
         i64vec2::{I64Vec2, i64vec2},
         vec4::Vec4,
     },
-    raster::{rect::draw_rect, tri::draw_tri},
+    raster::{
+        glyph::{GLYPH_RUN_MAX, Glyph, GlyphRun, draw_glyph_run},
+        rect::{RectUvs, draw_rect, rect_px_bounds},
+        tri::draw_tri,
+    },
 };
 use ahash::HashMap;
+#[cfg(feature = "rayon")]
+use alloc::vec::Vec;
 use egui::{Pos2, Vec2, epaint::Vertex, vec2};
 
 #[allow(clippy::too_many_arguments)]
@@ -22,10 +30,13 @@ pub fn draw_egui_mesh<const SUBPIX_BITS: i32>(
     vert_offset: Vec2,
     allow_raster_opt: bool,
     convert_tris_to_rects: bool,
+    lcd_text: Option<ColorFieldOrder>,
+    coverage_aa: Option<&mut EdgeScratch>,
     #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
     stats: &mut crate::stats::RasterStats,
 ) {
-    crate::dispatch_simd_impl!(simd_impl, |simd_impl| draw_egui_mesh_impl::<SUBPIX_BITS>(
+    let scissor = Scissor::full(buffer);
+    draw_egui_mesh_scissored::<SUBPIX_BITS>(
         simd_impl,
         textures,
         buffer,
@@ -34,14 +45,19 @@ pub fn draw_egui_mesh<const SUBPIX_BITS: i32>(
         vert_offset,
         allow_raster_opt,
         convert_tris_to_rects,
+        lcd_text,
+        coverage_aa,
         #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
         stats,
-    ))
+        &[scissor],
+    );
 }
 
+/// Like `draw_egui_mesh()` but only writes the pixels inside the given scissors, which must not overlap. The mesh is
+/// only prepared once, each draw command is rasterized with every scissor it overlaps.
 #[allow(clippy::too_many_arguments)]
-fn draw_egui_mesh_impl<const SUBPIX_BITS: i32>(
-    simd_impl: impl SelectedImpl,
+pub fn draw_egui_mesh_scissored<const SUBPIX_BITS: i32>(
+    simd_impl: AvailableImpl,
     textures: &HashMap<egui::TextureId, EguiTexture>,
     buffer: &mut BufferMutRef,
     clip_rect: &egui::Rect,
@@ -49,28 +65,257 @@ fn draw_egui_mesh_impl<const SUBPIX_BITS: i32>(
     vert_offset: Vec2,
     allow_raster_opt: bool,
     convert_tris_to_rects: bool,
+    lcd_text: Option<ColorFieldOrder>,
+    coverage_aa: Option<&mut EdgeScratch>,
     #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
     stats: &mut crate::stats::RasterStats,
+    scissors: &[Scissor],
 ) {
-    if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+    let Some(texture) = textures.get(&mesh.texture_id) else {
         return;
-    }
+    };
+    let buffer_size = [buffer.width, buffer.height];
+    crate::dispatch_simd_impl!(simd_impl, |simd_impl| mesh_draw_cmds::<SUBPIX_BITS>(
+        texture,
+        buffer_size,
+        clip_rect,
+        mesh,
+        vert_offset,
+        allow_raster_opt,
+        convert_tris_to_rects,
+        lcd_text,
+        coverage_aa,
+        #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+        stats,
+        Some(scissors),
+        |cmd| {
+            let Some(bounds) = cmd.bounds() else {
+                return;
+            };
+            for scissor in scissors.iter().filter(|scissor| scissor.overlaps(&bounds)) {
+                cmd.raster(simd_impl, texture, buffer, scissor);
+            }
+        },
+    ))
+}
+
+/// The draw commands of a mesh, all drawn with its texture.
+#[cfg(feature = "rayon")]
+#[derive(Default)]
+pub struct MeshDrawCmds {
+    pub texture_id: egui::TextureId,
+    pub cmds: Vec<DrawCmd>,
+    /// Meshes are turned into commands in parallel, so each has its own
+    edges: EdgeScratch,
+}
+
+/// Allocations of `draw_binned()` kept between calls.
+#[cfg(feature = "rayon")]
+#[derive(Default)]
+pub struct BinScratch {
+    /// Per tile, the [mesh, command] indices of the draw commands overlapping it, in draw order
+    bins: Vec<Vec<[u32; 2]>>,
+    /// Per tile, the pixels its draw commands are rasterized into
+    tiles: Vec<Vec<[u8; 4]>>,
+}
 
+/// Replaces `cmds` with the draw commands for a mesh, to be binned and drawn with `draw_binned()`.
+#[cfg(feature = "rayon")]
+#[allow(clippy::too_many_arguments)]
+pub fn egui_mesh_draw_cmds<const SUBPIX_BITS: i32>(
+    textures: &HashMap<egui::TextureId, EguiTexture>,
+    buffer_size: [usize; 2],
+    clip_rect: &egui::Rect,
+    mesh: &egui::Mesh,
+    vert_offset: Vec2,
+    allow_raster_opt: bool,
+    convert_tris_to_rects: bool,
+    lcd_text: Option<ColorFieldOrder>,
+    coverage_aa: bool,
+    cmds: &mut MeshDrawCmds,
+) {
+    cmds.texture_id = mesh.texture_id;
+    cmds.cmds.clear();
     let Some(texture) = textures.get(&mesh.texture_id) else {
         return;
     };
+    mesh_draw_cmds::<SUBPIX_BITS>(
+        texture,
+        buffer_size,
+        clip_rect,
+        mesh,
+        vert_offset,
+        allow_raster_opt,
+        convert_tris_to_rects,
+        lcd_text,
+        coverage_aa.then_some(&mut cmds.edges),
+        None,
+        |cmd| cmds.cmds.push(cmd),
+    );
+}
+
+/// Bins the draw commands of the meshes into the tiles they overlap, then rasterizes the tiles in parallel. Each tile
+/// is drawn into its own pixels, copied from the buffer before and back to it after. Commands are drawn in order within
+/// each tile, so the result is identical to drawing them one after the other.
+#[cfg(feature = "rayon")]
+pub fn draw_binned(
+    simd_impl: AvailableImpl,
+    textures: &HashMap<egui::TextureId, EguiTexture>,
+    buffer: &mut BufferMutRef,
+    meshes: &[MeshDrawCmds],
+    scratch: &mut BinScratch,
+) {
+    use rayon::{
+        iter::{
+            IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator,
+            ParallelIterator,
+        },
+        slice::{ParallelSlice, ParallelSliceMut},
+    };
+
+    let width = buffer.width;
+    let height = buffer.height;
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tile_count = tiles_x * height.div_ceil(TILE_SIZE);
+
+    if scratch.bins.len() < tile_count {
+        scratch.bins.resize_with(tile_count, Vec::new);
+        scratch.tiles.resize_with(tile_count, Vec::new);
+    }
+    let bins = &mut scratch.bins[..tile_count];
+    let tiles = &mut scratch.tiles[..tile_count];
+    bins.iter_mut().for_each(Vec::clear);
+
+    let mut binned_any = false;
+    for (mesh_idx, mesh) in meshes.iter().enumerate() {
+        for (cmd_idx, cmd) in mesh.cmds.iter().enumerate() {
+            let Some([min, max]) = cmd.bounds() else {
+                continue;
+            };
+            let first_tile_x = min.x as usize / TILE_SIZE;
+            let last_tile_x = (max.x as usize - 1) / TILE_SIZE;
+            for tile_y in min.y as usize / TILE_SIZE..=(max.y as usize - 1) / TILE_SIZE {
+                let row = tile_y * tiles_x;
+                for bin in &mut bins[row + first_tile_x..=row + last_tile_x] {
+                    bin.push([mesh_idx as u32, cmd_idx as u32]);
+                }
+            }
+            binned_any = true;
+        }
+    }
+    if !binned_any {
+        return;
+    }
+
+    // Pixels [x_start, x_start + tile_width) x [y_start, y_start + tile_height) of a tile
+    let tile_rect = |tile_idx: usize| {
+        let x_start = tile_idx % tiles_x * TILE_SIZE;
+        let y_start = tile_idx / tiles_x * TILE_SIZE;
+        let tile_width = TILE_SIZE.min(width - x_start);
+        let tile_height = TILE_SIZE.min(height - y_start);
+        [x_start, y_start, tile_width, tile_height]
+    };
+
+    let data: &[[u8; 4]] = buffer.data;
+    tiles
+        .par_iter_mut()
+        .zip(bins.par_iter())
+        .enumerate()
+        .filter(|(_, (_, bin))| !bin.is_empty())
+        .for_each(|(tile_idx, (tile, bin))| {
+            let [x_start, y_start, tile_width, tile_height] = tile_rect(tile_idx);
+            tile.clear();
+            for y in y_start..y_start + tile_height {
+                let row_start = y * width + x_start;
+                tile.extend_from_slice(&data[row_start..row_start + tile_width]);
+            }
+            let tile_buffer = &mut BufferMutRef::new(tile, tile_width, tile_height);
+            let scissor = Scissor {
+                bounds: [
+                    i64vec2(x_start as i64, y_start as i64),
+                    i64vec2(
+                        (x_start + tile_width) as i64,
+                        (y_start + tile_height) as i64,
+                    ),
+                ],
+                buffer_x: x_start,
+                buffer_y: y_start,
+            };
+
+            crate::dispatch_simd_impl!(simd_impl, |simd_impl| {
+                // Consecutive commands are mostly of the same mesh, so its texture is only looked up once
+                let mut mesh_texture: Option<(u32, &EguiTexture)> = None;
+                for &[mesh_idx, cmd_idx] in bin {
+                    let mesh = &meshes[mesh_idx as usize];
+                    let texture = match mesh_texture {
+                        Some((idx, texture)) if idx == mesh_idx => texture,
+                        _ => {
+                            let Some(texture) = textures.get(&mesh.texture_id) else {
+                                continue;
+                            };
+                            mesh_texture = Some((mesh_idx, texture));
+                            texture
+                        }
+                    };
+                    mesh.cmds[cmd_idx as usize].raster(simd_impl, texture, tile_buffer, &scissor);
+                }
+            });
+        });
+
+    buffer
+        .data
+        .par_chunks_mut(width * TILE_SIZE)
+        .zip(tiles.par_chunks(tiles_x).zip(bins.par_chunks(tiles_x)))
+        .enumerate()
+        .for_each(|(tile_y, (tile_row, (row_tiles, row_bins)))| {
+            for (tile_x, (tile, bin)) in row_tiles.iter().zip(row_bins).enumerate() {
+                if bin.is_empty() {
+                    continue;
+                }
+                let [x_start, _, tile_width, _] = tile_rect(tile_y * tiles_x + tile_x);
+                for (row, tile_row) in tile_row
+                    .chunks_exact_mut(width)
+                    .zip(tile.chunks_exact(tile_width))
+                {
+                    row[x_start..x_start + tile_width].copy_from_slice(tile_row);
+                }
+            }
+        });
+}
+
+/// Converts the mesh into draw commands, passing each to `emit` in draw order. The outline of the mesh is drawn with
+/// coverage AA if `coverage_aa` is given, with the map of its edges built in it.
+#[allow(clippy::too_many_arguments)]
+fn mesh_draw_cmds<const SUBPIX_BITS: i32>(
+    texture: &EguiTexture,
+    buffer_size: [usize; 2],
+    clip_rect: &egui::Rect,
+    mesh: &egui::Mesh,
+    vert_offset: Vec2,
+    allow_raster_opt: bool,
+    convert_tris_to_rects: bool,
+    lcd_text: Option<ColorFieldOrder>,
+    coverage_aa: Option<&mut EdgeScratch>,
+    #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+    stats: &mut crate::stats::RasterStats,
+    scissors: Option<&[Scissor]>,
+    mut emit: impl FnMut(DrawCmd),
+) {
+    if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+        return;
+    }
 
     let indices = &mesh.indices;
     let vertices = &mesh.vertices;
 
     let clip_bounds = [
         i64vec2(
-            (clip_rect.min.x as i64).clamp(0, buffer.width as i64),
-            (clip_rect.min.y as i64).clamp(0, buffer.height as i64),
+            (clip_rect.min.x as i64).clamp(0, buffer_size[0] as i64),
+            (clip_rect.min.y as i64).clamp(0, buffer_size[1] as i64),
         ),
         i64vec2(
-            (clip_rect.max.x.ceil() as i64).clamp(0, buffer.width as i64),
-            (clip_rect.max.y.ceil() as i64).clamp(0, buffer.height as i64),
+            (clip_rect.max.x.ceil() as i64).clamp(0, buffer_size[0] as i64),
+            (clip_rect.max.y.ceil() as i64).clamp(0, buffer_size[1] as i64),
         ),
     ];
 
@@ -78,9 +323,71 @@ fn draw_egui_mesh_impl<const SUBPIX_BITS: i32>(
         return;
     }
 
-    let mut i = 0;
+    // Text is drawn with the font texture, glyph quads of it can be blitted in runs
+    let find_glyphs =
+        allow_raster_opt && convert_tris_to_rects && mesh.texture_id == egui::TextureId::default();
+
+    // Edges used by a single tri are the outline of the mesh, which is drawn with coverage AA. Edges shared by two tris
+    // are inside of it and are point sampled, so the tris meet without seams. Tris that are culled, like those without
+    // area closing a path, don't share their edges.
+    let edge_tris = coverage_aa.map(|scratch| {
+        let edge_tris = &mut scratch.edge_tris;
+        edge_tris.clear();
+        for (tri_idx, tri) in indices.chunks_exact(3).enumerate() {
+            let [a, b, c] = [0, 1, 2].map(|v| vertices[tri[v] as usize].pos);
+            if egui_orient2df(&a, &b, &c) <= 0.0 {
+                continue;
+            }
+            for edge in 0..3 {
+                let entry = edge_tris.entry(tri_edge(vertices, tri, edge)).or_default();
+                if (entry.count as usize) < entry.tris.len() {
+                    entry.tris[entry.count as usize] = tri_idx as u32 * 3;
+                }
+                entry.count += 1;
+            }
+        }
+        &*edge_tris
+    });
+
+    // Bit n is set if the tri n + 1 tris after the current one was already drawn as the second half of a rect or as
+    // part of a glyph run
+    let mut paired_tris = 0u64;
     // Get texture
-    while i < indices.len() {
+    for i in (0..indices.len()).step_by(3) {
+        let already_drawn = paired_tris & 1 != 0;
+        paired_tris >>= 1;
+        if already_drawn {
+            continue;
+        }
+
+        if find_glyphs
+            && paired_tris == 0
+            && let Some((quads, run)) = find_glyph_run(
+                texture,
+                vertices,
+                indices,
+                i,
+                vert_offset,
+                &clip_bounds,
+                lcd_text,
+            )
+        {
+            paired_tris = (1 << (quads * 2 - 1)) - 1;
+            if run.len > 0 {
+                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                stats.start_raster();
+
+                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                let glyphs = run.len;
+
+                emit(DrawCmd::GlyphRun(run));
+
+                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                stats.finish_glyph_run(glyphs);
+            }
+            continue;
+        }
+
         let mut tri = [
             vertices[indices[i] as usize],
             vertices[indices[i + 1] as usize],
@@ -101,10 +408,19 @@ fn draw_egui_mesh_impl<const SUBPIX_BITS: i32>(
 
         let fsize = tri_max - tri_min;
         if fsize.x <= 0.0 || fsize.y <= 0.0 {
-            i += 3;
             continue;
         }
 
+        // Tris only drawn into scissors they don't overlap can be skipped before any setup. Both tris of a rect have
+        // the same bounds so this never splits a rect.
+        if let Some(scissors) = scissors {
+            let visible = conservative_bounds(tri_min, tri_max, &clip_bounds)
+                .is_some_and(|bounds| scissors.iter().any(|scissor| scissor.overlaps(&bounds)));
+            if !visible {
+                continue;
+            }
+        }
+
         let color0_u8x4 = tri[0].color.to_array();
         let color1_u8x4 = tri[1].color.to_array();
         let color2_u8x4 = tri[2].color.to_array();
@@ -129,15 +445,56 @@ fn draw_egui_mesh_impl<const SUBPIX_BITS: i32>(
             tri_min,
             tri_max,
         );
+        if let Some(edge_tris) = edge_tris {
+            set_aa_edges(&mut draw, edge_tris, vertices, indices, i, vert_offset);
+        }
 
         if !allow_raster_opt {
-            draw_tri::<SUBPIX_BITS>(simd_impl, buffer, texture, &draw, true, true, true);
-            i += 3;
+            emit(DrawCmd::Prim(PrimCmd {
+                draw,
+                subpix_bits: SUBPIX_BITS,
+                is_rect: false,
+                vert_col_vary: true,
+                vert_uvs_vary: true,
+                alpha_blend: true,
+            }));
             continue;
         }
 
-        let mut vert_uvs_vary = !(draw.uv[0] == draw.uv[1] && draw.uv[0] == draw.uv[2]);
-        let mut vert_col_vary = !(color0_u8x4 == color1_u8x4 && color0_u8x4 == color2_u8x4);
+        // Rects are drawn without AA, so with it only those on pixel boundaries are converted
+        let pixel_aligned = tri_min == tri_min.round() && tri_max == tri_max.round();
+        let quad = if convert_tris_to_rects && (edge_tris.is_none() || pixel_aligned) {
+            let quad = find_quad(
+                vertices,
+                indices,
+                i,
+                vert_offset,
+                &tri,
+                [tri_min, tri_max],
+                paired_tris,
+            );
+            #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+            stats.rect_search(quad.is_some());
+            quad
+        } else {
+            None
+        };
+
+        if let Some(quad) = &quad {
+            paired_tris |= 1 << (quad.partner - 1);
+            if fsize.x * fsize.y < 0.25 {
+                continue; // early out of rects smaller than quarter px
+            }
+            draw.rect_colors = quad.corners.map(|v| u8x4_to_vec4(&v.color.to_array()));
+            draw.rect_uvs = [quad.corners[0].uv.to_vec2(), quad.corners[3].uv.to_vec2()];
+        }
+
+        let verts: &[Vertex] = match &quad {
+            Some(quad) => &quad.corners,
+            None => &tri,
+        };
+        let vert_uvs_vary = verts.iter().any(|v| v.uv != verts[0].uv);
+        let vert_col_vary = verts.iter().any(|v| v.color != verts[0].color);
         let mut alpha_blend = true;
 
         if !vert_uvs_vary {
@@ -161,92 +518,151 @@ fn draw_egui_mesh_impl<const SUBPIX_BITS: i32>(
         if !vert_uvs_vary
             && vert_col_vary
             && draw.const_tex_color_u8x4[3] == 255
-            && color0_u8x4[3] == 255
-            && color1_u8x4[3] == 255
-            && color2_u8x4[3] == 255
+            && verts.iter().all(|v| v.color.a() == 255)
         {
             alpha_blend = false;
         }
 
-        let find_rects = convert_tris_to_rects && !vert_col_vary && i + 6 < indices.len();
-        let mut found_rect = false;
-
-        if find_rects {
-            let mut tri2 = [
-                vertices[indices[i + 3] as usize],
-                vertices[indices[i + 4] as usize],
-                vertices[indices[i + 5] as usize],
-            ];
-            tri2[0].pos += vert_offset;
-            tri2[1].pos += vert_offset;
-            tri2[2].pos += vert_offset;
-
-            found_rect = tri_verts_match_corners(tri_min, tri_max, tri, tri2);
-
-            if found_rect {
-                let tri_area = egui_orient2df(&tri[0].pos, &tri[1].pos, &tri[2].pos).abs();
-                let rect_area = (tri_max.x - tri_min.x) * (tri_max.y - tri_min.y);
-                let areas_match = (tri_area - rect_area).abs() < 0.5;
-
-                if areas_match {
-                    if rect_area.abs() < 0.25 {
-                        i += 6; // Skip both tris
-                        continue; // early out of rects smaller than quarter px
-                    }
-
-                    if !vert_uvs_vary {
-                        let tri2_uvs_match = tri[0].uv == tri2[0].uv
-                            && tri[0].uv == tri2[1].uv
-                            && tri[0].uv == tri2[2].uv;
-                        vert_uvs_vary = vert_uvs_vary && tri2_uvs_match;
-                    }
-
-                    if !vert_col_vary {
-                        let tri2_colors_match = tri[0].color == tri2[0].color
-                            && tri[0].color == tri2[1].color
-                            && tri[0].color == tri2[2].color;
-                        vert_col_vary = vert_col_vary && tri2_colors_match;
-                    }
-                } else {
-                    found_rect = false;
-                }
-            }
-        }
-
-        let rect = found_rect && !vert_col_vary; // vert_col_vary not supported by rect render
+        let is_rect = quad.is_some();
 
         #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
         stats.start_raster();
-        if rect {
-            draw_rect(
-                simd_impl,
-                buffer,
-                texture,
-                &draw,
-                vert_col_vary,
-                vert_uvs_vary,
-                alpha_blend,
-            );
 
-            #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+        emit(DrawCmd::Prim(PrimCmd {
+            draw,
+            subpix_bits: SUBPIX_BITS,
+            is_rect,
+            vert_col_vary,
+            vert_uvs_vary,
+            alpha_blend,
+        }));
+
+        #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+        if is_rect {
             stats.finish_rect(fsize, vert_uvs_vary, vert_col_vary, alpha_blend);
-            i += 6;
         } else {
-            draw_tri::<SUBPIX_BITS>(
-                simd_impl,
-                buffer,
-                texture,
-                &draw,
-                vert_col_vary,
-                vert_uvs_vary,
-                alpha_blend,
+            stats.finish_tri(fsize, vert_uvs_vary, vert_col_vary, alpha_blend);
+        }
+    }
+}
+
+/// A draw command of a mesh, ready to be rasterized with the mesh's texture.
+pub enum DrawCmd {
+    Prim(PrimCmd),
+    GlyphRun(GlyphRun),
+}
+
+impl DrawCmd {
+    #[inline]
+    pub fn raster(
+        &self,
+        simd_impl: impl SelectedImpl,
+        texture: &EguiTexture,
+        buffer: &mut BufferMutRef,
+        scissor: &Scissor,
+    ) {
+        match self {
+            DrawCmd::Prim(prim) => prim.raster(simd_impl, texture, buffer, scissor),
+            DrawCmd::GlyphRun(run) => draw_glyph_run(simd_impl, buffer, texture, run, scissor),
+        }
+    }
+
+    /// Conservative pixel bounds of the command (max exclusive), None if it's empty.
+    fn bounds(&self) -> Option<[I64Vec2; 2]> {
+        match self {
+            DrawCmd::Prim(prim) => prim.bounds(),
+            DrawCmd::GlyphRun(run) => Some(run.bounds),
+        }
+    }
+}
+
+/// A tri or rect of a mesh.
+pub struct PrimCmd {
+    pub draw: DrawInfo,
+    pub subpix_bits: i32,
+    pub is_rect: bool,
+    pub vert_col_vary: bool,
+    pub vert_uvs_vary: bool,
+    pub alpha_blend: bool,
+}
+
+impl PrimCmd {
+    #[inline]
+    fn raster(
+        &self,
+        simd_impl: impl SelectedImpl,
+        texture: &EguiTexture,
+        buffer: &mut BufferMutRef,
+        scissor: &Scissor,
+    ) {
+        let draw = &self.draw;
+        let flags = (self.vert_col_vary, self.vert_uvs_vary, self.alpha_blend);
+        if self.is_rect {
+            draw_rect(
+                simd_impl, buffer, texture, draw, scissor, flags.0, flags.1, flags.2,
+            );
+        } else if self.subpix_bits == 2 {
+            draw_tri::<2>(
+                simd_impl, buffer, texture, draw, scissor, flags.0, flags.1, flags.2,
             );
+        } else {
+            debug_assert_eq!(self.subpix_bits, 8);
+            draw_tri::<8>(
+                simd_impl, buffer, texture, draw, scissor, flags.0, flags.1, flags.2,
+            );
+        }
+    }
 
-            #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
-            stats.finish_tri(fsize, vert_uvs_vary, vert_col_vary, alpha_blend);
-            i += 3;
+    /// Conservative pixel bounds of the command (max exclusive), None if it's empty.
+    fn bounds(&self) -> Option<[I64Vec2; 2]> {
+        conservative_bounds(self.draw.tri_min, self.draw.tri_max, &self.draw.clip_bounds)
+    }
+}
+
+/// Pixel bounds (max exclusive) that a tri or rect with the given bounds may write to, None if it's empty.
+#[inline]
+fn conservative_bounds(
+    tri_min: Vec2,
+    tri_max: Vec2,
+    clip_bounds: &[I64Vec2; 2],
+) -> Option<[I64Vec2; 2]> {
+    let min =
+        i64vec2(tri_min.x.floor() as i64 - 1, tri_min.y.floor() as i64 - 1).max(clip_bounds[0]);
+    let max = i64vec2(tri_max.x.ceil() as i64 + 1, tri_max.y.ceil() as i64 + 1).min(clip_bounds[1]);
+    (min.x < max.x && min.y < max.y).then_some([min, max])
+}
+
+/// The part of the target a draw writes to. Unlike `DrawInfo::clip_bounds` this doesn't affect how attributes are
+/// interpolated, so drawing a tri one tile at a time writes the same pixels as drawing it all at once.
+#[derive(Clone, Copy, Default)]
+pub struct Scissor {
+    pub bounds: [I64Vec2; 2],
+    /// Screen space x of the first column of the buffer being drawn to
+    pub buffer_x: usize,
+    /// Screen space y of the first row of the buffer being drawn to
+    pub buffer_y: usize,
+}
+
+impl Scissor {
+    pub fn full(buffer: &BufferMutRef) -> Self {
+        Scissor {
+            bounds: [
+                i64vec2(0, 0),
+                i64vec2(buffer.width as i64, buffer.height as i64),
+            ],
+            buffer_x: 0,
+            buffer_y: 0,
         }
     }
+
+    /// Returns true if the scissor overlaps the given bounds (max exclusive)
+    #[inline]
+    fn overlaps(&self, [min, max]: &[I64Vec2; 2]) -> bool {
+        min.x < self.bounds[1].x
+            && max.x > self.bounds[0].x
+            && min.y < self.bounds[1].y
+            && max.y > self.bounds[0].y
+    }
 }
 
 pub struct DrawInfo {
@@ -261,6 +677,24 @@ pub struct DrawInfo {
     pub const_vert_color: Vec4,
     pub const_vert_color_u8x4: [u8; 4],
     pub const_tri_color_u8x4: [u8; 4],
+    pub rect_colors: [Vec4; 4], // Corner colors of a rect: min/min, max/min, min/max, max/max
+    pub rect_uvs: [Vec2; 2],    // Uvs at the min/min and max/max corners of a rect
+    /// Edges of a tri drawn with coverage AA, bit n is set for the edge opposite vertex n
+    pub aa_edges: u8,
+    /// The outline edges of the tri across each edge shared with another tri. Samples of pixels on a shared edge that
+    /// fall on the other tri are tested against them, so thin shapes made of two tris, like lines, get the coverage of
+    /// both.
+    pub aa_neighbors: [AaNeighbor; 3],
+}
+
+/// Outline edges of the tri across a shared edge, see `DrawInfo::aa_neighbors`.
+#[derive(Clone, Copy, Default)]
+pub struct AaNeighbor {
+    /// Vertex of the neighboring tri that's not on the shared edge
+    pub vert: Vec2,
+    /// Bit k is set if the neighbor's edge from vertex k of the shared edge to `vert` is an outline edge. The shared
+    /// edge goes from vertex n + 1 to vertex n + 2 of the tri.
+    pub outline: u8,
 }
 
 impl DrawInfo {
@@ -284,39 +718,324 @@ impl DrawInfo {
             const_vert_color: Vec4::ONE,
             const_vert_color_u8x4: [255; 4],
             const_tri_color_u8x4: [255; 4],
+            rect_colors: [Vec4::ONE; 4],
+            rect_uvs: [Vec2::ZERO; 2],
+            aa_edges: 0,
+            aa_neighbors: Default::default(),
+        }
+    }
+}
+
+/// Map of the edges of a mesh drawn with coverage AA to the tris using them, kept between meshes so building it doesn't
+/// allocate once it's large enough.
+#[derive(Default)]
+pub struct EdgeScratch {
+    edge_tris: HashMap<EdgeKey, EdgeTris>,
+}
+
+/// The tris of a mesh using an edge, see `set_aa_edges()`.
+#[derive(Default)]
+struct EdgeTris {
+    count: u32,
+    /// Index into the mesh indices of the first two tris
+    tris: [u32; 2],
+}
+
+/// Sets the edges of the tri at `indices[i..i + 3]` drawn with coverage AA, those used by no other tri, and the outline
+/// edges of the tris across the others.
+fn set_aa_edges(
+    draw: &mut DrawInfo,
+    edge_tris: &HashMap<EdgeKey, EdgeTris>,
+    vertices: &[Vertex],
+    indices: &[u32],
+    i: usize,
+    vert_offset: Vec2,
+) {
+    let vert_key = |idx: u32| {
+        let pos = vertices[idx as usize].pos;
+        [pos.x.to_bits(), pos.y.to_bits()]
+    };
+    let is_outline = |a: u32, b: u32| {
+        let (a, b) = (vert_key(a), vert_key(b));
+        edge_tris
+            .get(&[a.min(b), a.max(b)])
+            .is_some_and(|tris| tris.count == 1)
+    };
+    for edge in 0..3 {
+        let key = tri_edge(vertices, &indices[i..i + 3], edge);
+        let Some(tris) = edge_tris.get(&key) else {
+            continue;
+        };
+        if tris.count == 1 {
+            draw.aa_edges |= 1 << edge;
+        } else if tris.count == 2 {
+            let other = if tris.tris[0] as usize == i {
+                tris.tris[1]
+            } else {
+                tris.tris[0]
+            } as usize;
+            let Some(&vert) = indices[other..other + 3]
+                .iter()
+                .find(|&&idx| !key.contains(&vert_key(idx)))
+            else {
+                continue;
+            };
+            let shared = [indices[i + (edge + 1) % 3], indices[i + (edge + 2) % 3]];
+            let neighbor = &mut draw.aa_neighbors[edge];
+            neighbor.vert = vertices[vert as usize].pos.to_vec2() + vert_offset;
+            for (k, from) in shared.into_iter().enumerate() {
+                if is_outline(from, vert) {
+                    neighbor.outline |= 1 << k;
+                }
+            }
         }
     }
 }
 
+/// An edge by the bits of its vertex positions, so tris meet at an edge even where the mesh splits its vertices.
+type EdgeKey = [[u32; 2]; 2];
+
+/// Key of the edge opposite vertex `edge` of a tri, in either winding.
+#[inline(always)]
+fn tri_edge(vertices: &[Vertex], tri: &[u32], edge: usize) -> EdgeKey {
+    let [a, b] = [(edge + 1) % 3, (edge + 2) % 3].map(|v| {
+        let pos = vertices[tri[v] as usize].pos;
+        [pos.x.to_bits(), pos.y.to_bits()]
+    });
+    [a.min(b), a.max(b)]
+}
+
 #[inline(always)]
 /// Returns twice the signed area of triangle abc
 pub fn egui_orient2df(a: &Pos2, b: &Pos2, c: &Pos2) -> f32 {
     (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
 }
 
-fn tri_verts_match_corners(
-    tri_min: Vec2,
-    tri_max: Vec2,
-    tri: [Vertex; 3],
-    tri2: [Vertex; 3],
-) -> bool {
+/// Max number of tris after the current one that are searched for the other half of a rect
+const RECT_SEARCH_TRIS: usize = 8;
+
+/// Max distance in pixels from a rect corner for a vertex to still be considered on that corner
+const RECT_EPSILON: f32 = 1.0 / 64.0;
+
+/// Two tris that together cover an axis aligned rect
+struct Quad {
+    partner: usize, // How many tris after the current one the other half of the rect is
+    corners: [Vertex; 4], // min/min, max/min, min/max, max/max
+}
+
+/// Searches the tris following the one at `indices[i..i + 3]` for one that shares its diagonal, such that together they
+/// form an axis aligned rect that `draw_rect` can draw. Tris already drawn as part of another rect are skipped. The
+/// search stops at the first tri that overlaps the rect, since drawing the rect before it would change the blend order.
+///
+/// Tris are matched by the rect corners their vertex positions are on, not by shared vertex indices, so the order of
+/// the indices within and between the two tris doesn't matter, nor whether they share vertices. Only the next
+/// `RECT_SEARCH_TRIS` tris are searched though: a rect whose halves are further apart in the index buffer is drawn as
+/// two tris. egui emits both halves of a rect next to each other.
+fn find_quad(
+    vertices: &[Vertex],
+    indices: &[u32],
+    i: usize,
+    vert_offset: Vec2,
+    tri: &[Vertex; 3],
+    [rect_min, rect_max]: [Vec2; 2],
+    paired_tris: u64,
+) -> Option<Quad> {
+    let (tri_corners, tri_missing) = tri_rect_corners(tri, rect_min, rect_max)?;
+
+    for partner in 1..=RECT_SEARCH_TRIS {
+        let j = i + partner * 3;
+        if j + 3 > indices.len() {
+            break;
+        }
+        if paired_tris & (1 << (partner - 1)) != 0 {
+            continue;
+        }
+
+        let mut tri2 = [
+            vertices[indices[j] as usize],
+            vertices[indices[j + 1] as usize],
+            vertices[indices[j + 2] as usize],
+        ];
+        tri2[0].pos += vert_offset;
+        tri2[1].pos += vert_offset;
+        tri2[2].pos += vert_offset;
+
+        if let Some((tri2_corners, tri2_missing)) = tri_rect_corners(&tri2, rect_min, rect_max) {
+            // The tris only share a diagonal if the corners they leave out are opposite each other
+            if tri_missing ^ tri2_missing == 3 {
+                return quad_from_corners(partner, tri, tri_corners, &tri2, tri2_corners);
+            }
+        }
+
+        let tri2_min = tri2[0].pos.min(tri2[1].pos).min(tri2[2].pos);
+        let tri2_max = tri2[0].pos.max(tri2[1].pos).max(tri2[2].pos);
+        if tri2_min.x < rect_max.x
+            && tri2_max.x > rect_min.x
+            && tri2_min.y < rect_max.y
+            && tri2_max.y > rect_min.y
+        {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// Maps each vertex of the tri to the rect corner it's on (0: min/min, 1: max/min, 2: min/max, 3: max/max). Returns
+/// None if a vertex isn't on a corner or two vertices are on the same one, otherwise also returns the uncovered corner.
+fn tri_rect_corners(
+    tri: &[Vertex; 3],
+    rect_min: Vec2,
+    rect_max: Vec2,
+) -> Option<([usize; 3], usize)> {
     #[inline(always)]
-    fn close(a: f32, b: f32) -> bool {
-        //(a - b).abs() <= 0.1
-        a == b
+    fn side(v: f32, min: f32, max: f32) -> Option<usize> {
+        if (v - min).abs() <= RECT_EPSILON {
+            Some(0)
+        } else if (v - max).abs() <= RECT_EPSILON {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    let mut corners = [0; 3];
+    let mut covered = 0u32;
+    for (corner, vert) in corners.iter_mut().zip(tri) {
+        *corner = side(vert.pos.x, rect_min.x, rect_max.x)?
+            + 2 * side(vert.pos.y, rect_min.y, rect_max.y)?;
+        covered |= 1 << *corner;
+    }
+
+    if covered.count_ones() != 3 {
+        return None;
+    }
+    Some((corners, (!covered & 0xF).trailing_zeros() as usize))
+}
+
+/// Collects the glyph quads starting at the tri `indices[i..i + 3]` into a run. Glyphs are quads added by
+/// `Mesh::add_rect_with_uv()` with a constant color, whose texels line up 1:1 with pixels. They are blended exactly like
+/// `draw_rect` would blend them. Returns the number of quads consumed, None if the tri doesn't start a glyph quad.
+/// Quads that are clipped away or too small to be drawn are consumed without being added to the run.
+///
+/// `lcd` is the output field order if the glyphs are to be drawn as LCD text, which is not blended like `draw_rect`.
+#[allow(clippy::too_many_arguments)]
+fn find_glyph_run(
+    texture: &EguiTexture,
+    vertices: &[Vertex],
+    indices: &[u32],
+    i: usize,
+    vert_offset: Vec2,
+    clip_bounds: &[I64Vec2; 2],
+    lcd: Option<ColorFieldOrder>,
+) -> Option<(usize, GlyphRun)> {
+    let mut run = GlyphRun {
+        color: vertices[indices[i] as usize].color.to_array(),
+        lcd,
+        glyphs: [Glyph::default(); GLYPH_RUN_MAX],
+        len: 0,
+        bounds: [I64Vec2::default(); 2],
+    };
+
+    let mut quads = 0;
+    while quads < GLYPH_RUN_MAX {
+        let j = i + quads * 6;
+        let Some(&[b, b1, b2, b2_, b1_, b3]) = indices.get(j..j + 6) else {
+            break;
+        };
+        if [b1, b2, b2_, b1_, b3] != [b + 1, b + 2, b + 2, b + 1, b + 3] {
+            break;
+        }
+        let Some(&[lt, rt, lb, rb]) = vertices.get(b as usize..b as usize + 4) else {
+            break;
+        };
+
+        let color = run.color;
+        let axis_aligned = lt.pos.y == rt.pos.y
+            && lb.pos.y == rb.pos.y
+            && lt.pos.x == lb.pos.x
+            && rt.pos.x == rb.pos.x
+            && lt.uv.y == rt.uv.y
+            && lb.uv.y == rb.uv.y
+            && lt.uv.x == lb.uv.x
+            && rt.uv.x == rb.uv.x;
+        if !axis_aligned
+            || [lt, rt, lb, rb].iter().any(|v| v.color.to_array() != color)
+            || lt.pos.x >= rt.pos.x
+            || lt.pos.y >= lb.pos.y
+        {
+            break;
+        }
+
+        let rect_min = lt.pos.to_vec2() + vert_offset;
+        let rect_max = rb.pos.to_vec2() + vert_offset;
+        let fsize = rect_max - rect_min;
+        let [min, max] = rect_px_bounds(rect_min, rect_max, clip_bounds);
+        if fsize.x * fsize.y >= 0.25 && min.x < max.x && min.y < max.y {
+            let uvs = RectUvs::new(
+                texture,
+                [lt.uv.to_vec2(), rb.uv.to_vec2()],
+                rect_min,
+                rect_max,
+                min,
+            );
+            if !uvs.use_nearest_sampling(texture) || !uvs.no_texture_wrap_or_overflow(texture) {
+                break;
+            }
+            let tex_min = i64vec2(uvs.ts_min.x as i64, uvs.ts_min.y as i64);
+            // Unclipped pixel columns, rounded like rect_px_bounds()
+            let cols = [(rect_min.x + 0.5) as i64, (rect_max.x + 0.5) as i64];
+            let [min_x, max_x] = if lcd.is_some() {
+                [
+                    (cols[0] - 1).max(clip_bounds[0].x),
+                    (cols[1] + 1).min(clip_bounds[1].x),
+                ]
+            } else {
+                [min.x, max.x]
+            };
+            run.push(Glyph {
+                px_min: [min_x as u32, min.y as u32],
+                px_max: [max_x as u32, max.y as u32],
+                tex_offset: [(tex_min.x - min.x) as i32, (tex_min.y - min.y) as i32],
+                cols: cols.map(|col| col as i32),
+            });
+        }
+        quads += 1;
     }
 
-    // https://github.com/emilk/imgui_software_renderer/blob/b5ae63a9e42eccf7db3bf64696761a53424c53dd/src/imgui_sw.cpp#L577
-    (close(tri[0].pos.x, tri_min.x) || close(tri[0].pos.x, tri_max.x))
-        && (close(tri[0].pos.y, tri_min.y) || close(tri[0].pos.y, tri_max.y))
-        && (close(tri[1].pos.x, tri_min.x) || close(tri[1].pos.x, tri_max.x))
-        && (close(tri[1].pos.y, tri_min.y) || close(tri[1].pos.y, tri_max.y))
-        && (close(tri[2].pos.x, tri_min.x) || close(tri[2].pos.x, tri_max.x))
-        && (close(tri[2].pos.y, tri_min.y) || close(tri[2].pos.y, tri_max.y))
-        && (close(tri2[0].pos.x, tri_min.x) || close(tri2[0].pos.x, tri_max.x))
-        && (close(tri2[0].pos.y, tri_min.y) || close(tri2[0].pos.y, tri_max.y))
-        && (close(tri2[1].pos.x, tri_min.x) || close(tri2[1].pos.x, tri_max.x))
-        && (close(tri2[1].pos.y, tri_min.y) || close(tri2[1].pos.y, tri_max.y))
-        && (close(tri2[2].pos.x, tri_min.x) || close(tri2[2].pos.x, tri_max.x))
-        && (close(tri2[2].pos.y, tri_min.y) || close(tri2[2].pos.y, tri_max.y))
+    (quads > 0).then_some((quads, run))
+}
+
+/// Builds the quad if the vertex attributes of both tris can be drawn as one rect. Vertices on the same corner must
+/// agree. Colors must be linear across the rect, since only then does interpolating over the whole rect match
+/// interpolating over each tri separately. Uvs must be axis aligned.
+fn quad_from_corners(
+    partner: usize,
+    tri: &[Vertex; 3],
+    tri_corners: [usize; 3],
+    tri2: &[Vertex; 3],
+    tri2_corners: [usize; 3],
+) -> Option<Quad> {
+    let mut corners: [Option<Vertex>; 4] = [None; 4];
+    let verts = tri
+        .iter()
+        .zip(tri_corners)
+        .chain(tri2.iter().zip(tri2_corners));
+    for (vert, corner) in verts {
+        match corners[corner] {
+            Some(v) if v.uv != vert.uv || v.color != vert.color => return None,
+            _ => corners[corner] = Some(*vert),
+        }
+    }
+    let corners = [corners[0]?, corners[1]?, corners[2]?, corners[3]?];
+
+    let [c00, c10, c01, c11] = corners.map(|v| v.color.to_array());
+    let colors_linear =
+        (0..4).all(|c| c00[c] as u16 + c11[c] as u16 == c10[c] as u16 + c01[c] as u16);
+
+    let [uv00, uv10, uv01, uv11] = corners.map(|v| v.uv);
+    let uvs_axis_aligned =
+        uv00.x == uv01.x && uv10.x == uv11.x && uv00.y == uv10.y && uv01.y == uv11.y;
+
+    (colors_linear && uvs_axis_aligned).then_some(Quad { partner, corners })
 }
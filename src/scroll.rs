@@ -0,0 +1,282 @@
+//! Detection of cached primitives whose mesh moved by a whole number of pixels since they were rasterized, typically
+//! the contents of a `ScrollArea`. The pixels that are still visible can then be copied over from the previous
+//! primitive and only the newly exposed part needs to be rasterized.
+//!
+//! Where nothing else changes under or over the primitive, the canvas tiles it covers are shifted in place instead of
+//! composited again, see `EguiSoftwareRender::shift_scrolled_tiles()`.
+
+use ahash::HashMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+use egui::{Mesh, Vec2};
+
+use crate::{
+    hash::{Hash32, Hash64},
+    math::i64vec2::{I64Vec2, i64vec2},
+};
+
+/// Max number of tris of the new mesh used to vote for the scroll offset
+const MAX_VOTING_TRIS: usize = 256;
+
+/// Tris that have more equivalents than this in the old mesh don't vote. Rare tris are the ones that tell rows of
+/// similar text apart, and this bounds the number of votes.
+const MAX_VOTER_EQUIVALENTS: u32 = 64;
+
+/// Max number of the most voted for offsets whose runs of matching tris are compared. Rows of similar text vote for
+/// offsets that are off by whole rows almost as much as for the right one, but their runs end at the first difference.
+const MAX_CANDIDATE_OFFSETS: usize = 64;
+
+/// Vertex positions are compared at this precision (8 subpixel bits, the finest the rasterizer uses)
+const POS_QUANTIZE: f32 = 256.0;
+
+/// Key of a tri that doesn't depend on where the tri is, and the pixel space bounds of the tri.
+#[derive(Clone, Copy)]
+pub struct TriKey {
+    hash: u64,
+    /// Computed independently from `hash` with cache verification, so tris whose hashes collide aren't taken as moved.
+    /// Zero otherwise.
+    fingerprint: u32,
+    min: Vec2,
+    max: Vec2,
+}
+
+/// Writes the key of each tri of a pixel space mesh to `keys`, in draw order. With `verify` the keys also get a
+/// fingerprint, see `EguiSoftwareRender::with_cache_verification()`.
+pub fn tri_keys(mesh: &Mesh, verify: bool, keys: &mut Vec<TriKey>) {
+    keys.clear();
+    keys.extend(mesh.indices.chunks_exact(3).map(|tri| {
+        let tri = [
+            mesh.vertices[tri[0] as usize],
+            mesh.vertices[tri[1] as usize],
+            mesh.vertices[tri[2] as usize],
+        ];
+        let min = tri[0].pos.min(tri[1].pos).min(tri[2].pos).to_vec2();
+        let max = tri[0].pos.max(tri[1].pos).max(tri[2].pos).to_vec2();
+        let mut hasher = Hash64::new_fnv();
+        let mut fingerprint = Hash32::new_fnv();
+        for v in tri {
+            // Relative to min so never negative, rounds without a call to round()
+            let pos = (v.pos.to_vec2() - min) * POS_QUANTIZE + Vec2::splat(0.5);
+            let (pos_x, pos_y) = (pos.x as u32, pos.y as u32);
+            let color = u32::from_le_bytes(v.color.to_array());
+            hasher.hash(pos_x as u64 | (pos_y as u64) << 32);
+            hasher.hash(v.uv.x.to_bits() as u64 | (v.uv.y.to_bits() as u64) << 32);
+            hasher.hash(color as u64);
+            hasher.fnv_wrap();
+            if verify {
+                for field in [pos_x, pos_y, v.uv.x.to_bits(), v.uv.y.to_bits(), color] {
+                    fingerprint.hash_wrap(field);
+                }
+            }
+        }
+        TriKey {
+            hash: hasher.finalize(),
+            fingerprint: if verify { fingerprint.finalize() } else { 0 },
+            min,
+            max,
+        }
+    }));
+}
+
+/// Allocations of `find_scroll()` kept between calls.
+#[derive(Default)]
+pub struct ScrollScratch {
+    /// Hash and index of the tris of the new mesh that vote, sorted by hash
+    voters: Vec<(u64, usize)>,
+    /// Range of each voting hash in `voters` and the number of equivalent tris in the old mesh
+    voter_hashes: HashMap<u64, (Range<usize>, u32)>,
+    /// Votes for each offset and a matching pair of new and old tris
+    votes: HashMap<I64Vec2, (u32, usize, usize)>,
+    sorted_votes: Vec<(I64Vec2, (u32, usize, usize))>,
+}
+
+/// A translation of a primitive's mesh from the previous primitive at the same place.
+#[derive(Clone, Copy)]
+pub struct Scroll {
+    /// Offset in pixels from the previous primitive's mesh to the new one
+    pub offset: I64Vec2,
+    /// Part of the new primitive's bounds (max exclusive) where the pixels are the same as the previous primitive's
+    /// pixels shifted by `offset`
+    pub overlap: [I64Vec2; 2],
+}
+
+/// Finds an offset by which the previous mesh (`old`, rasterized within the pixel bounds `old_bounds`) was shifted to
+/// produce the pixels of the new mesh within `new_bounds`. The tris that moved must be a contiguous run in both
+/// meshes and every other tri must be away from the overlap, so the pixels there are exactly the same as before.
+pub fn find_scroll(
+    new: &[TriKey],
+    new_bounds: [I64Vec2; 2],
+    old: &[TriKey],
+    old_bounds: [I64Vec2; 2],
+    scratch: &mut ScrollScratch,
+) -> Option<Scroll> {
+    if new.is_empty() || old.is_empty() {
+        return None;
+    }
+
+    // Tris spread over the new mesh vote for the offset to each equivalent tri in the old mesh. Tris that didn't move
+    // relative to each other all vote for the same offset. Remember one matching pair for each offset.
+    let ScrollScratch {
+        voters,
+        voter_hashes,
+        votes,
+        sorted_votes,
+    } = scratch;
+    let step = new.len().div_ceil(MAX_VOTING_TRIS);
+    voters.clear();
+    voters.extend(
+        new.iter()
+            .enumerate()
+            .step_by(step)
+            .map(|(i, key)| (key.hash, i)),
+    );
+    voters.sort_unstable();
+    voter_hashes.clear();
+    for (idx, &(hash, _)) in voters.iter().enumerate() {
+        voter_hashes.entry(hash).or_insert((idx..idx, 0)).0.end = idx + 1;
+    }
+    for old_key in old {
+        if let Some((_, equivalents)) = voter_hashes.get_mut(&old_key.hash) {
+            *equivalents += 1;
+        }
+    }
+    votes.clear();
+    for (j, old_key) in old.iter().enumerate() {
+        let Some((voter_range, _)) = voter_hashes
+            .get(&old_key.hash)
+            .filter(|(_, equivalents)| *equivalents <= MAX_VOTER_EQUIVALENTS)
+        else {
+            continue;
+        };
+        for &(_, i) in &voters[voter_range.clone()] {
+            if let Some(offset) = whole_px_offset(new[i].min - old_key.min) {
+                votes.entry(offset).or_insert((0, i, j)).0 += 1;
+            }
+        }
+    }
+
+    // The offset whose matching pair extends to the longest run of tris that moved by it
+    sorted_votes.clear();
+    sorted_votes.extend(votes.drain());
+    // Ties are ordered by offset, as the order of the drained votes depends on the capacity of the map
+    sorted_votes.sort_unstable_by_key(|(offset, (votes, _, _))| {
+        (core::cmp::Reverse(*votes), offset.x, offset.y)
+    });
+    let (offset, new_run, old_run) = sorted_votes
+        .iter()
+        .copied()
+        .take(MAX_CANDIDATE_OFFSETS)
+        .map(|(offset, (_, i, j))| {
+            let (new_run, old_run) = moved_run(new, old, offset, [i, j]);
+            (offset, new_run, old_run)
+        })
+        .max_by_key(|(_, new_run, _)| new_run.len())?;
+
+    // Pixels near the edges of the bounds may be cut off by the clip rect, so they're never reused
+    let overlap = shrink(intersect(new_bounds, translate(old_bounds, offset)), 1);
+    if is_empty(&overlap) {
+        return None;
+    }
+
+    let touches_overlap = |key: &TriKey, offset: Vec2| {
+        // A tri may write pixels up to 1px outside of its bounds
+        let min = key.min + offset - Vec2::splat(1.0);
+        let max = key.max + offset + Vec2::splat(1.0);
+        min.x < overlap[1].x as f32
+            && max.x > overlap[0].x as f32
+            && min.y < overlap[1].y as f32
+            && max.y > overlap[0].y as f32
+    };
+
+    let foffset = Vec2::new(offset.x as f32, offset.y as f32);
+    let mut new_outside_run = new[..new_run.start].iter().chain(&new[new_run.end..]);
+    let mut old_outside_run = old[..old_run.start].iter().chain(&old[old_run.end..]);
+    if new_outside_run.any(|key| touches_overlap(key, Vec2::ZERO))
+        || old_outside_run.any(|key| touches_overlap(key, foffset))
+    {
+        return None;
+    }
+
+    Some(Scroll { offset, overlap })
+}
+
+/// Extends the pair of new and old tris `[i, j]` to the whole run of tris that moved by `offset`, returning the
+/// ranges of the run in the new and old tris. Tris whose hashes match but whose fingerprints don't are a collision and
+/// end the run, so their pixels are rasterized again.
+fn moved_run(
+    new: &[TriKey],
+    old: &[TriKey],
+    offset: I64Vec2,
+    [i, j]: [usize; 2],
+) -> (Range<usize>, Range<usize>) {
+    let offset = Vec2::new(offset.x as f32, offset.y as f32);
+    let moved = |new_idx: usize, old_idx: usize| {
+        let (new, old) = (&new[new_idx], &old[old_idx]);
+        new.hash == old.hash
+            && new.fingerprint == old.fingerprint
+            && is_close(new.min, old.min + offset)
+    };
+    let run_back = (1..=i.min(j)).take_while(|&n| moved(i - n, j - n)).count();
+    let run_len = (i - run_back..new.len())
+        .zip(j - run_back..old.len())
+        .take_while(|&(new_idx, old_idx)| moved(new_idx, old_idx))
+        .count();
+    (
+        i - run_back..i - run_back + run_len,
+        j - run_back..j - run_back + run_len,
+    )
+}
+
+/// Returns the offset rounded to whole pixels, None if it's not close to whole pixels.
+fn whole_px_offset(offset: Vec2) -> Option<I64Vec2> {
+    let rounded = offset.round();
+    is_close(offset, rounded).then(|| i64vec2(rounded.x as i64, rounded.y as i64))
+}
+
+#[inline]
+fn is_close(a: Vec2, b: Vec2) -> bool {
+    let diff = (a - b).abs();
+    diff.x < 0.5 / POS_QUANTIZE && diff.y < 0.5 / POS_QUANTIZE
+}
+
+pub fn intersect(a: [I64Vec2; 2], b: [I64Vec2; 2]) -> [I64Vec2; 2] {
+    [a[0].max(b[0]), a[1].min(b[1])]
+}
+
+pub fn translate(bounds: [I64Vec2; 2], offset: I64Vec2) -> [I64Vec2; 2] {
+    [bounds[0] + offset, bounds[1] + offset]
+}
+
+fn shrink(bounds: [I64Vec2; 2], px: i64) -> [I64Vec2; 2] {
+    [bounds[0] + px, bounds[1] - px]
+}
+
+/// Whether `inner` is entirely within `outer`, `inner` must not be empty.
+pub fn contains(outer: [I64Vec2; 2], inner: [I64Vec2; 2]) -> bool {
+    inner[0].x >= outer[0].x
+        && inner[0].y >= outer[0].y
+        && inner[1].x <= outer[1].x
+        && inner[1].y <= outer[1].y
+}
+
+pub fn is_empty(bounds: &[I64Vec2; 2]) -> bool {
+    bounds[1].x <= bounds[0].x || bounds[1].y <= bounds[0].y
+}
+
+/// Splits the part of `bounds` that's outside of `hole` into up to 4 non-overlapping rects. `hole` must be inside
+/// `bounds`.
+pub fn bounds_around(
+    bounds: [I64Vec2; 2],
+    hole: [I64Vec2; 2],
+) -> impl Iterator<Item = [I64Vec2; 2]> {
+    let [min, max] = bounds;
+    let [hole_min, hole_max] = hole;
+    [
+        [min, i64vec2(max.x, hole_min.y)],
+        [i64vec2(min.x, hole_max.y), max],
+        [i64vec2(min.x, hole_min.y), i64vec2(hole_min.x, hole_max.y)],
+        [i64vec2(hole_max.x, hole_min.y), i64vec2(max.x, hole_max.y)],
+    ]
+    .into_iter()
+    .filter(|bounds| !is_empty(bounds))
+}
@@ -27,7 +27,15 @@ pub struct RasterStats {
     pub rect_alpha_blend: u32,                 // Count of rects that required alpha blending
     pub tris: u32,                             // Total tris drawn
     pub rects: u32,                            // Total rects drawn
-    pub start: Instant,                        // Time just before latest rasterization
+    pub rect_searches: u32, // Count of tris searched for a second tri completing a rect
+    pub rect_search_hits: u32, // Count of rect searches that found one
+    pub scrolled_prims: u32, // Count of cached primitives shifted from a previous frame's instead of fully rasterized
+    pub shifted_tiles: u32, // Count of canvas tiles shifted with a scrolled primitive instead of composited
+    pub cache_collisions: u32, // Count of primitive cache key collisions caught by cache verification
+    pub glyph_runs: u32,       // Count of glyph runs blitted by the text fast path
+    pub glyphs: u32,           // Total glyphs in glyph runs
+    pub glyph_run_time: f32,   // Time spent blitting glyph runs
+    pub start: Instant,        // Time just before latest rasterization
     pub set_textures: f32,
     pub update_dirty_tiles: f32,
     pub update_canvas_from_cached: f32,
@@ -51,6 +59,14 @@ impl Default for RasterStats {
             rect_alpha_blend: Default::default(),
             rects: Default::default(),
             tris: Default::default(),
+            rect_searches: Default::default(),
+            rect_search_hits: Default::default(),
+            scrolled_prims: Default::default(),
+            shifted_tiles: Default::default(),
+            cache_collisions: Default::default(),
+            glyph_runs: Default::default(),
+            glyphs: Default::default(),
+            glyph_run_time: Default::default(),
             set_textures: Default::default(),
             update_dirty_tiles: Default::default(),
             update_canvas_from_cached: Default::default(),
@@ -82,7 +98,21 @@ fn insert_or_increment(long_side_size: u32, elapsed: f32, area: f32, map: &mut H
 
 impl RasterStats {
     pub(crate) fn clear(&mut self) {
+        // The buckets are emptied in place, so collecting stats doesn't allocate every frame
+        let mut buckets = [
+            core::mem::take(&mut self.tri_width_buckets),
+            core::mem::take(&mut self.tri_height_buckets),
+            core::mem::take(&mut self.rect_width_buckets),
+            core::mem::take(&mut self.rect_height_buckets),
+        ];
+        buckets.iter_mut().for_each(HashMap::clear);
         *self = RasterStats::default();
+        [
+            self.tri_width_buckets,
+            self.tri_height_buckets,
+            self.rect_width_buckets,
+            self.rect_height_buckets,
+        ] = buckets;
     }
 
     #[cfg(not(feature = "rayon"))]
@@ -118,6 +148,19 @@ impl RasterStats {
         self.rect_alpha_blend += alpha_blend as u32;
     }
 
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) fn rect_search(&mut self, found: bool) {
+        self.rect_searches += 1;
+        self.rect_search_hits += found as u32;
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) fn finish_glyph_run(&mut self, glyphs: usize) {
+        self.glyph_run_time += self.start.elapsed().as_secs_f32();
+        self.glyph_runs += 1;
+        self.glyphs += glyphs as u32;
+    }
+
     #[cfg(not(feature = "rayon"))]
     pub(crate) fn finish_tri(
         &mut self,
@@ -164,6 +207,37 @@ impl RasterStats {
                     stat("blit_canvas_to_buffer", self.blit_canvas_to_buffer);
                     stat("render_direct", self.render_direct);
 
+                    let hit_rate = self.rect_search_hits as f32 / self.rect_searches.max(1) as f32;
+                    ui.label("rect detection hit rate");
+                    ui.label(format!(
+                        "{:.1}% ({}/{})",
+                        hit_rate * 100.0,
+                        self.rect_search_hits,
+                        self.rect_searches
+                    ));
+                    ui.end_row();
+
+                    ui.label("scrolled prims");
+                    ui.label(format!("{}", self.scrolled_prims));
+                    ui.end_row();
+
+                    ui.label("shifted tiles");
+                    ui.label(format!("{}", self.shifted_tiles));
+                    ui.end_row();
+
+                    ui.label("glyph runs");
+                    ui.label(format!(
+                        "{} ({} glyphs, {:.0}μs)",
+                        self.glyph_runs,
+                        self.glyphs,
+                        self.glyph_run_time * 1000000.0 // Seconds to microseconds
+                    ));
+                    ui.end_row();
+
+                    ui.label("cache collisions");
+                    ui.label(format!("{}", self.cache_collisions));
+                    ui.end_row();
+
                     ui.heading("");
                     ui.heading("Tri");
                     ui.heading("Rect");
@@ -1,9 +1,119 @@
-use alloc::{string::String, vec, vec::Vec};
-use egui::TexturesDelta;
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use egui::{TexturesDelta, Vec2};
 use egui_kittest::TestRenderer;
 use image::ImageBuffer;
 
-use crate::{BufferMutRef, EguiSoftwareRender};
+use crate::{
+    BufferMutRef, ColorFieldOrder, EguiSoftwareRender, YuvFormat,
+    color::{AvailableImpl, GenericImpl, SelectedImpl, YuvCoeffs, available_instrs},
+    egui_texture::{EguiTexture, TexelFormat},
+    math::vec4::vec4,
+};
+
+/// Key of a primitive in the primitive cache at 1 pixel per point, for a clip rect relative to the cropped mesh. Exposed
+/// to test the cache keys for collisions.
+pub fn primitive_cache_key(clip_rect: egui::Rect, mesh: &egui::Mesh) -> u64 {
+    crate::primitive_cache_key(&clip_rect, mesh, 1.0)
+}
+
+/// Clears the bits of the primitive cache keys of `renderer` that aren't in `mask`, to force keys to collide.
+pub fn set_cache_key_mask(renderer: &mut EguiSoftwareRender, mask: u64) {
+    renderer.cache_key_mask = mask;
+}
+
+/// Whether the texture `id` of `renderer` is stored as single channel alpha, None if there's no such texture.
+pub fn texture_is_alpha(renderer: &EguiSoftwareRender, id: egui::TextureId) -> Option<bool> {
+    let textures = renderer.textures.read();
+    let texture = textures.textures.get(&id)?;
+    Some(texture.format == TexelFormat::Alpha)
+}
+
+/// Names of the SIMD implementations available on this processor, most performant first and `Generic` last. The
+/// kernels below run with the implementation at an index of it, to test them against each other.
+pub fn simd_impls() -> Vec<String> {
+    available_instrs()
+        .iter()
+        .map(|simd_impl| simd_impl.to_string())
+        .collect()
+}
+
+/// Samples a color texture of `image` along a span, dst[i] = sample at `uv_start + uv_step * i`, using the
+/// implementation `simd_impls()[simd_impl]`. The texture is uploaded with the generic implementation.
+pub fn sample_bilinear_span(
+    simd_impl: usize,
+    image: &egui::ColorImage,
+    options: egui::TextureOptions,
+    field_order: ColorFieldOrder,
+    uv_start: Vec2,
+    uv_step: Vec2,
+    dst: &mut [[u8; 4]],
+) {
+    let texture = EguiTexture::new(
+        AvailableImpl::Generic(GenericImpl),
+        field_order,
+        options,
+        image.size,
+        &image.pixels,
+        false,
+    );
+    crate::dispatch_simd_impl!(available_instrs()[simd_impl], |simd_impl| texture
+        .sample_span(simd_impl, uv_start, uv_step, dst));
+}
+
+/// Fills a span with a gradient, dst[i] = `col_start + col_step * i` as unorm, using the implementation
+/// `simd_impls()[simd_impl]`.
+pub fn color_gradient_span(
+    simd_impl: usize,
+    [r, g, b, a]: [f32; 4],
+    [step_r, step_g, step_b, step_a]: [f32; 4],
+    dst: &mut [[u8; 4]],
+) {
+    let (col_start, col_step) = (vec4(r, g, b, a), vec4(step_r, step_g, step_b, step_a));
+    crate::dispatch_simd_impl!(available_instrs()[simd_impl], |simd_impl| simd_impl
+        .color_gradient_span(col_start, col_step, dst));
+}
+
+/// Blends tinted texels over a span, dst[i] = blend(src[i] * tints[i], dst[i]), using the implementation
+/// `simd_impls()[simd_impl]`.
+pub fn egui_blend_u8_slice_tinted_per_px(
+    simd_impl: usize,
+    src: &[[u8; 4]],
+    tints: &[[u8; 4]],
+    dst: &mut [[u8; 4]],
+) {
+    crate::dispatch_simd_impl!(available_instrs()[simd_impl], |simd_impl| simd_impl
+        .egui_blend_u8_slice_tinted_per_px(src, tints, dst));
+}
+
+/// Swaps the red and blue channels of colors, dst[i] = bgra(src[i]), using the implementation
+/// `simd_impls()[simd_impl]`.
+pub fn swizzle_rgba_bgra_slice(simd_impl: usize, src: &[egui::Color32], dst: &mut [[u8; 4]]) {
+    crate::dispatch_simd_impl!(available_instrs()[simd_impl], |simd_impl| simd_impl
+        .swizzle_rgba_bgra_slice(src, dst));
+}
+
+/// Converts Y, U, V texels to colors in `field_order` with the conversion of `format`, using the implementation
+/// `simd_impls()[simd_impl]`. Must match `yuv_convert()` exactly.
+pub fn yuv_to_rgba_span(
+    simd_impl: usize,
+    format: YuvFormat,
+    field_order: ColorFieldOrder,
+    [y, u, v]: [&[u8]; 3],
+    dst: &mut [[u8; 4]],
+) {
+    let coeffs = YuvCoeffs::new(format.matrix, format.range, field_order);
+    crate::dispatch_simd_impl!(available_instrs()[simd_impl], |simd_impl| simd_impl
+        .yuv_to_rgba_span(y, u, v, &coeffs, dst));
+}
+
+/// Converts a Y, U, V texel to a color in `field_order` with the conversion of `format`.
+pub fn yuv_convert(format: YuvFormat, field_order: ColorFieldOrder, [y, u, v]: [u8; 3]) -> [u8; 4] {
+    YuvCoeffs::new(format.matrix, format.range, field_order).convert(y, u, v)
+}
 
 impl TestRenderer for EguiSoftwareRender {
     fn handle_delta(&mut self, delta: &TexturesDelta) {
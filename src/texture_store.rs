@@ -0,0 +1,415 @@
+use core::ops::{Deref, DerefMut};
+
+use alloc::{borrow::Cow, sync::Arc, vec::Vec};
+
+use ahash::HashMap;
+use egui::mutex::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{
+    BufferMutRef, ColorFieldOrder,
+    color::AvailableImpl,
+    egui_texture::{EguiTexture, ExtendedSampling, ProceduralTexture, TexelFormat, YuvFormat},
+};
+
+/// egui textures, swizzled to the output color field order as they are loaded.
+///
+/// Each `EguiSoftwareRender` has its own store by default. To avoid holding a copy of the font atlas and images per
+/// renderer when rendering several viewports of one egui context, a `SharedTextureStore` can be given to each of them
+/// with `EguiSoftwareRender::with_texture_store()`. Renderers sharing a store load the textures of the deltas they're
+/// given, but never free them since another renderer may still draw them this frame. Free them on the store once all
+/// of them have rendered.
+///
+/// ```rust
+///use egui_software_backend::{ColorFieldOrder, EguiSoftwareRender, TextureStore};
+///let store = TextureStore::new_shared(ColorFieldOrder::Bgra);
+///let main_viewport = EguiSoftwareRender::new(ColorFieldOrder::Bgra).with_texture_store(store.clone());
+///let other_viewport = EguiSoftwareRender::new(ColorFieldOrder::Bgra).with_texture_store(store.clone());
+///// Each frame, after rendering both viewports with the frame's `TexturesDelta`:
+///store.write().free_textures(&egui::TexturesDelta::default());
+///```
+pub struct TextureStore {
+    pub(crate) textures: HashMap<egui::TextureId, EguiTexture>,
+    sampling: HashMap<egui::TextureId, ExtendedSampling>,
+    /// Id of the next procedural, streaming or YUV texture registered
+    next_user_id: u64,
+    /// Textures streamed into since the last `TextureStore::set_textures()`, which updates what's cached from texels
+    streamed: Vec<egui::TextureId>,
+    /// `textures_delta.set` of the last delta applied, to skip it when the next renderer sharing the store is given the
+    /// same delta. Cleared by the next empty delta so the images aren't held longer.
+    applied_set: Vec<(egui::TextureId, egui::epaint::ImageDelta)>,
+    output_field_order: ColorFieldOrder,
+    alpha_textures: bool,
+    simd_impl: AvailableImpl,
+}
+
+/// A `TextureStore` shared by several renderers.
+pub type SharedTextureStore = Arc<RwLock<TextureStore>>;
+
+impl TextureStore {
+    /// # Arguments
+    /// * `output_field_order` - egui textures will be swizzled to match the desired output buffer order. Must match
+    ///   the order of the renderers using the store.
+    pub fn new(output_field_order: ColorFieldOrder) -> Self {
+        TextureStore {
+            textures: Default::default(),
+            sampling: Default::default(),
+            next_user_id: 0,
+            streamed: Vec::new(),
+            applied_set: Vec::new(),
+            output_field_order,
+            alpha_textures: true,
+            simd_impl: Default::default(),
+        }
+    }
+
+    /// Creates an empty store to be shared by several renderers.
+    pub fn new_shared(output_field_order: ColorFieldOrder) -> SharedTextureStore {
+        Arc::new(RwLock::new(TextureStore::new(output_field_order)))
+    }
+
+    /// If true (default): textures that are only grayscale coverage, like the font atlas, are detected as they are
+    /// loaded and stored with a single `u8` alpha channel. Saves memory and bandwidth when sampling them. Only affects
+    /// textures loaded afterwards.
+    pub fn with_alpha_textures(mut self, set: bool) -> Self {
+        self.set_alpha_textures(set);
+        self
+    }
+
+    /// See `TextureStore::with_alpha_textures()`
+    pub fn set_alpha_textures(&mut self, set: bool) {
+        self.alpha_textures = set;
+    }
+
+    /// Draws the texture with the given filters when it's shown on an axis aligned rect at a different size than its
+    /// native one. Can be set before the texture is loaded, and is kept when it's updated or replaced until it's freed.
+    /// On a change, the cached primitives of every renderer using the store that are drawn with the texture are
+    /// rasterized again.
+    pub fn set_extended_sampling(&mut self, id: egui::TextureId, sampling: ExtendedSampling) {
+        if sampling == self.extended_sampling(id) {
+            return;
+        }
+        if sampling == ExtendedSampling::default() {
+            self.sampling.remove(&id);
+        } else {
+            self.sampling.insert(id, sampling);
+        }
+        if let Some(texture) = self.textures.get_mut(&id) {
+            texture.sampling = sampling;
+            texture.generation += 1;
+        }
+    }
+
+    pub fn extended_sampling(&self, id: egui::TextureId) -> ExtendedSampling {
+        self.sampling.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Adds a texture that's sampled from `texture` instead of uploaded texels, see `ProceduralTexture`. Returns the
+    /// `egui::TextureId::User` to draw it with, e.g. with `egui::Image::new((id, size))`.
+    pub fn register_procedural_texture(
+        &mut self,
+        texture: Arc<dyn ProceduralTexture>,
+    ) -> egui::TextureId {
+        let id = egui::TextureId::User(self.next_user_id);
+        self.next_user_id += 1;
+        self.textures.insert(
+            id,
+            EguiTexture::new_procedural(self.output_field_order, texture),
+        );
+        id
+    }
+
+    /// Replaces the `ProceduralTexture` of a texture from `TextureStore::register_procedural_texture()`.
+    pub fn replace_procedural_texture(
+        &mut self,
+        id: egui::TextureId,
+        texture: Arc<dyn ProceduralTexture>,
+    ) {
+        debug_assert!(
+            self.textures
+                .get(&id)
+                .is_some_and(|texture| texture.procedural.is_some()),
+            "{id:?} is not a procedural texture"
+        );
+        self.textures.insert(
+            id,
+            EguiTexture::new_procedural(self.output_field_order, texture),
+        );
+    }
+
+    /// Removes a texture from `TextureStore::register_procedural_texture()`,
+    /// `TextureStore::register_streaming_texture()` or `TextureStore::register_yuv_texture()`.
+    pub fn free_user_texture(&mut self, id: egui::TextureId) {
+        self.textures.remove(&id);
+    }
+
+    /// Adds a transparent texture of the given size to stream texels into with `TextureStore::texture_mut()` or
+    /// `TextureStore::swap_texture_buffer()`, without going through egui. Returns the `egui::TextureId::User` to draw it
+    /// with.
+    pub fn register_streaming_texture(
+        &mut self,
+        size: [usize; 2],
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        assert!(size[0] > 0 && size[1] > 0);
+        let id = egui::TextureId::User(self.next_user_id);
+        self.next_user_id += 1;
+        self.textures
+            .insert(id, EguiTexture::new_streaming(options, size));
+        id
+    }
+
+    /// Adds an opaque black texture of the given size holding video frames in a planar YUV format, written with
+    /// `TextureStore::yuv_texture_mut()` or `TextureStore::swap_yuv_buffer()`. Frames are converted to the output
+    /// color field order as they're drawn, so a decoder's output can be copied in as is. Returns the
+    /// `egui::TextureId::User` to draw it with.
+    pub fn register_yuv_texture(
+        &mut self,
+        size: [usize; 2],
+        format: YuvFormat,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        assert!(size[0] > 0 && size[1] > 0);
+        let id = egui::TextureId::User(self.next_user_id);
+        self.next_user_id += 1;
+        self.textures.insert(
+            id,
+            EguiTexture::new_yuv(self.output_field_order, options, size, format),
+        );
+        id
+    }
+
+    /// The planes of a texture from `TextureStore::register_yuv_texture()`, to write a new frame of it directly. Laid
+    /// out as given by its `YuvFormat`, with `YuvFormat::buffer_len()` bytes. None if the texture isn't loaded or
+    /// isn't YUV, e.g. after it was converted by `TextureStore::texture_mut()`.
+    pub fn yuv_texture_mut(&mut self, id: egui::TextureId) -> Option<&mut [u8]> {
+        let texture = self
+            .textures
+            .get_mut(&id)
+            .filter(|texture| texture.format == TexelFormat::Yuv)?;
+        mark_streamed(&mut self.streamed, id, texture);
+        Some(&mut texture.yuv.data)
+    }
+
+    /// Swaps the planes of a YUV texture with `buffer`, which must hold `YuvFormat::buffer_len()` bytes. The previous
+    /// frame is left in `buffer` to be reused. Returns false, leaving `buffer` as is, if the texture isn't loaded or
+    /// isn't YUV. See `TextureStore::yuv_texture_mut()`.
+    pub fn swap_yuv_buffer(&mut self, id: egui::TextureId, buffer: &mut Vec<u8>) -> bool {
+        let Some(texture) = self
+            .textures
+            .get_mut(&id)
+            .filter(|texture| texture.format == TexelFormat::Yuv)
+        else {
+            return false;
+        };
+        assert_eq!(
+            buffer.len(),
+            texture.yuv.data.len(),
+            "Buffer size doesn't match texture {id:?}"
+        );
+        mark_streamed(&mut self.streamed, id, texture);
+        core::mem::swap(&mut texture.yuv.data, buffer);
+        true
+    }
+
+    /// The texels of a loaded texture, to write a new frame of it directly. Texels are premultiplied and in the
+    /// output field order. Only the cached primitives drawn with this texture are rasterized again. Alpha and YUV
+    /// textures are converted to color ones. None if the texture isn't loaded or is procedural.
+    ///
+    /// Works with textures from egui too, e.g. a `TextureHandle` loaded once, though a later `TexturesDelta` for it
+    /// overwrites what was written.
+    pub fn texture_mut(&mut self, id: egui::TextureId) -> Option<BufferMutRef<'_>> {
+        let texture = self.stream_into(id)?;
+        let (width, height) = (texture.width, texture.height);
+        Some(BufferMutRef::new(&mut texture.data, width, height))
+    }
+
+    /// Swaps the texels of a loaded texture with `buffer`, which must hold as many texels as the texture. The
+    /// previous texels are left in `buffer` to be reused for the next frame. Returns false, leaving `buffer` as is, if
+    /// the texture isn't loaded or is procedural. See `TextureStore::texture_mut()`.
+    pub fn swap_texture_buffer(&mut self, id: egui::TextureId, buffer: &mut Vec<[u8; 4]>) -> bool {
+        let Some(texture) = self.stream_into(id) else {
+            return false;
+        };
+        assert_eq!(
+            buffer.len(),
+            texture.width * texture.height,
+            "Buffer size doesn't match texture {id:?}"
+        );
+        core::mem::swap(&mut texture.data, buffer);
+        true
+    }
+
+    /// Marks a texture as streamed into and returns it as a color texture.
+    fn stream_into(&mut self, id: egui::TextureId) -> Option<&mut EguiTexture> {
+        let texture = self
+            .textures
+            .get_mut(&id)
+            .filter(|texture| texture.procedural.is_none())?;
+        texture.convert_to_color();
+        mark_streamed(&mut self.streamed, id, texture);
+        Some(texture)
+    }
+
+    pub fn output_field_order(&self) -> ColorFieldOrder {
+        self.output_field_order
+    }
+
+    /// Number of textures currently loaded.
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Loads and updates the textures of `textures_delta.set`. Applying the delta that was last applied again has no
+    /// effect, so renderers sharing a store can each be given the same delta of a frame (or a clone of it, which
+    /// shares its images). Alternatively apply it once here before rendering and pass the renderers an empty delta.
+    pub fn set_textures(&mut self, textures_delta: &egui::TexturesDelta) {
+        for id in self.streamed.drain(..) {
+            if let Some(texture) = self.textures.get_mut(&id) {
+                texture.uv_zero_val = texture.texel(0);
+            }
+        }
+        if self.is_applied(&textures_delta.set) {
+            return;
+        }
+        self.applied_set.clear();
+        self.applied_set.extend(textures_delta.set.iter().cloned());
+
+        for (id, delta) in &textures_delta.set {
+            if delta.options.magnification != delta.options.minification {
+                // Would need helper lanes to impl?
+                #[cfg(feature = "log")]
+                log::warn!(
+                    "TextureOptions magnification and minification not matching is unsupported."
+                );
+            }
+            let pixels = match &delta.image {
+                egui::ImageData::Color(image) => {
+                    assert_eq!(image.width() * image.height(), image.pixels.len());
+                    Cow::Borrowed(&image.pixels)
+                }
+            };
+            let size = delta.image.size();
+            if let Some(pos) = delta.pos {
+                if let Some(texture) = self.textures.get_mut(id) {
+                    texture.update(self.simd_impl, self.output_field_order, pos, size, &pixels);
+                }
+            } else {
+                let mut new_texture = EguiTexture::new(
+                    self.simd_impl,
+                    self.output_field_order,
+                    delta.options,
+                    size,
+                    &pixels,
+                    self.alpha_textures,
+                );
+                new_texture.sampling = self.extended_sampling(*id);
+
+                self.textures.insert(*id, new_texture);
+            }
+        }
+    }
+
+    /// True if `set` is the same as the last applied `textures_delta.set`, with the same images.
+    fn is_applied(&self, set: &[(egui::TextureId, egui::epaint::ImageDelta)]) -> bool {
+        !set.is_empty()
+            && set.len() == self.applied_set.len()
+            && set
+                .iter()
+                .zip(&self.applied_set)
+                .all(|((id, delta), (applied_id, applied))| {
+                    let (egui::ImageData::Color(image), egui::ImageData::Color(applied_image)) =
+                        (&delta.image, &applied.image);
+                    id == applied_id
+                        && delta.pos == applied.pos
+                        && Arc::ptr_eq(image, applied_image)
+                })
+    }
+
+    /// Removes the textures of `textures_delta.free`. Renderers only free textures from their own store, with a shared
+    /// store call this once every renderer using it has rendered the frame the delta belongs to.
+    pub fn free_textures(&mut self, textures_delta: &egui::TexturesDelta) {
+        for free in &textures_delta.free {
+            self.textures.remove(free);
+            self.sampling.remove(free);
+        }
+    }
+}
+
+/// Invalidates what's cached of a texture written outside of `TextureStore::set_textures()`.
+fn mark_streamed(
+    streamed: &mut Vec<egui::TextureId>,
+    id: egui::TextureId,
+    texture: &mut EguiTexture,
+) {
+    texture.generation += 1;
+    if !streamed.contains(&id) {
+        streamed.push(id);
+    }
+}
+
+/// The textures of a renderer, either its own store or one shared with other renderers.
+pub(crate) enum Textures {
+    Owned(TextureStore),
+    Shared(SharedTextureStore),
+}
+
+impl Textures {
+    pub(crate) fn read(&self) -> TexturesRead<'_> {
+        match self {
+            Textures::Owned(store) => TexturesRead::Owned(store),
+            Textures::Shared(store) => TexturesRead::Shared(store.read()),
+        }
+    }
+
+    pub(crate) fn write(&mut self) -> TexturesWrite<'_> {
+        match self {
+            Textures::Owned(store) => TexturesWrite::Owned(store),
+            Textures::Shared(store) => TexturesWrite::Shared(store.write()),
+        }
+    }
+}
+
+pub(crate) enum TexturesRead<'a> {
+    Owned(&'a TextureStore),
+    Shared(RwLockReadGuard<'a, TextureStore>),
+}
+
+impl Deref for TexturesRead<'_> {
+    type Target = TextureStore;
+
+    fn deref(&self) -> &TextureStore {
+        match self {
+            TexturesRead::Owned(store) => store,
+            TexturesRead::Shared(guard) => guard,
+        }
+    }
+}
+
+pub(crate) enum TexturesWrite<'a> {
+    Owned(&'a mut TextureStore),
+    Shared(RwLockWriteGuard<'a, TextureStore>),
+}
+
+impl Deref for TexturesWrite<'_> {
+    type Target = TextureStore;
+
+    fn deref(&self) -> &TextureStore {
+        match self {
+            TexturesWrite::Owned(store) => store,
+            TexturesWrite::Shared(guard) => guard,
+        }
+    }
+}
+
+impl DerefMut for TexturesWrite<'_> {
+    fn deref_mut(&mut self) -> &mut TextureStore {
+        match self {
+            TexturesWrite::Owned(store) => store,
+            TexturesWrite::Shared(guard) => guard,
+        }
+    }
+}
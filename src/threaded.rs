@@ -0,0 +1,284 @@
+#[cfg(feature = "raster_stats")]
+use std::boxed::Box;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread::{self, JoinHandle};
+use std::vec::Vec;
+
+use crate::color::AvailableImpl;
+#[cfg(feature = "raster_stats")]
+use crate::stats::RasterStats;
+use crate::{BufferMutRef, Canvas, EguiSoftwareRender};
+
+/// Runs an `EguiSoftwareRender` on a worker thread so frames are pipelined: frame N+1 is rendered while frame N is
+/// blitted and presented.
+///
+/// Every submitted frame is rendered, in order, so texture deltas are never lost. The worker composites each frame into
+/// one of the recycled frame canvases and hands it off to be blitted with `try_blit()`. Only the tiles that changed
+/// since the frame a recycled canvas held are composited again.
+///
+/// ```rust
+///use egui_software_backend::{BufferMutRef, ColorFieldOrder, EguiSoftwareRender, ThreadedEguiSoftwareRender};
+///let buffer = &mut vec![[0u8; 4]; 512 * 512];
+///let ctx = egui::Context::default();
+///let mut sw_render = ThreadedEguiSoftwareRender::new(EguiSoftwareRender::new(ColorFieldOrder::Bgra));
+///
+///let out = ctx.run_ui(egui::RawInput::default(), |ui| {
+///    ui.label("Hello World!");
+///});
+///
+///let primitives = ctx.tessellate(out.shapes, out.pixels_per_point);
+///sw_render.submit(512, 512, primitives, out.textures_delta, out.pixels_per_point);
+///
+///sw_render.finish();
+///assert!(sw_render.try_blit(&mut BufferMutRef::new(buffer, 512, 512)));
+///```
+pub struct ThreadedEguiSoftwareRender {
+    to_worker: Option<Sender<WorkerMsg>>,
+    from_worker: Receiver<FrameCanvas>,
+    worker: Option<JoinHandle<EguiSoftwareRender>>,
+    /// Newest finished frame
+    front: Option<FrameCanvas>,
+    frames_submitted: u64,
+    frames_finished: u64,
+    max_latency: u64,
+    simd_impl: AvailableImpl,
+}
+
+enum WorkerMsg {
+    Render(FrameJob),
+    /// A frame canvas that is no longer blitted, to be reused for a later frame
+    Recycle(FrameCanvas),
+}
+
+struct FrameJob {
+    width: usize,
+    height: usize,
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    pixels_per_point: f32,
+}
+
+#[derive(Default)]
+struct FrameCanvas {
+    canvas: Canvas,
+    tile_masks: Vec<u8>,
+    tiles_x: usize,
+    /// 1 based index of the submitted frame this is the canvas of
+    frame: u64,
+    #[cfg(feature = "raster_stats")]
+    stats: Box<RasterStats>,
+}
+
+impl ThreadedEguiSoftwareRender {
+    /// Moves `renderer` to a new worker thread. Use `into_inner()` to get it back.
+    pub fn new(renderer: EguiSoftwareRender) -> Self {
+        let simd_impl = renderer.simd_impl;
+        let (to_worker, worker_rx) = channel();
+        let (worker_tx, from_worker) = channel();
+        let worker = thread::Builder::new()
+            .name("egui_software_render".into())
+            .spawn(move || render_worker(renderer, worker_rx, worker_tx))
+            .expect("Failed to spawn render thread");
+
+        ThreadedEguiSoftwareRender {
+            to_worker: Some(to_worker),
+            from_worker,
+            worker: Some(worker),
+            front: None,
+            frames_submitted: 0,
+            frames_finished: 0,
+            max_latency: 1,
+            simd_impl,
+        }
+    }
+
+    /// Max number of frames that the blitted frame may lag behind the last submitted one (default 1).
+    ///
+    /// With 0, `try_blit()` only blits the last submitted frame and `submit()` waits for the previous frame to finish,
+    /// so only the caller's UI update overlaps with rendering. With 1, frame N+1 can be rendered while frame N is
+    /// blitted and presented. Higher values allow more frames to be queued.
+    pub fn with_max_latency(mut self, frames: u64) -> Self {
+        self.max_latency = frames;
+        self
+    }
+
+    /// Queues the given paint jobs to be rendered on the worker thread. See `EguiSoftwareRender::render_to_canvas()`.
+    ///
+    /// Blocks while more than `max_latency` frames are still being rendered.
+    pub fn submit(
+        &mut self,
+        width: usize,
+        height: usize,
+        paint_jobs: Vec<egui::ClippedPrimitive>,
+        textures_delta: egui::TexturesDelta,
+        pixels_per_point: f32,
+    ) {
+        // Checked here as well so invalid input panics on the calling thread.
+        assert!(width > 0);
+        assert!(height > 0);
+        assert!(pixels_per_point > 0.0);
+
+        self.receive_finished();
+        while self.frames_submitted - self.frames_finished > self.max_latency {
+            self.wait_for_frame();
+        }
+
+        self.frames_submitted += 1;
+        self.send(WorkerMsg::Render(FrameJob {
+            width,
+            height,
+            paint_jobs,
+            textures_delta,
+            pixels_per_point,
+        }));
+    }
+
+    /// Draw the newest finished frame alpha over the given buffer, without waiting for the worker thread.
+    ///
+    /// Returns false, leaving the buffer untouched, if no finished frame is within `max_latency` frames of the last
+    /// submitted one, or if its size doesn't match the buffer.
+    pub fn try_blit(&mut self, buffer: &mut BufferMutRef) -> bool {
+        self.receive_finished();
+
+        let Some(front) = &self.front else {
+            return false;
+        };
+        if front.frame + self.max_latency < self.frames_submitted
+            || front.canvas.width != buffer.width
+            || front.canvas.height != buffer.height
+        {
+            return false;
+        }
+
+        front
+            .canvas
+            .blit_tiles(self.simd_impl, &front.tile_masks, front.tiles_x, buffer);
+        true
+    }
+
+    /// Blocks until every submitted frame has been rendered.
+    pub fn finish(&mut self) {
+        while self.frames_finished < self.frames_submitted {
+            self.wait_for_frame();
+        }
+    }
+
+    /// Number of frames submitted but not yet rendered.
+    pub fn frames_in_flight(&self) -> u64 {
+        self.frames_submitted - self.frames_finished
+    }
+
+    /// Stats of the newest finished frame.
+    #[cfg(feature = "raster_stats")]
+    pub fn stats(&self) -> Option<&RasterStats> {
+        self.front.as_ref().map(|front| &*front.stats)
+    }
+
+    /// Waits for the submitted frames to finish and returns the renderer, holding the canvas of the last frame.
+    pub fn into_inner(mut self) -> EguiSoftwareRender {
+        self.to_worker = None;
+        let worker = self.worker.take().expect("Render thread already stopped");
+        let mut renderer = match worker.join() {
+            Ok(renderer) => renderer,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+        if let Some(last) = self.from_worker.try_iter().last().or(self.front.take()) {
+            renderer.canvas = last.canvas;
+        }
+        renderer
+    }
+
+    fn receive_finished(&mut self) {
+        loop {
+            match self.from_worker.try_recv() {
+                Ok(frame) => self.set_front(frame),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => self.resume_worker_panic(),
+            }
+        }
+    }
+
+    fn wait_for_frame(&mut self) {
+        match self.from_worker.recv() {
+            Ok(frame) => self.set_front(frame),
+            Err(_) => self.resume_worker_panic(),
+        }
+    }
+
+    fn set_front(&mut self, frame: FrameCanvas) {
+        self.frames_finished = frame.frame;
+        if let Some(previous) = self.front.replace(frame) {
+            self.send(WorkerMsg::Recycle(previous));
+        }
+    }
+
+    fn send(&mut self, msg: WorkerMsg) {
+        let sent = self.to_worker.as_ref().map(|tx| tx.send(msg).is_ok());
+        if sent != Some(true) {
+            self.resume_worker_panic();
+        }
+    }
+
+    /// The worker thread only hangs up if it panicked, so join it and resume its panic here.
+    fn resume_worker_panic(&mut self) -> ! {
+        self.to_worker = None;
+        if let Some(Err(panic)) = self.worker.take().map(JoinHandle::join) {
+            std::panic::resume_unwind(panic);
+        }
+        panic!("Render thread stopped unexpectedly");
+    }
+}
+
+impl Drop for ThreadedEguiSoftwareRender {
+    fn drop(&mut self) {
+        self.to_worker = None;
+        if let Some(worker) = self.worker.take() {
+            // Don't panic again while unwinding
+            let _ = worker.join();
+        }
+    }
+}
+
+fn render_worker(
+    mut renderer: EguiSoftwareRender,
+    jobs: Receiver<WorkerMsg>,
+    frames: Sender<FrameCanvas>,
+) -> EguiSoftwareRender {
+    let mut free_canvases = Vec::new();
+    let mut frame = 0;
+
+    for msg in jobs {
+        match msg {
+            WorkerMsg::Recycle(canvas) => free_canvases.push(canvas),
+            WorkerMsg::Render(job) => {
+                // The renderer composites into the recycled canvas, which is then handed off as is
+                let mut back: FrameCanvas = free_canvases.pop().unwrap_or_default();
+                std::mem::swap(&mut renderer.canvas, &mut back.canvas);
+                renderer.render_to_canvas(
+                    job.width,
+                    job.height,
+                    &job.paint_jobs,
+                    &job.textures_delta,
+                    job.pixels_per_point,
+                );
+                std::mem::swap(&mut renderer.canvas, &mut back.canvas);
+                frame += 1;
+
+                back.tile_masks.clear();
+                back.tile_masks.extend_from_slice(&renderer.dirty_tiles);
+                back.tiles_x = renderer.tiles_dim[0];
+                back.frame = frame;
+                #[cfg(feature = "raster_stats")]
+                {
+                    *back.stats = std::mem::take(&mut renderer.stats);
+                }
+
+                if frames.send(back).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    renderer
+}
@@ -826,6 +826,17 @@ pub struct SoftwareBackendAppConfiguration {
     /// Default is true!
     pub convert_tris_to_rects: bool,
 
+    /// If true: text is drawn with subpixel anti-aliasing for LCD monitors. See `EguiSoftwareRender::with_lcd_text()`.
+    ///
+    /// Default is false!
+    pub lcd_text: bool,
+
+    /// If true: the outline of meshes is anti-aliased by pixel coverage, for content drawn without feathering. See
+    /// `EguiSoftwareRender::with_coverage_aa()`.
+    ///
+    /// Default is false!
+    pub coverage_aa: bool,
+
     /// If true: rasterized ClippedPrimitives are cached and rendered to an intermediate tiled canvas. That canvas is
     /// then rendered over the frame buffer. If false ClippedPrimitives are rendered directly to the frame buffer.
     /// Rendering without caching is much slower and primarily intended for testing.
@@ -877,6 +888,8 @@ impl SoftwareBackendAppConfiguration {
 
             allow_raster_opt: true,
             convert_tris_to_rects: true,
+            lcd_text: false,
+            coverage_aa: false,
             caching: true,
         }
     }
@@ -1085,6 +1098,23 @@ impl SoftwareBackendAppConfiguration {
         self
     }
 
+    /// If true: text is drawn with subpixel anti-aliasing for LCD monitors. See `EguiSoftwareRender::with_lcd_text()`.
+    ///
+    /// Default is false!
+    pub const fn lcd_text(mut self, lcd_text: bool) -> Self {
+        self.lcd_text = lcd_text;
+        self
+    }
+
+    /// If true: the outline of meshes is anti-aliased by pixel coverage, for content drawn without feathering. See
+    /// `EguiSoftwareRender::with_coverage_aa()`.
+    ///
+    /// Default is false!
+    pub const fn coverage_aa(mut self, coverage_aa: bool) -> Self {
+        self.coverage_aa = coverage_aa;
+        self
+    }
+
     /// If true: rasterized ClippedPrimitives are cached and rendered to an intermediate tiled canvas. That canvas is
     /// then rendered over the frame buffer. If false ClippedPrimitives are rendered directly to the frame buffer.
     /// Rendering without caching is much slower and primarily intended for testing.
@@ -1112,6 +1142,8 @@ pub fn run_app_with_software_backend<T: App>(
     let egui_software_render = EguiSoftwareRender::new(ColorFieldOrder::Bgra)
         .with_allow_raster_opt(settings.allow_raster_opt)
         .with_convert_tris_to_rects(settings.convert_tris_to_rects)
+        .with_lcd_text(settings.lcd_text)
+        .with_coverage_aa(settings.coverage_aa)
         .with_caching(settings.caching);
 
     let event_loop: EventLoop<UserEvent> = EventLoop::with_user_event()
@@ -1,13 +1,73 @@
 #![cfg(feature = "test_render")]
 mod tests {
-    use egui::{Vec2, vec2};
-    use egui_software_backend::{ColorFieldOrder, EguiSoftwareRender};
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        collections::HashSet,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use egui::{Color32, Pos2, Rect, Vec2, pos2, vec2};
+    use egui_software_backend::{
+        BufferMutRef, ColorFieldOrder, EguiSoftwareRender, ExtendedSampling, MagnificationFilter,
+        MinificationFilter, ProceduralTexture, TextureStore, ThreadedEguiSoftwareRender, YuvFormat,
+        YuvLayout, YuvMatrix, YuvRange,
+        test_render::{
+            color_gradient_span, egui_blend_u8_slice_tinted_per_px, primitive_cache_key,
+            sample_bilinear_span, set_cache_key_mask, simd_impls, swizzle_rgba_bgra_slice,
+            texture_is_alpha, yuv_convert, yuv_to_rgba_span,
+        },
+    };
     use image::{ImageBuffer, Rgba};
 
     use egui_kittest::HarnessBuilder;
 
     const RESOLUTION: Vec2 = vec2(1280.0, 720.0);
 
+    /// Counts the allocations of each thread, for tests that check that rendering doesn't allocate. Allocations of
+    /// threads marked with `POOL_THREAD`, like those of a test's own thread pool, are also counted in
+    /// `POOL_ALLOCATIONS`.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        static POOL_THREAD: Cell<bool> = const { Cell::new(false) };
+    }
+
+    static POOL_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    impl CountingAllocator {
+        fn count() {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+            if POOL_THREAD.with(Cell::get) {
+                POOL_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            Self::count();
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            Self::count();
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
     #[test]
     // Tests many configurations of the cpu software render backend against the GPU implementation.
     // Outputs PNG files with diffs when pixels didn't match and will panic above a certain threshold:
@@ -93,6 +153,2627 @@ mod tests {
         }
     }
 
+    #[test]
+    // Scrolls long lists of text with scroll detection enabled, so the still visible part of each list is shifted from
+    // the previous frame instead of being rasterized again, and so are the canvas tiles where only the panel behind it
+    // shows. Every frame is compared with a full redraw of the same frame with scroll detection disabled. At 1.0 px per
+    // point they must be identical. At fractional ones scrolled vertices only move by whole pixels up to float
+    // precision, so the known limitation documented on `with_scroll_detection()` allows a difference of 1. With raster
+    // stats, checks that the primitives and canvas tiles of frames scrolled by a few rows are shifted.
+    pub fn compare_scrolled_with_full_redraw() {
+        fn app(ui: &mut egui::Ui, scroll: f32) {
+            egui::CentralPanel::default().show_inside(ui, |ui| {
+                ui.columns(2, |columns| {
+                    egui::ScrollArea::vertical()
+                        .id_salt("vertical")
+                        .vertical_scroll_offset(scroll)
+                        .show(&mut columns[0], |ui| {
+                            for i in 0..500 {
+                                ui.label(format!(
+                                    "Row {i}: The quick brown fox jumps over the lazy dog"
+                                ));
+                            }
+                        });
+                    egui::ScrollArea::both()
+                        .id_salt("both")
+                        .scroll_offset(Vec2::splat(scroll))
+                        .show(&mut columns[1], |ui| {
+                            for i in 0..500 {
+                                ui.add(
+                                    egui::Label::new(format!(
+                                        "Row {i}: {}",
+                                        "The quick brown fox jumps over the lazy dog. ".repeat(8)
+                                    ))
+                                    .extend(),
+                                );
+                            }
+                        });
+                });
+            });
+        }
+
+        // Scroll offsets in points of consecutive frames: small steps, a 1px step, scrolling back and a jump further
+        // than the height of the view
+        let scroll_offsets = [0.0, 0.0, 17.0, 40.0, 41.0, 300.0, 250.0, 2000.0, 1990.0];
+
+        // Max difference of any color channel, and frames scrolled by a few rows that must be shifted. At 1.5 px per
+        // point the steps of frames 3 and 4 are a fraction of a pixel, so the rows round to pixels differently.
+        for (px_per_point, max_channel_diff, shifted_frames) in
+            [(1.0, 0, [3, 4]), (1.0833334, 1, [3, 4]), (1.5, 1, [6, 8])]
+        {
+            let ctx = egui::Context::default();
+            ctx.set_pixels_per_point(px_per_point);
+            let input = egui::RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, RESOLUTION)),
+                ..Default::default()
+            };
+            let width = (RESOLUTION.x * px_per_point).ceil() as usize;
+            let height = (RESOLUTION.y * px_per_point).ceil() as usize;
+            let mut renderer =
+                EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_scroll_detection(true);
+            let mut full_redraw_renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+
+            for (frame, offset) in scroll_offsets.into_iter().enumerate() {
+                let output = ctx.run_ui(input.clone(), |ui| app(ui, offset));
+                let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+                let render = |renderer: &mut EguiSoftwareRender| {
+                    let mut buffer = vec![[0u8; 4]; width * height];
+                    renderer.render(
+                        &mut BufferMutRef::new(&mut buffer, width, height),
+                        &paint_jobs,
+                        &output.textures_delta,
+                        output.pixels_per_point,
+                    );
+                    buffer
+                };
+                let scrolled = render(&mut renderer);
+                let full_redraw = render(&mut full_redraw_renderer);
+                let name = format!("px_per_pt {px_per_point}, frame {frame}");
+
+                #[cfg(feature = "raster_stats")]
+                if shifted_frames.contains(&frame) {
+                    assert!(
+                        renderer.stats.scrolled_prims > 0,
+                        "{name}: nothing scrolled"
+                    );
+                    assert!(renderer.stats.shifted_tiles > 0, "{name}: no tiles shifted");
+                }
+                #[cfg(not(feature = "raster_stats"))]
+                let _ = shifted_frames;
+
+                let diff = scrolled
+                    .iter()
+                    .flatten()
+                    .zip(full_redraw.iter().flatten())
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .max()
+                    .unwrap();
+                if diff > max_channel_diff {
+                    let _ = std::fs::create_dir("tests/tmp/");
+                    for (prefix, buffer) in [("scrolled", &scrolled), ("full_redraw", &full_redraw)]
+                    {
+                        ImageBuffer::<Rgba<u8>, _>::from_raw(
+                            width as u32,
+                            height as u32,
+                            buffer.iter().flatten().copied().collect::<Vec<_>>(),
+                        )
+                        .unwrap()
+                        .save(format!("tests/tmp/{prefix}_{name} - FAIL.png"))
+                        .unwrap();
+                    }
+                    panic!("max channel diff {diff}: {name}")
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Zooms back and forth between fractional scales with the primitive cache kept across frames, with and without
+    // keeping the cache generation of the previous scale. Every frame must match a fresh render at the same scale
+    // exactly, so no primitive rasterized at another scale is reused. Zooming keeps the size in pixels, so the fresh
+    // render is sized to the same pixels at its scale.
+    pub fn compare_zoomed_with_fresh_render() {
+        fn app() -> impl FnMut(&mut egui::Ui) {
+            move |ui: &mut egui::Ui| {
+                egui::CentralPanel::default().show_inside(ui, |ui| {
+                    ui.heading("Zoom");
+                    for i in 0..20 {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Row {i}: The quick brown fox jumps over the lazy dog"
+                            ));
+                            let _ = ui.button("Button");
+                            ui.checkbox(&mut (i % 2 == 0), "Checkbox");
+                        });
+                    }
+                    ui.separator();
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.add(egui::Slider::new(&mut 0.5, 0.0..=1.0).text("Slider"));
+                    });
+                });
+            }
+        }
+
+        let px_per_point_sequence = [1.0, 1.0833334, 1.5, 1.0833334, 1.5, 1.0, 1.0833334];
+
+        for keep_previous_scale_cache in [false, true] {
+            let mut harness = HarnessBuilder::default()
+                .with_size(RESOLUTION)
+                .with_pixels_per_point(px_per_point_sequence[0])
+                .renderer(
+                    EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                        .with_keep_previous_scale_cache(keep_previous_scale_cache),
+                )
+                .build_ui(app());
+
+            for (frame, px_per_point) in px_per_point_sequence.into_iter().enumerate() {
+                harness.ctx.set_pixels_per_point(px_per_point);
+                harness.run();
+                let zoomed_image = harness.render().unwrap();
+
+                let mut fresh_harness = HarnessBuilder::default()
+                    .with_size(RESOLUTION / px_per_point)
+                    .with_pixels_per_point(px_per_point)
+                    .renderer(EguiSoftwareRender::new(ColorFieldOrder::Rgba))
+                    .build_ui(app());
+                fresh_harness.run();
+                let fresh_image = fresh_harness.render().unwrap();
+                assert_eq!(
+                    zoomed_image.dimensions(),
+                    fresh_image.dimensions(),
+                    "zoomed and fresh renders differ in size"
+                );
+
+                if zoomed_image.as_raw() != fresh_image.as_raw() {
+                    let name = format!(
+                        "keep_previous_scale_cache {keep_previous_scale_cache}, frame {frame}, px_per_pt {px_per_point}"
+                    );
+                    let _ = std::fs::create_dir("tests/tmp/");
+                    zoomed_image
+                        .save(format!("tests/tmp/zoomed_{name} - FAIL.png"))
+                        .unwrap();
+                    fresh_image
+                        .save(format!("tests/tmp/fresh_{name} - FAIL.png"))
+                        .unwrap();
+                    panic!("zoomed render doesn't match fresh render: {name}")
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Grows and shrinks the window with the primitive cache kept across frames, to sizes that aren't a multiple of the
+    // tile size, at whole and fractional scales. Every frame must match a fresh render at the same size exactly, so the
+    // cached primitives are composited into the tiles they occupy at the new size.
+    pub fn compare_resized_with_fresh_render() {
+        fn app() -> impl FnMut(&mut egui::Ui) {
+            move |ui: &mut egui::Ui| {
+                egui::CentralPanel::default().show_inside(ui, |ui| {
+                    ui.heading("Resize");
+                    for i in 0..20 {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label(format!(
+                                "Row {i}: The quick brown fox jumps over the lazy dog"
+                            ));
+                            let _ = ui.button("Button");
+                            ui.checkbox(&mut (i % 2 == 0), "Checkbox");
+                        });
+                    }
+                    ui.separator();
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.add(egui::Slider::new(&mut 0.5, 0.0..=1.0).text("Slider"));
+                    });
+                });
+            }
+        }
+
+        let size_sequence = [
+            RESOLUTION,
+            vec2(1500.0, 830.0),
+            vec2(1500.0, 830.0),
+            vec2(701.0, 459.0),
+            vec2(333.0, 901.0),
+            RESOLUTION,
+        ];
+
+        for px_per_point in [1.0, 1.5] {
+            let mut harness = HarnessBuilder::default()
+                .with_size(size_sequence[0])
+                .with_pixels_per_point(px_per_point)
+                .renderer(EguiSoftwareRender::new(ColorFieldOrder::Rgba))
+                .build_ui(app());
+
+            for (frame, size) in size_sequence.into_iter().enumerate() {
+                harness.set_size(size);
+                harness.run();
+                let resized_image = harness.render().unwrap();
+
+                let mut fresh_harness = HarnessBuilder::default()
+                    .with_size(size)
+                    .with_pixels_per_point(px_per_point)
+                    .renderer(EguiSoftwareRender::new(ColorFieldOrder::Rgba))
+                    .build_ui(app());
+                fresh_harness.run();
+                let fresh_image = fresh_harness.render().unwrap();
+                assert_eq!(
+                    resized_image.dimensions(),
+                    fresh_image.dimensions(),
+                    "resized and fresh renders differ in size"
+                );
+
+                if resized_image.as_raw() != fresh_image.as_raw() {
+                    let name = format!("px_per_pt {px_per_point}, frame {frame}, size {size:?}");
+                    let _ = std::fs::create_dir("tests/tmp/");
+                    resized_image
+                        .save(format!("tests/tmp/resized_{name} - FAIL.png"))
+                        .unwrap();
+                    fresh_image
+                        .save(format!("tests/tmp/fresh_{name} - FAIL.png"))
+                        .unwrap();
+                    panic!("resized render doesn't match fresh render: {name}")
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    // Renders the demo with meshes binned into tiles rasterized in parallel and with meshes rasterized one after the
+    // other, with and without caching. Binning must not change a single pixel. Also covers LCD text and coverage AA, whose
+    // spans are clipped to the tiles differently.
+    pub fn binned_raster_matches_serial() {
+        for px_per_point in [1.0, 1.5] {
+            for use_cache in [false, true] {
+                for lcd_and_aa in [false, true] {
+                    let [binned_image, serial_image] = [true, false].map(|binned_raster| {
+                        let mut demo = egui_demo_lib::DemoWindows::default();
+                        let mut harness = HarnessBuilder::default()
+                            .with_size(RESOLUTION)
+                            .with_pixels_per_point(px_per_point)
+                            .renderer(
+                                EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                                    .with_caching(use_cache)
+                                    .with_lcd_text(lcd_and_aa)
+                                    .with_coverage_aa(lcd_and_aa)
+                                    .with_binned_raster(binned_raster),
+                            )
+                            .build_ui(move |ui| demo.ui(ui));
+                        harness.run();
+                        harness.render().unwrap()
+                    });
+
+                    if binned_image.as_raw() != serial_image.as_raw() {
+                        let name = format!(
+                            "px_per_pt {px_per_point}, use_cache {use_cache}, lcd_and_aa {lcd_and_aa}"
+                        );
+                        let _ = std::fs::create_dir("tests/tmp/");
+                        binned_image
+                            .save(format!("tests/tmp/binned_{name} - FAIL.png"))
+                            .unwrap();
+                        serial_image
+                            .save(format!("tests/tmp/serial_{name} - FAIL.png"))
+                            .unwrap();
+                        panic!("binned render doesn't match serial render: {name}")
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Counts primitive cache key collisions over millions of distinct meshes: single glyph sized quads at every quarter
+    // pixel, and long meshes that only differ by the color of one vertex. No 64 bit key may collide, the lower 32 bits
+    // of the keys are counted for comparison.
+    pub fn count_primitive_cache_key_collisions() {
+        let mut keys = HashSet::new();
+        let mut keys_32 = HashSet::new();
+        let mut collisions = 0;
+        let mut collisions_32 = 0;
+        let mut count = |key: u64| {
+            collisions += !keys.insert(key) as u32;
+            collisions_32 += !keys_32.insert(key as u32) as u32;
+        };
+
+        let quad_size = vec2(7.0, 9.0);
+        let clip_rect = Rect::from_min_size(Pos2::ZERO, quad_size + Vec2::splat(0.5));
+        for i in 0..2_000_000u32 {
+            let mut mesh = egui::Mesh::default();
+            mesh.add_rect_with_uv(
+                Rect::from_min_size(
+                    pos2((i % 2000) as f32 * 0.25, (i / 2000) as f32 * 0.25),
+                    quad_size,
+                ),
+                Rect::from_min_size(
+                    pos2((i % 97) as f32, (i % 89) as f32) / 512.0,
+                    quad_size / 512.0,
+                ),
+                Color32::from_gray((i % 251) as u8),
+            );
+            count(primitive_cache_key(clip_rect, &mesh));
+        }
+
+        let mut mesh = egui::Mesh::default();
+        for i in 0..100 {
+            mesh.add_rect_with_uv(
+                Rect::from_min_size(pos2(i as f32 * 8.0, 0.0), quad_size),
+                Rect::from_min_size(Pos2::ZERO, quad_size / 512.0),
+                Color32::WHITE,
+            );
+        }
+        let clip_rect = Rect::from_min_size(Pos2::ZERO, vec2(800.5, 9.5));
+        for vertex in 0..mesh.vertices.len() {
+            for gray in 0..255 {
+                let mut mesh = mesh.clone();
+                mesh.vertices[vertex].color = Color32::from_gray(gray);
+                count(primitive_cache_key(clip_rect, &mesh));
+            }
+        }
+
+        println!("64 bit key collisions: {collisions}, 32 bit key collisions: {collisions_32}");
+        assert_eq!(collisions, 0, "64 bit primitive cache keys collided");
+    }
+
+    #[test]
+    // Draws overlapping rects whose cache keys all collide in the same frame, with cache verification. Frames where
+    // they're rasterized, reused from the cache and one of them moved must match a render without caching. With raster
+    // stats, checks that the collisions were caught.
+    pub fn same_frame_cache_key_collisions_draw_every_primitive() {
+        let (width, height) = (96, 64);
+        let mut textures_delta = egui::TexturesDelta::default();
+        textures_delta.set.push((
+            egui::TextureId::default(),
+            egui::epaint::ImageDelta::full(
+                egui::ColorImage::new([1, 1], vec![Color32::WHITE]),
+                egui::TextureOptions::NEAREST,
+            ),
+        ));
+        let paint_jobs = |moved: f32| {
+            [
+                (
+                    Rect::from_min_size(Pos2::ZERO, vec2(96.0, 64.0)),
+                    Color32::DARK_GRAY,
+                ),
+                (
+                    Rect::from_min_size(pos2(4.0, 4.0), vec2(50.0, 30.0)),
+                    Color32::RED,
+                ),
+                (
+                    Rect::from_min_size(pos2(30.0, 20.0), vec2(40.0, 40.0)),
+                    Color32::from_rgba_unmultiplied(0, 0, 255, 128),
+                ),
+                (
+                    Rect::from_min_size(pos2(moved, 10.0), vec2(20.0, 50.0)),
+                    Color32::GREEN,
+                ),
+            ]
+            .map(|(rect, color)| {
+                let mut mesh = egui::Mesh::default();
+                mesh.add_colored_rect(rect, color);
+                egui::ClippedPrimitive {
+                    clip_rect: Rect::EVERYTHING,
+                    primitive: egui::epaint::Primitive::Mesh(mesh),
+                }
+            })
+        };
+
+        let mut renderer =
+            EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_cache_verification(true);
+        set_cache_key_mask(&mut renderer, 0);
+        let mut uncached = EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_caching(false);
+        let no_delta = egui::TexturesDelta::default();
+        for (frame, moved) in [60.0, 60.0, 70.0].into_iter().enumerate() {
+            let paint_jobs = paint_jobs(moved);
+            let delta = if frame == 0 {
+                &textures_delta
+            } else {
+                &no_delta
+            };
+            let mut buffer = vec![[0u8; 4]; width * height];
+            renderer.render(
+                &mut BufferMutRef::new(&mut buffer, width, height),
+                &paint_jobs,
+                delta,
+                1.0,
+            );
+            let mut uncached_buffer = vec![[0u8; 4]; width * height];
+            uncached.render(
+                &mut BufferMutRef::new(&mut uncached_buffer, width, height),
+                &paint_jobs,
+                delta,
+                1.0,
+            );
+            assert!(
+                buffer == uncached_buffer,
+                "frame {frame}: the render doesn't match a render without caching"
+            );
+            #[cfg(feature = "raster_stats")]
+            if frame == 0 {
+                assert_eq!(
+                    renderer.stats.cache_collisions, 3,
+                    "the key collisions weren't caught"
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Renders a UI frame after frame, calling the renderer directly as the test harness allocates. Once egui's layout
+    // and the font atlas settled, rendering a frame must not allocate. The UI is rendered static, and with a large rect
+    // that changes color every frame so its primitive is rasterized again, binned into tiles with rayon, each with and
+    // without coverage AA. Allocations are counted per thread, so tests running in parallel don't interfere. With rayon
+    // the renderer runs in a thread pool of its own whose threads are all counted.
+    pub fn settled_ui_renders_without_allocating() {
+        #[cfg(feature = "rayon")]
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .start_handler(|_| POOL_THREAD.with(|pool_thread| pool_thread.set(true)))
+            .build()
+            .unwrap();
+
+        for (changing, coverage_aa) in [(false, false), (true, false), (false, true), (true, true)]
+        {
+            let ctx = egui::Context::default();
+            let mut renderer =
+                EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_coverage_aa(coverage_aa);
+            let (width, height) = (RESOLUTION.x as usize, RESOLUTION.y as usize);
+            let mut buffer = vec![[0u8; 4]; width * height];
+            let input = egui::RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, RESOLUTION)),
+                ..Default::default()
+            };
+
+            for frame in 0..10 {
+                let output = ctx.run_ui(input.clone(), |ui| {
+                    egui::CentralPanel::default().show_inside(ui, |ui| {
+                        ui.heading("Static");
+                        for i in 0..20 {
+                            ui.horizontal(|ui| {
+                                ui.label(format!(
+                                    "Row {i}: The quick brown fox jumps over the lazy dog"
+                                ));
+                                let _ = ui.button("Button");
+                                ui.checkbox(&mut (i % 2 == 0), "Checkbox");
+                            });
+                        }
+                        ui.add(egui::Slider::new(&mut 0.5, 0.0..=1.0).text("Slider"));
+                        if changing {
+                            ui.painter().rect_filled(
+                                Rect::from_min_size(pos2(700.0, 100.0), vec2(400.0, 300.0)),
+                                0.0,
+                                Color32::from_gray(frame as u8 * 20),
+                            );
+                        }
+                    });
+                });
+                let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+
+                let allocations_before = ALLOCATIONS.with(Cell::get);
+                let pool_allocations_before = POOL_ALLOCATIONS.load(Ordering::Relaxed);
+                #[allow(unused_mut)]
+                let mut render = || {
+                    renderer.render(
+                        &mut BufferMutRef::new(&mut buffer, width, height),
+                        &paint_jobs,
+                        &output.textures_delta,
+                        output.pixels_per_point,
+                    )
+                };
+                #[cfg(feature = "rayon")]
+                pool.install(render);
+                #[cfg(not(feature = "rayon"))]
+                render();
+                let allocations = ALLOCATIONS.with(Cell::get) - allocations_before
+                    + POOL_ALLOCATIONS.load(Ordering::Relaxed)
+                    - pool_allocations_before;
+
+                if frame >= 3 {
+                    assert_eq!(
+                        allocations, 0,
+                        "rendering frame {frame} allocated, changing {changing}, coverage_aa {coverage_aa}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Draws a test image minified and magnified with each extended sampling filter, with and without caching, and
+    // compares the results with the golden images in tests/golden/. Pixels may differ by 1 between SIMD
+    // implementations. Run with UPDATE_GOLDEN=1 to write the golden images again after an intended change.
+    pub fn extended_sampling_matches_golden_images() {
+        let pixels = (0..96 * 64)
+            .map(|i| {
+                let (x, y) = (i % 96, i / 96);
+                if x == y + 16 {
+                    Color32::WHITE
+                } else {
+                    let checker = if (x / 8 + y / 8) % 2 == 0 { 255 } else { 40 };
+                    Color32::from_rgb((x * 255 / 95) as u8, (y * 255 / 63) as u8, checker)
+                }
+            })
+            .collect();
+        let image = egui::ColorImage::new([96, 64], pixels);
+        let (width, height) = (260, 180);
+        let cases = [
+            (
+                "box_minified",
+                ExtendedSampling {
+                    minification: MinificationFilter::Box,
+                    ..Default::default()
+                },
+                vec2(37.0, 25.0),
+            ),
+            (
+                "bicubic_magnified",
+                ExtendedSampling {
+                    magnification: MagnificationFilter::Bicubic,
+                    ..Default::default()
+                },
+                vec2(250.0, 170.0),
+            ),
+            (
+                "lanczos3_magnified",
+                ExtendedSampling {
+                    magnification: MagnificationFilter::Lanczos3,
+                    ..Default::default()
+                },
+                vec2(250.0, 170.0),
+            ),
+        ];
+        let update = std::env::var("UPDATE_GOLDEN").is_ok();
+        let _ = std::fs::create_dir("tests/golden/");
+
+        for (name, sampling, size) in cases {
+            for use_cache in [false, true] {
+                let ctx = egui::Context::default();
+                let mut renderer =
+                    EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_caching(use_cache);
+                let mut texture = None;
+                let mut buffer = Vec::new();
+                let input = egui::RawInput {
+                    screen_rect: Some(Rect::from_min_size(
+                        Pos2::ZERO,
+                        vec2(width as f32, height as f32),
+                    )),
+                    ..Default::default()
+                };
+
+                for _ in 0..3 {
+                    let output = ctx.run_ui(input.clone(), |ui| {
+                        let texture: &egui::TextureHandle = texture.get_or_insert_with(|| {
+                            ui.ctx().load_texture(
+                                "image",
+                                image.clone(),
+                                egui::TextureOptions::LINEAR,
+                            )
+                        });
+                        ui.ctx().layer_painter(egui::LayerId::background()).image(
+                            texture.id(),
+                            Rect::from_min_size(pos2(5.0, 5.0), size),
+                            Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0)),
+                            Color32::WHITE,
+                        );
+                    });
+                    if let Some(texture) = &texture {
+                        renderer.set_extended_sampling(texture.id(), sampling);
+                    }
+                    let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+                    buffer = vec![[32, 32, 32, 255]; width * height];
+                    renderer.render(
+                        &mut BufferMutRef::new(&mut buffer, width, height),
+                        &paint_jobs,
+                        &output.textures_delta,
+                        output.pixels_per_point,
+                    );
+                }
+
+                let render_image = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(
+                    width as u32,
+                    height as u32,
+                    buffer.iter().flatten().copied().collect(),
+                )
+                .unwrap();
+                let path = format!("tests/golden/{name}.png");
+                if update && !use_cache {
+                    render_image.save(&path).unwrap();
+                    continue;
+                }
+
+                let golden = image::open(&path).unwrap().to_rgba8();
+                let max_diff = golden
+                    .as_raw()
+                    .iter()
+                    .zip(render_image.as_raw())
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .max()
+                    .unwrap();
+                if max_diff > 1 {
+                    let _ = std::fs::create_dir("tests/tmp/");
+                    render_image
+                        .save(format!("tests/tmp/{name}_use_cache_{use_cache} - FAIL.png"))
+                        .unwrap();
+                    panic!("{name}, use_cache {use_cache}: max diff {max_diff} from golden image");
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Renders text in several colors, partly clipped, at whole and fractional scales and with the font texture stored as
+    // color and as alpha. Glyph quads of the font texture are blitted in glyph runs. Rendered again with the font
+    // texture loaded under another id, the same quads are drawn one by one as rects. Both must match exactly.
+    pub fn glyph_runs_match_rects() {
+        let (width, height) = (320, 200);
+        let font_copy = egui::TextureId::Managed(1000);
+        // The same paint jobs and texture deltas with the font texture moved to `font_copy`
+        let move_font = |paint_jobs: &[egui::ClippedPrimitive], delta: &egui::TexturesDelta| {
+            let paint_jobs = paint_jobs
+                .iter()
+                .cloned()
+                .map(|mut job| {
+                    if let egui::epaint::Primitive::Mesh(mesh) = &mut job.primitive
+                        && mesh.texture_id == egui::TextureId::default()
+                    {
+                        mesh.texture_id = font_copy;
+                    }
+                    job
+                })
+                .collect::<Vec<_>>();
+            let set = delta
+                .set
+                .iter()
+                .cloned()
+                .map(|(id, image)| {
+                    let id = if id == egui::TextureId::default() {
+                        font_copy
+                    } else {
+                        id
+                    };
+                    (id, image)
+                })
+                .collect();
+            (paint_jobs, egui::TexturesDelta { set, free: vec![] })
+        };
+
+        for px_per_point in [1.0, 1.5] {
+            for alpha_textures in [false, true] {
+                let ctx = egui::Context::default();
+                let mut renderers = [(); 2].map(|_| {
+                    EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                        .with_alpha_textures(alpha_textures)
+                        .with_caching(false)
+                });
+                let input = egui::RawInput {
+                    screen_rect: Some(Rect::from_min_size(
+                        Pos2::ZERO,
+                        vec2(width as f32, height as f32) / px_per_point,
+                    )),
+                    ..Default::default()
+                };
+                ctx.set_pixels_per_point(px_per_point);
+
+                for frame in 0..2 {
+                    let output = ctx.run_ui(input.clone(), |ui| {
+                        ui.heading(format!("Frame {frame}"));
+                        for (i, color) in [Color32::WHITE, Color32::YELLOW, Color32::LIGHT_BLUE]
+                            .into_iter()
+                            .enumerate()
+                        {
+                            ui.colored_label(color, format!("Row {i}: The quick brown fox jumps"));
+                        }
+                        egui::ScrollArea::vertical()
+                            .max_height(30.0)
+                            .show(ui, |ui| {
+                                for i in 0..5 {
+                                    ui.label(format!("Clipped row {i} of text, abcdefghijklmnop"));
+                                }
+                            });
+                    });
+                    let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+                    let (moved_jobs, moved_delta) = move_font(&paint_jobs, &output.textures_delta);
+                    let render =
+                        |renderer: &mut EguiSoftwareRender,
+                         paint_jobs: &[egui::ClippedPrimitive],
+                         textures_delta: &egui::TexturesDelta| {
+                            let mut buffer = vec![[0u8; 4]; width * height];
+                            renderer.render(
+                                &mut BufferMutRef::new(&mut buffer, width, height),
+                                paint_jobs,
+                                textures_delta,
+                                output.pixels_per_point,
+                            );
+                            buffer
+                        };
+                    let glyph_runs = render(&mut renderers[0], &paint_jobs, &output.textures_delta);
+                    let rects = render(&mut renderers[1], &moved_jobs, &moved_delta);
+                    let name = format!(
+                        "px_per_pt {px_per_point}, alpha_textures {alpha_textures}, frame {frame}"
+                    );
+                    #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                    {
+                        assert!(renderers[0].stats.glyph_runs > 0, "{name}: no glyph runs");
+                        assert_eq!(renderers[1].stats.glyph_runs, 0, "{name}");
+                    }
+                    assert!(glyph_runs == rects, "{name}: glyph runs don't match rects");
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Renders white text with LCD text on and off, over a transparent background and over an opaque black one. Over
+    // the transparent background LCD text must fall back to grayscale and match exactly. Over the black background
+    // the channels must be filtered separately, giving colored fringes where grayscale text is gray, with about the
+    // same total coverage. A BGRA render must put red on the left like an RGBA render.
+    pub fn lcd_text_filters_channels_over_opaque_dst() {
+        let (width, height) = (240, 80);
+        let screen = Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32));
+        let render = |lcd_text: bool, opaque: bool, field_order: ColorFieldOrder| {
+            let ctx = egui::Context::default();
+            let input = egui::RawInput {
+                screen_rect: Some(screen),
+                ..Default::default()
+            };
+            let output = ctx.run_ui(input, |ui| {
+                if opaque {
+                    ui.painter().rect_filled(screen, 0.0, Color32::BLACK);
+                }
+                for i in 0..3 {
+                    ui.colored_label(
+                        Color32::WHITE,
+                        format!("Row {i}: The quick brown fox jumps"),
+                    );
+                }
+            });
+            let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+            let mut renderer = EguiSoftwareRender::new(field_order)
+                .with_lcd_text(lcd_text)
+                .with_caching(false);
+            let mut buffer = vec![[0u8; 4]; width * height];
+            renderer.render(
+                &mut BufferMutRef::new(&mut buffer, width, height),
+                &paint_jobs,
+                &output.textures_delta,
+                output.pixels_per_point,
+            );
+            buffer
+        };
+        let coverage = |buffer: &[[u8; 4]]| {
+            buffer
+                .iter()
+                .map(|pixel| pixel[..3].iter().map(|&c| c as u64).sum::<u64>())
+                .sum::<u64>()
+        };
+
+        let gray = render(false, false, ColorFieldOrder::Rgba);
+        assert!(gray.iter().any(|pixel| pixel[3] > 0), "no text drawn");
+        assert!(
+            render(true, false, ColorFieldOrder::Rgba) == gray,
+            "LCD text over a transparent background isn't grayscale"
+        );
+
+        let gray = render(false, true, ColorFieldOrder::Rgba);
+        let lcd = render(true, true, ColorFieldOrder::Rgba);
+        assert!(
+            gray.iter()
+                .all(|&[r, g, b, a]| r == g && g == b && a == 255),
+            "grayscale text over an opaque background isn't gray and opaque"
+        );
+        assert!(
+            lcd.iter().all(|pixel| pixel[3] == 255),
+            "LCD text over an opaque background isn't opaque"
+        );
+        let fringes = lcd
+            .iter()
+            .filter(|&&[r, _, b, _]| r.abs_diff(b) > 32)
+            .count();
+        assert!(
+            fringes > 100,
+            "only {fringes} pixels of LCD text over an opaque background have colored fringes"
+        );
+        let (lcd_coverage, gray_coverage) = (coverage(&lcd), coverage(&gray));
+        assert!(
+            lcd_coverage.abs_diff(gray_coverage) * 50 < gray_coverage,
+            "LCD text coverage {lcd_coverage} differs from grayscale {gray_coverage} by more than 2%"
+        );
+
+        let bgra = render(true, true, ColorFieldOrder::Bgra);
+        assert!(
+            bgra.iter()
+                .map(|&[b, g, r, a]| [r, g, b, a])
+                .eq(lcd.iter().copied()),
+            "BGRA LCD text doesn't match RGBA LCD text"
+        );
+    }
+
+    #[test]
+    // Renders the same frames with two renderers sharing a TextureStore, one cached and one direct, and with a renderer
+    // that has its own store. A texture is drawn and freed in the same frame, so the second renderer sharing the store
+    // still draws it. The shared store is only freed once both rendered. Also checks that the second renderer given the
+    // same delta doesn't load its textures again.
+    pub fn shared_texture_store_matches_own_store() {
+        let ctx = egui::Context::default();
+        let store = TextureStore::new_shared(ColorFieldOrder::Rgba);
+        let mut shared = [
+            EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_texture_store(store.clone()),
+            EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                .with_texture_store(store.clone())
+                .with_caching(false),
+        ];
+        let mut own = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+        let (width, height) = (320, 200);
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                vec2(width as f32, height as f32),
+            )),
+            ..Default::default()
+        };
+        let image = |seed: usize| {
+            let pixels = (0..32 * 32)
+                .map(|i| {
+                    Color32::from_rgb((i * seed) as u8, (i / 32 * 8) as u8, (i % 32 * 8) as u8)
+                })
+                .collect();
+            egui::ColorImage::new([32, 32], pixels)
+        };
+
+        let mut texture: Option<egui::TextureHandle> = None;
+        for frame in 0..6 {
+            let output = ctx.run_ui(input.clone(), |ui| {
+                if frame == 1 || frame == 4 {
+                    texture = Some(ui.ctx().load_texture(
+                        format!("image {frame}"),
+                        image(frame + 3),
+                        egui::TextureOptions::NEAREST,
+                    ));
+                }
+                ui.label(format!("Frame {frame}"));
+                if let Some(texture) = &texture {
+                    ui.image((texture.id(), vec2(64.0, 64.0)));
+                }
+                if frame == 3 {
+                    texture = None;
+                }
+            });
+            let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+            let render = |renderer: &mut EguiSoftwareRender| {
+                let mut buffer = vec![[0u8; 4]; width * height];
+                renderer.render(
+                    &mut BufferMutRef::new(&mut buffer, width, height),
+                    &paint_jobs,
+                    &output.textures_delta,
+                    output.pixels_per_point,
+                );
+                buffer
+            };
+            let own_buffer = render(&mut own);
+            for (i, renderer) in shared.iter_mut().enumerate() {
+                assert!(
+                    render(renderer) == own_buffer,
+                    "frame {frame}: renderer {i} sharing the store doesn't match one with its own store"
+                );
+            }
+            store.write().free_textures(&output.textures_delta);
+        }
+
+        let id = egui::TextureId::Managed(1000);
+        let delta = egui::TexturesDelta {
+            set: vec![(
+                id,
+                egui::epaint::ImageDelta::full(image(1), egui::TextureOptions::NEAREST),
+            )],
+            free: vec![],
+        };
+        store.write().set_textures(&delta);
+        store.write().texture_mut(id).unwrap().data[0] = [1, 2, 3, 4];
+        store.write().set_textures(&delta.clone());
+        assert_eq!(
+            store.write().texture_mut(id).unwrap().data[0],
+            [1, 2, 3, 4],
+            "applying the same delta again loaded the texture again"
+        );
+    }
+
+    #[test]
+    // Draws two minified images with two cached renderers sharing a TextureStore, setting the extended sampling of one
+    // of them every frame and changing it now and then. Every frame must match a direct render with the same sampling,
+    // so the renderer that didn't set it also rasterizes that image again. With raster stats, checks that setting an
+    // unchanged sampling doesn't rasterize anything and a change only rasterizes the image using that texture.
+    pub fn changing_extended_sampling_invalidates_its_texture() {
+        let ctx = egui::Context::default();
+        let store = TextureStore::new_shared(ColorFieldOrder::Rgba);
+        let mut cached = [
+            EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_texture_store(store.clone()),
+            EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_texture_store(store.clone()),
+        ];
+        let mut direct = EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_caching(false);
+        let (width, height) = (200, 100);
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                vec2(width as f32, height as f32),
+            )),
+            ..Default::default()
+        };
+        let pixels = (0..128 * 128)
+            .map(|i| {
+                let checker = if (i % 128 + i / 128) % 2 == 0 { 255 } else { 0 };
+                Color32::from_rgb(checker, (i % 128 * 2) as u8, (i / 128 * 2) as u8)
+            })
+            .collect();
+        let image = egui::ColorImage::new([128, 128], pixels);
+        let boxed = ExtendedSampling {
+            minification: MinificationFilter::Box,
+            ..Default::default()
+        };
+        let samplings = [
+            ExtendedSampling::default(),
+            boxed,
+            boxed,
+            boxed,
+            ExtendedSampling::default(),
+            ExtendedSampling::default(),
+        ];
+
+        let mut textures: Option<[egui::TextureHandle; 2]> = None;
+        for (frame, sampling) in samplings.into_iter().enumerate() {
+            let output = ctx.run_ui(input.clone(), |ui| {
+                let textures = textures.get_or_insert_with(|| {
+                    [0, 1].map(|i| {
+                        ui.ctx().load_texture(
+                            format!("image {i}"),
+                            image.clone(),
+                            egui::TextureOptions::LINEAR,
+                        )
+                    })
+                });
+                for (i, texture) in textures.iter().enumerate() {
+                    ui.ctx().layer_painter(egui::LayerId::background()).image(
+                        texture.id(),
+                        Rect::from_min_size(pos2(10.0 + i as f32 * 100.0, 10.0), vec2(37.0, 41.0)),
+                        Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0)),
+                        Color32::WHITE,
+                    );
+                }
+            });
+            let id = textures.as_ref().unwrap()[0].id();
+            cached[0].set_extended_sampling(id, sampling);
+            direct.set_extended_sampling(id, sampling);
+
+            let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+            let render = |renderer: &mut EguiSoftwareRender| {
+                let mut buffer = vec![[32, 32, 32, 255]; width * height];
+                renderer.render(
+                    &mut BufferMutRef::new(&mut buffer, width, height),
+                    &paint_jobs,
+                    &output.textures_delta,
+                    output.pixels_per_point,
+                );
+                buffer
+            };
+            let direct_buffer = render(&mut direct);
+            for (i, renderer) in cached.iter_mut().enumerate() {
+                assert!(
+                    render(renderer) == direct_buffer,
+                    "frame {frame}: cached renderer {i} doesn't match a direct render"
+                );
+                #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+                if frame > 0 && i == 0 {
+                    let rects = renderer.stats.rects;
+                    if sampling == samplings[frame - 1] {
+                        assert_eq!(rects, 0, "frame {frame}: unchanged sampling rasterized");
+                    } else {
+                        assert_eq!(rects, 1, "frame {frame}: changed sampling rasterized");
+                    }
+                }
+            }
+            store.write().free_textures(&output.textures_delta);
+        }
+    }
+
+    /// Procedural texture that controls the worker thread of a `ThreadedEguiSoftwareRender` drawing it: sampling waits
+    /// while `paused` is set and panics once `panic` is set.
+    #[derive(Default)]
+    struct WorkerControl {
+        paused: AtomicBool,
+        panic: AtomicBool,
+    }
+
+    impl ProceduralTexture for WorkerControl {
+        fn sample(&self, _uv: Vec2) -> [u8; 4] {
+            while self.paused.load(Ordering::Acquire) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert!(
+                !self.panic.load(Ordering::Acquire),
+                "render worker test panic"
+            );
+            [255, 0, 0, 255]
+        }
+    }
+
+    /// Procedural texture whose colors change with `frame`, counting the spans and single samples taken of it
+    #[derive(Default)]
+    struct AnimatedTexture {
+        frame: AtomicUsize,
+        spans: AtomicUsize,
+        samples: AtomicUsize,
+    }
+
+    impl AnimatedTexture {
+        fn color(&self, uv: Vec2) -> [u8; 4] {
+            let frame = self.frame.load(Ordering::Relaxed);
+            [
+                (uv.x * 255.0) as u8,
+                (uv.y * 255.0) as u8,
+                (frame * 60) as u8,
+                255,
+            ]
+        }
+    }
+
+    impl ProceduralTexture for AnimatedTexture {
+        fn sample(&self, uv: Vec2) -> [u8; 4] {
+            self.samples.fetch_add(1, Ordering::Relaxed);
+            self.color(uv)
+        }
+
+        fn fill_span(&self, uv_start: Vec2, uv_step: Vec2, out: &mut [[u8; 4]]) {
+            self.spans.fetch_add(1, Ordering::Relaxed);
+            for (i, out) in out.iter_mut().enumerate() {
+                *out = self.color(uv_start + uv_step * i as f32);
+            }
+        }
+    }
+
+    /// A paint job drawing the texture `id` over `rect`
+    fn textured_rect(id: egui::TextureId, rect: Rect) -> egui::ClippedPrimitive {
+        let mut mesh = egui::Mesh::with_texture(id);
+        mesh.add_rect_with_uv(
+            rect,
+            Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        egui::ClippedPrimitive {
+            clip_rect: Rect::EVERYTHING,
+            primitive: egui::epaint::Primitive::Mesh(mesh),
+        }
+    }
+
+    #[test]
+    // Draws an animated procedural texture as a rect and as a tri over a static textured background, with the same
+    // paint jobs every frame. The procedural primitives must be rasterized again every frame from the cache and match
+    // a fresh renderer's render of the frame, each frame differing from the last. The texture must be sampled in spans
+    // through `fill_span`, never a sample at a time.
+    pub fn procedural_textures_rasterize_every_frame() {
+        let (width, height) = (96, 64);
+        let background_id = egui::TextureId::Managed(1);
+        let mut textures_delta = egui::TexturesDelta::default();
+        textures_delta.set.push((
+            background_id,
+            egui::epaint::ImageDelta::full(
+                egui::ColorImage::new([4, 4], vec![Color32::from_rgb(40, 80, 120); 16]),
+                egui::TextureOptions::NEAREST,
+            ),
+        ));
+
+        let texture = Arc::new(AnimatedTexture::default());
+        let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+        let id = renderer.register_procedural_texture(texture.clone());
+
+        let mut tri = egui::Mesh::with_texture(id);
+        for (pos, uv) in [
+            (pos2(50.0, 6.0), pos2(0.0, 0.0)),
+            (pos2(92.0, 20.0), pos2(1.0, 0.5)),
+            (pos2(60.0, 58.0), pos2(0.2, 1.0)),
+        ] {
+            tri.vertices.push(egui::epaint::Vertex {
+                pos,
+                uv,
+                color: Color32::WHITE,
+            });
+        }
+        tri.add_triangle(0, 1, 2);
+        let paint_jobs = [
+            textured_rect(
+                background_id,
+                Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32)),
+            ),
+            textured_rect(id, Rect::from_min_size(pos2(4.0, 4.0), vec2(40.0, 30.0))),
+            egui::ClippedPrimitive {
+                clip_rect: Rect::EVERYTHING,
+                primitive: egui::epaint::Primitive::Mesh(tri),
+            },
+        ];
+
+        let no_delta = egui::TexturesDelta::default();
+        let mut previous: Option<Vec<[u8; 4]>> = None;
+        for frame in 0..4 {
+            texture.frame.store(frame, Ordering::Relaxed);
+            texture.spans.store(0, Ordering::Relaxed);
+            let mut buffer = vec![[0u8; 4]; width * height];
+            renderer.render(
+                &mut BufferMutRef::new(&mut buffer, width, height),
+                &paint_jobs,
+                if frame == 0 {
+                    &textures_delta
+                } else {
+                    &no_delta
+                },
+                1.0,
+            );
+            assert!(
+                texture.spans.load(Ordering::Relaxed) > 0,
+                "frame {frame}: the procedural texture wasn't sampled"
+            );
+
+            let mut fresh = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+            assert_eq!(fresh.register_procedural_texture(texture.clone()), id);
+            let mut fresh_buffer = vec![[0u8; 4]; width * height];
+            fresh.render(
+                &mut BufferMutRef::new(&mut fresh_buffer, width, height),
+                &paint_jobs,
+                &textures_delta,
+                1.0,
+            );
+            assert!(
+                buffer == fresh_buffer,
+                "frame {frame}: the render doesn't match a fresh render"
+            );
+            if let Some(previous) = &previous {
+                assert!(
+                    *previous != buffer,
+                    "frame {frame}: the procedural texture wasn't drawn again"
+                );
+            }
+            previous = Some(buffer);
+        }
+        assert_eq!(
+            texture.samples.load(Ordering::Relaxed),
+            0,
+            "the procedural texture was sampled outside of spans"
+        );
+    }
+
+    #[test]
+    // Draws two streaming textures over a static textured background, the first as a rect and a tri and the second as
+    // a rect, with the same paint jobs every frame. A new frame is written into the first with `texture_mut` and
+    // swapped into the second with `swap_texture_buffer`, one texture per frame. Every frame must match a fresh render
+    // of the same texels. With stats, only the primitives of the written texture may be rasterized again.
+    pub fn streamed_textures_rasterize_their_primitives_only() {
+        let (width, height) = (96, 64);
+        let size = [8, 8];
+        let background_id = egui::TextureId::Managed(1);
+        let mut textures_delta = egui::TexturesDelta::default();
+        textures_delta.set.push((
+            background_id,
+            egui::epaint::ImageDelta::full(
+                egui::ColorImage::new([4, 4], vec![Color32::from_rgb(40, 80, 120); 16]),
+                egui::TextureOptions::NEAREST,
+            ),
+        ));
+        // Opaque texels, so they're valid premultiplied colors
+        let frame_texels = |seed: u32| {
+            let mut bytes = random_bytes(seed);
+            (0..size[0] * size[1])
+                .map(|_| [(); 3].map(|_| bytes.next().unwrap()))
+                .map(|[r, g, b]| [r, g, b, 255])
+                .collect::<Vec<[u8; 4]>>()
+        };
+
+        let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+        let ids = [(); 2]
+            .map(|_| renderer.register_streaming_texture(size, egui::TextureOptions::NEAREST));
+
+        let mut tri = egui::Mesh::with_texture(ids[0]);
+        for (pos, uv) in [
+            (pos2(50.0, 6.0), pos2(0.0, 0.0)),
+            (pos2(92.0, 20.0), pos2(1.0, 0.5)),
+            (pos2(60.0, 58.0), pos2(0.2, 1.0)),
+        ] {
+            tri.vertices.push(egui::epaint::Vertex {
+                pos,
+                uv,
+                color: Color32::WHITE,
+            });
+        }
+        tri.add_triangle(0, 1, 2);
+        let paint_jobs = [
+            textured_rect(
+                background_id,
+                Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32)),
+            ),
+            textured_rect(
+                ids[0],
+                Rect::from_min_size(pos2(4.0, 4.0), vec2(40.0, 24.0)),
+            ),
+            egui::ClippedPrimitive {
+                clip_rect: Rect::EVERYTHING,
+                primitive: egui::epaint::Primitive::Mesh(tri),
+            },
+            textured_rect(
+                ids[1],
+                Rect::from_min_size(pos2(4.0, 34.0), vec2(40.0, 24.0)),
+            ),
+        ];
+
+        let mut texels = [frame_texels(1), frame_texels(2)];
+        let mut swap_buffer = texels[1].clone();
+        renderer
+            .texture_mut(ids[0])
+            .unwrap()
+            .data
+            .copy_from_slice(&texels[0]);
+        assert!(renderer.swap_texture_buffer(ids[1], &mut swap_buffer));
+
+        let no_delta = egui::TexturesDelta::default();
+        let mut previous: Option<Vec<[u8; 4]>> = None;
+        // The texture written before each frame, if any
+        for (frame, written) in [None, Some(0), Some(1), None, Some(0)]
+            .into_iter()
+            .enumerate()
+        {
+            match written {
+                Some(0) => {
+                    texels[0] = frame_texels(10 + frame as u32);
+                    let texture = renderer.texture_mut(ids[0]).unwrap();
+                    assert_eq!((texture.width, texture.height), (size[0], size[1]));
+                    texture.data.copy_from_slice(&texels[0]);
+                }
+                Some(_) => {
+                    let previous_texels = texels[1].clone();
+                    texels[1] = frame_texels(10 + frame as u32);
+                    swap_buffer.copy_from_slice(&texels[1]);
+                    assert!(renderer.swap_texture_buffer(ids[1], &mut swap_buffer));
+                    assert!(
+                        swap_buffer == previous_texels,
+                        "frame {frame}: the swapped out buffer doesn't hold the previous texels"
+                    );
+                }
+                None => {}
+            }
+            let mut buffer = vec![[0u8; 4]; width * height];
+            renderer.render(
+                &mut BufferMutRef::new(&mut buffer, width, height),
+                &paint_jobs,
+                if frame == 0 {
+                    &textures_delta
+                } else {
+                    &no_delta
+                },
+                1.0,
+            );
+
+            let mut fresh = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+            for (id, texels) in ids.iter().zip(&texels) {
+                assert_eq!(
+                    fresh.register_streaming_texture(size, egui::TextureOptions::NEAREST),
+                    *id
+                );
+                fresh.texture_mut(*id).unwrap().data.copy_from_slice(texels);
+            }
+            let mut fresh_buffer = vec![[0u8; 4]; width * height];
+            fresh.render(
+                &mut BufferMutRef::new(&mut fresh_buffer, width, height),
+                &paint_jobs,
+                &textures_delta,
+                1.0,
+            );
+            assert!(
+                buffer == fresh_buffer,
+                "frame {frame}: the render doesn't match a fresh render"
+            );
+
+            #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+            {
+                // The background and both textures' rects and the tri the first time, then only those of the texture
+                // written
+                let expected = match (frame, written) {
+                    (0, _) => (3, 1),
+                    (_, Some(0)) => (1, 1),
+                    (_, Some(_)) => (1, 0),
+                    (_, None) => (0, 0),
+                };
+                assert_eq!(
+                    (renderer.stats.rects, renderer.stats.tris),
+                    expected,
+                    "frame {frame}: (rects, tris) rasterized"
+                );
+            }
+            if let Some(previous) = &previous {
+                assert_eq!(
+                    *previous != buffer,
+                    written.is_some(),
+                    "frame {frame}: the render changed if and only if a texture was written"
+                );
+            }
+            previous = Some(buffer);
+        }
+    }
+
+    #[test]
+    // Draws a textured rect from a mesh laid out like `Shape::image`, then with its vertices and the indices within and
+    // between its two tris permuted and other tris between them. Both must be drawn as one rect and match. With raster
+    // stats, also checks that the halves of a rect further apart than the searched tris aren't paired.
+    pub fn rect_pairing_ignores_index_order() {
+        let (width, height) = (64, 64);
+        let texture_id = egui::TextureId::Managed(0);
+        let pixels = (0..16 * 16)
+            .map(|i| Color32::from_rgb((i % 16 * 16) as u8, (i / 16 * 16) as u8, 128))
+            .collect();
+        let mut textures_delta = egui::TexturesDelta::default();
+        textures_delta.set.push((
+            texture_id,
+            egui::epaint::ImageDelta::full(
+                egui::ColorImage::new([16, 16], pixels),
+                egui::TextureOptions::NEAREST,
+            ),
+        ));
+
+        let rect = Rect::from_min_max(pos2(8.0, 8.0), pos2(40.0, 40.0));
+        let uv = Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0));
+        // Tris that aren't half of a rect and don't overlap `rect`
+        let add_fillers = |mesh: &mut egui::Mesh, count: usize| {
+            for i in 0..count {
+                let x = i as f32 * 6.0;
+                let first = mesh.vertices.len() as u32;
+                for pos in [pos2(x, 48.0), pos2(x + 4.0, 48.0), pos2(x + 2.0, 52.0)] {
+                    mesh.colored_vertex(pos, Color32::GREEN);
+                }
+                mesh.add_triangle(first, first + 1, first + 2);
+            }
+        };
+
+        // The same mesh as `Shape::image`
+        let mut image = egui::Mesh::with_texture(texture_id);
+        image.add_rect_with_uv(rect, uv, Color32::WHITE);
+        add_fillers(&mut image, 3);
+
+        // Vertices in the order max/max, min/min, min/max, max/min, with the two tris swapped and rotated
+        let mut permuted = egui::Mesh::with_texture(texture_id);
+        for (pos, uv) in [
+            (rect.max, uv.max),
+            (rect.min, uv.min),
+            (rect.left_bottom(), uv.left_bottom()),
+            (rect.right_top(), uv.right_top()),
+        ] {
+            permuted.vertices.push(egui::epaint::Vertex {
+                pos,
+                uv,
+                color: Color32::WHITE,
+            });
+        }
+        permuted.add_triangle(0, 2, 3);
+        add_fillers(&mut permuted, 3);
+        permuted.add_triangle(2, 1, 3);
+
+        // The halves of the rect are 9 tris apart, past the searched tris
+        let mut far_apart = egui::Mesh::with_texture(texture_id);
+        far_apart.add_rect_with_uv(rect, uv, Color32::WHITE);
+        let second_half = far_apart.indices.split_off(3);
+        add_fillers(&mut far_apart, 8);
+        far_apart.indices.extend(second_half);
+
+        let render = |mesh: egui::Mesh, expected_hits: u32| {
+            let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+            let mut buffer = vec![[0u8; 4]; width * height];
+            renderer.render(
+                &mut BufferMutRef::new(&mut buffer, width, height),
+                &[egui::ClippedPrimitive {
+                    clip_rect: Rect::EVERYTHING,
+                    primitive: egui::epaint::Primitive::Mesh(mesh),
+                }],
+                &textures_delta,
+                1.0,
+            );
+            #[cfg(all(feature = "raster_stats", not(feature = "rayon")))]
+            assert_eq!(renderer.stats.rect_search_hits, expected_hits);
+            #[cfg(not(all(feature = "raster_stats", not(feature = "rayon"))))]
+            let _ = expected_hits;
+            buffer
+        };
+
+        assert!(
+            render(permuted, 1) == render(image, 1),
+            "the permuted mesh doesn't match the image mesh"
+        );
+        render(far_apart, 0);
+    }
+
+    /// The z component of the cross product of `a` and `b`, positive if `b` points clockwise of `a` on the screen
+    fn cross(a: Vec2, b: Vec2) -> f32 {
+        a.x * b.y - a.y * b.x
+    }
+
+    /// A mesh filling the convex polygon `points` with `color`, as a fan of tris around its center so its tris share
+    /// edges inside of it
+    fn convex_fan_mesh(points: &[Pos2], color: Color32) -> egui::Mesh {
+        let mut mesh = egui::Mesh::default();
+        let center =
+            points.iter().fold(Vec2::ZERO, |sum, p| sum + p.to_vec2()) / points.len() as f32;
+        mesh.colored_vertex(center.to_pos2(), color);
+        for &point in points {
+            mesh.colored_vertex(point, color);
+        }
+        for i in 0..points.len() as u32 {
+            let (a, b) = (1 + i, 1 + (i + 1) % points.len() as u32);
+            let (pa, pb) = (mesh.vertices[a as usize].pos, mesh.vertices[b as usize].pos);
+            // The rasterizer culls tris with a negative area
+            if cross(pa - center.to_pos2(), pb - center.to_pos2()) > 0.0 {
+                mesh.add_triangle(0, a, b);
+            } else {
+                mesh.add_triangle(0, b, a);
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    // Draws translucent convex polygons triangulated as fans with coverage AA, and compares every pixel with the same
+    // polygons drawn without it. Pixels fully inside a polygon must be unchanged and all the same, so tris sharing an
+    // edge inside of it leave no seam. Pixels fully outside must keep the background. Pixels on the outline must be
+    // blended between the two, by their coverage: summed up they must match the area of the polygon.
+    pub fn coverage_aa_blends_mesh_edges() {
+        let (width, height) = (160, 80);
+        let background = [30, 30, 30, 255];
+        let circle = (0..24)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::TAU / 24.0;
+                pos2(40.3, 40.7) + 25.2 * Vec2::angled(angle)
+            })
+            .collect::<Vec<_>>();
+        let square = (0..4)
+            .map(|i| {
+                let angle = 0.3 + i as f32 * std::f32::consts::FRAC_PI_2;
+                pos2(110.4, 40.2) + 28.0 * Vec2::angled(angle)
+            })
+            .collect::<Vec<_>>();
+        let polygons = [
+            (circle, Color32::from_white_alpha(160)),
+            (square, Color32::from_rgba_premultiplied(0, 120, 200, 200)),
+        ];
+        let paint_jobs = polygons
+            .iter()
+            .map(|(points, color)| egui::ClippedPrimitive {
+                clip_rect: Rect::EVERYTHING,
+                primitive: egui::epaint::Primitive::Mesh(convex_fan_mesh(points, *color)),
+            })
+            .collect::<Vec<_>>();
+        // The meshes are drawn with the font texture, white where they sample it
+        let mut textures_delta = egui::TexturesDelta::default();
+        textures_delta.set.push((
+            egui::TextureId::default(),
+            egui::epaint::ImageDelta::full(
+                egui::ColorImage::new([1, 1], vec![Color32::WHITE]),
+                egui::TextureOptions::NEAREST,
+            ),
+        ));
+
+        // The min distance of `p` inside the edges of the polygon. Outside of it this is negative and no further from
+        // the polygon than `p`.
+        let inside = |points: &[Pos2], p: Pos2| {
+            (0..points.len())
+                .map(|i| {
+                    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                    cross((b - a).normalized(), p - a)
+                })
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        for use_cache in [false, true] {
+            for allow_raster_opt in [false, true] {
+                let render = |coverage_aa: bool| {
+                    let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                        .with_caching(use_cache)
+                        .with_allow_raster_opt(allow_raster_opt)
+                        .with_coverage_aa(coverage_aa);
+                    let mut buffer = vec![background; width * height];
+                    renderer.render(
+                        &mut BufferMutRef::new(&mut buffer, width, height),
+                        &paint_jobs,
+                        &textures_delta,
+                        1.0,
+                    );
+                    buffer
+                };
+                let [aa, aliased] = [true, false].map(render);
+                let name = format!("use_cache {use_cache}, raster_opt {allow_raster_opt}");
+
+                let fills = polygons.each_ref().map(|(points, _)| {
+                    let center = points.iter().fold(Vec2::ZERO, |sum, p| sum + p.to_vec2())
+                        / points.len() as f32;
+                    aliased[center.y as usize * width + center.x as usize]
+                });
+                let mut coverage = [0.0; 2];
+                let mut blended = [0; 2];
+                let mut outline = [0; 2];
+                for y in 0..height {
+                    for x in 0..width {
+                        let i = y * width + x;
+                        let Some(k) = polygons.iter().position(|(points, _)| {
+                            inside(points, pos2(x as f32 + 0.5, y as f32 + 0.5))
+                                >= -std::f32::consts::FRAC_1_SQRT_2
+                        }) else {
+                            assert!(
+                                aa[i] == background,
+                                "{name}: pixel {x}, {y} outside of the polygons isn't the background"
+                            );
+                            continue;
+                        };
+                        let (points, fill) = (&polygons[k].0, fills[k]);
+                        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+                            .map(|(cx, cy)| inside(points, pos2(x as f32 + cx, y as f32 + cy)));
+                        if corners.iter().all(|&d| d >= 0.0) {
+                            assert!(
+                                aa[i] == fill && aliased[i] == fill,
+                                "{name}: pixel {x}, {y} inside of polygon {k} isn't its fill"
+                            );
+                            coverage[k] += 1.0;
+                        } else {
+                            outline[k] += 1;
+                            assert!(
+                                (0..4).all(|c| aa[i][c] >= fill[c].min(background[c])
+                                    && aa[i][c] <= fill[c].max(background[c])),
+                                "{name}: pixel {x}, {y} on the outline of polygon {k} isn't between its fill and the background"
+                            );
+                            blended[k] += (aa[i] != fill && aa[i] != background) as u32;
+                            let c = (0..3)
+                                .max_by_key(|&c| fill[c].abs_diff(background[c]))
+                                .unwrap();
+                            coverage[k] += (aa[i][c] as f32 - background[c] as f32)
+                                / (fill[c] as f32 - background[c] as f32);
+                        }
+                    }
+                }
+
+                for (k, (points, _)) in polygons.iter().enumerate() {
+                    let area = (0..points.len())
+                        .map(|i| {
+                            cross(
+                                points[i].to_vec2(),
+                                points[(i + 1) % points.len()].to_vec2(),
+                            )
+                        })
+                        .sum::<f32>()
+                        .abs()
+                        / 2.0;
+                    assert!(
+                        (coverage[k] - area).abs() < 1.0,
+                        "{name}: coverage {} of polygon {k} doesn't match its area {area}",
+                        coverage[k]
+                    );
+                    assert!(
+                        blended[k] * 2 >= outline[k],
+                        "{name}: only {} of {} pixels on the outline of polygon {k} are blended",
+                        blended[k],
+                        outline[k]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Draws filled shapes tessellated without feathering with coverage AA and compares them with the GPU drawing them
+    // feathered, which is egui's own AA. Only a few pixels may be off by more than 32, and the total difference must be
+    // well below that of the same shapes drawn without AA.
+    pub fn compare_coverage_aa_with_gpu() {
+        fn app() -> impl FnMut(&mut egui::Ui) {
+            move |ui: &mut egui::Ui| {
+                let painter = ui.painter();
+                painter.rect_filled(ui.max_rect(), 0.0, Color32::from_gray(30));
+                for i in 0..6 {
+                    let center = pos2(80.0 + i as f32 * 150.0, 100.0);
+                    let color = Color32::from_rgb(200, 40 * i as u8, 255 - 40 * i as u8);
+                    painter.circle_filled(center, 20.0 + i as f32 * 9.3, color);
+                    let angle = 0.1 + i as f32 * 0.25;
+                    painter.add(egui::Shape::convex_polygon(
+                        (0..4)
+                            .map(|k| {
+                                center
+                                    + vec2(0.0, 230.0)
+                                    + 50.0
+                                        * Vec2::angled(
+                                            angle + k as f32 * std::f32::consts::FRAC_PI_2,
+                                        )
+                            })
+                            .collect(),
+                        color,
+                        egui::Stroke::NONE,
+                    ));
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![
+                            center + vec2(-60.0, 350.0),
+                            center + vec2(40.0 + i as f32 * 7.0, 330.0),
+                            center + vec2(10.0, 460.0 - i as f32 * 11.0),
+                        ],
+                        color,
+                        egui::Stroke::NONE,
+                    ));
+                    painter.rect_filled(
+                        Rect::from_min_size(center + vec2(-50.3, 500.6), vec2(100.4, 60.2)),
+                        5.0 + i as f32 * 3.0,
+                        color,
+                    );
+                }
+            }
+        }
+
+        for px_per_point in [1.0, 1.5] {
+            let mut harness = HarnessBuilder::default()
+                .with_size(RESOLUTION)
+                .with_pixels_per_point(px_per_point)
+                .renderer(egui_kittest::LazyRenderer::default())
+                .build_ui(app());
+            harness.run();
+            let gpu_render_image = harness.render().unwrap();
+
+            // Count of pixels with a channel off by more than 32, and the sum of the max channel difference of every
+            // pixel
+            let [(aa_failed, aa_error), (aliased_failed, aliased_error)] =
+                [true, false].map(|coverage_aa| {
+                    let mut harness = HarnessBuilder::default()
+                        .with_size(RESOLUTION)
+                        .with_pixels_per_point(px_per_point)
+                        .renderer(
+                            EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                                .with_coverage_aa(coverage_aa),
+                        )
+                        .build_ui(app());
+                    harness
+                        .ctx
+                        .tessellation_options_mut(|options| options.feathering = false);
+                    harness.run();
+                    let cpu_render_image = harness.render().unwrap();
+                    let diffs = gpu_render_image
+                        .pixels()
+                        .zip(cpu_render_image.pixels())
+                        .map(|(a, b)| (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap())
+                        .collect::<Vec<_>>();
+                    let failed = diffs.iter().filter(|&&diff| diff > 32).count();
+                    if coverage_aa && failed > 64 {
+                        let _ = std::fs::create_dir("tests/tmp/");
+                        gpu_render_image
+                            .save(format!(
+                                "tests/tmp/gpu_coverage_aa_px_per_pt {px_per_point}.png"
+                            ))
+                            .unwrap();
+                        cpu_render_image
+                            .save(format!(
+                                "tests/tmp/cpu_coverage_aa_px_per_pt {px_per_point} - FAIL.png"
+                            ))
+                            .unwrap();
+                    }
+                    (failed, diffs.iter().map(|&diff| diff as u64).sum::<u64>())
+                });
+
+            assert!(
+                aa_failed <= 64,
+                "px_per_pt {px_per_point}: {aa_failed} pixels differ from the GPU by more than 32"
+            );
+            assert!(
+                aa_error * 4 < aliased_error,
+                "px_per_pt {px_per_point}: coverage AA error {aa_error} isn't much below aliased {aliased_error} \
+                 ({aliased_failed} pixels off by more than 32)"
+            );
+        }
+    }
+
+    /// Bytes from a xorshift generator, for kernel inputs that don't need to mean anything
+    fn random_bytes(seed: u32) -> impl Iterator<Item = u8> {
+        let mut state = seed;
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 24) as u8
+        })
+    }
+
+    /// Every combination of YUV matrix and range, in NV12
+    fn yuv_formats() -> impl Iterator<Item = YuvFormat> {
+        [YuvMatrix::Bt601, YuvMatrix::Bt709]
+            .into_iter()
+            .flat_map(|matrix| {
+                [YuvRange::Limited, YuvRange::Full].map(|range| YuvFormat {
+                    layout: YuvLayout::Nv12,
+                    matrix,
+                    range,
+                })
+            })
+    }
+
+    #[test]
+    // Converts Y, U, V texels with every SIMD implementation available on this processor, for every matrix, range and
+    // field order, and compares them with the scalar conversion. The texels include every combination of the limits of
+    // the ranges, so results are clamped, and random ones. Spans of every length up to 35 cover the tails that don't
+    // fill a SIMD register.
+    pub fn yuv_to_rgba_span_matches_convert() {
+        let limits = [0, 1, 16, 127, 128, 235, 240, 254, 255];
+        let mut texels = limits
+            .iter()
+            .flat_map(|&y| limits.iter().flat_map(move |&u| limits.map(|v| [y, u, v])))
+            .collect::<Vec<_>>();
+        let mut bytes = random_bytes(0x2545_f491);
+        texels.extend((0..300).map(|_| [0; 3].map(|_| bytes.next().unwrap())));
+        let planes: [Vec<u8>; 3] =
+            std::array::from_fn(|plane| texels.iter().map(|texel| texel[plane]).collect());
+
+        for (simd_impl, name) in simd_impls().iter().enumerate() {
+            for format in yuv_formats() {
+                for (field_order, order_name) in [
+                    (ColorFieldOrder::Rgba, "Rgba"),
+                    (ColorFieldOrder::Bgra, "Bgra"),
+                ] {
+                    for len in (0..=35).chain([texels.len()]) {
+                        let mut dst = vec![[0u8; 4]; len];
+                        yuv_to_rgba_span(
+                            simd_impl,
+                            format,
+                            field_order,
+                            planes.each_ref().map(|plane| &plane[..len]),
+                            &mut dst,
+                        );
+                        for (i, (color, &texel)) in dst.iter().zip(&texels).enumerate() {
+                            assert_eq!(
+                                *color,
+                                yuv_convert(format, field_order, texel),
+                                "{name}, {format:?}, {order_name}: texel {i} {texel:?} of a span of {len}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Draws a coverage texture like the font atlas 1:1 as a rect, scaled as a rect and as a tri, tinted, with alpha
+    // textures and without. As the font texture the 1:1 rect is a glyph run, as another texture it's a rect. It must
+    // be stored as alpha and draw the same as stored as color. A partial update with coverage texels keeps it alpha,
+    // one with colored texels converts it to color. A texture with colored texels is never stored as alpha.
+    pub fn alpha_textures_match_color_textures() {
+        let (width, height) = (48, 48);
+        let size = [16, 16];
+        let mut bytes = random_bytes(0x510e_527f);
+        let mut coverage = |len: usize| {
+            (0..len)
+                .map(|_| Color32::from_white_alpha(bytes.next().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        let full = coverage(size[0] * size[1]);
+        let coverage_update = coverage(5 * 3);
+        let mut color_update = coverage(4 * 6);
+        color_update[7] = Color32::from_rgba_premultiplied(10, 20, 30, 40);
+
+        let delta = |texture_id: egui::TextureId,
+                     pos: Option<[usize; 2]>,
+                     size: [usize; 2],
+                     pixels: &[Color32]| {
+            let mut textures_delta = egui::TexturesDelta::default();
+            textures_delta.set.push((
+                texture_id,
+                egui::epaint::ImageDelta {
+                    image: egui::ImageData::Color(
+                        egui::ColorImage::new(size, pixels.to_vec()).into(),
+                    ),
+                    options: egui::TextureOptions::LINEAR,
+                    pos,
+                },
+            ));
+            textures_delta
+        };
+        let mesh = |texture_id: egui::TextureId| {
+            let mut mesh = egui::Mesh::with_texture(texture_id);
+            let uv = Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0));
+            // Texels [2, 1] to [14, 15] like a glyph in the atlas, 1:1
+            mesh.add_rect_with_uv(
+                Rect::from_min_size(pos2(2.0, 2.0), vec2(12.0, 14.0)),
+                Rect::from_min_max(pos2(2.0 / 16.0, 1.0 / 16.0), pos2(14.0 / 16.0, 15.0 / 16.0)),
+                Color32::from_rgb(250, 200, 40),
+            );
+            mesh.add_rect_with_uv(
+                Rect::from_min_size(pos2(20.0, 3.5), vec2(25.0, 21.0)),
+                uv,
+                Color32::from_rgba_premultiplied(20, 90, 160, 200),
+            );
+            let first = mesh.vertices.len() as u32;
+            for (pos, uv) in [
+                (pos2(4.0, 26.0), pos2(0.0, 0.0)),
+                (pos2(44.0, 30.0), pos2(1.0, 0.2)),
+                (pos2(10.0, 46.0), pos2(0.3, 1.0)),
+            ] {
+                mesh.vertices.push(egui::epaint::Vertex {
+                    pos,
+                    uv,
+                    color: Color32::WHITE,
+                });
+            }
+            mesh.add_triangle(first, first + 1, first + 2);
+            [egui::ClippedPrimitive {
+                clip_rect: Rect::EVERYTHING,
+                primitive: egui::epaint::Primitive::Mesh(mesh),
+            }]
+        };
+
+        for texture_id in [egui::TextureId::default(), egui::TextureId::Managed(1)] {
+            // The texture after each delta, and whether it must then be stored as alpha
+            let deltas = [
+                (delta(texture_id, None, size, &full), true),
+                (
+                    delta(texture_id, Some([3, 9]), [5, 3], &coverage_update),
+                    true,
+                ),
+                (
+                    delta(texture_id, Some([10, 2]), [4, 6], &color_update),
+                    false,
+                ),
+                (
+                    delta(texture_id, Some([0, 0]), [5, 3], &coverage_update),
+                    false,
+                ),
+            ];
+            let paint_jobs = mesh(texture_id);
+
+            for allow_raster_opt in [false, true] {
+                let [mut alpha, mut color] = [true, false].map(|alpha_textures| {
+                    EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                        .with_alpha_textures(alpha_textures)
+                        .with_allow_raster_opt(allow_raster_opt)
+                        .with_caching(false)
+                });
+                for (frame, (textures_delta, stored_as_alpha)) in deltas.iter().enumerate() {
+                    let [alpha_buffer, color_buffer] = [&mut alpha, &mut color].map(|renderer| {
+                        let mut buffer = vec![[0u8; 4]; width * height];
+                        renderer.render(
+                            &mut BufferMutRef::new(&mut buffer, width, height),
+                            &paint_jobs,
+                            textures_delta,
+                            1.0,
+                        );
+                        buffer
+                    });
+                    let name =
+                        format!("{texture_id:?}, raster_opt {allow_raster_opt}, frame {frame}");
+                    assert_eq!(
+                        texture_is_alpha(&alpha, texture_id),
+                        Some(*stored_as_alpha),
+                        "{name}"
+                    );
+                    assert_eq!(texture_is_alpha(&color, texture_id), Some(false), "{name}");
+                    assert!(
+                        alpha_buffer == color_buffer,
+                        "{name}: the alpha texture doesn't draw like the color texture"
+                    );
+                }
+            }
+        }
+
+        let texture_id = egui::TextureId::default();
+        let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba).with_alpha_textures(true);
+        let mut buffer = vec![[0u8; 4]; width * height];
+        renderer.render(
+            &mut BufferMutRef::new(&mut buffer, width, height),
+            &mesh(texture_id),
+            &delta(texture_id, None, [4, 6], &color_update),
+            1.0,
+        );
+        assert_eq!(texture_is_alpha(&renderer, texture_id), Some(false));
+    }
+
+    #[test]
+    // Draws NV12 and I420 frames 1:1 with every matrix and range, in both field orders. The frames hold colors in 2x2
+    // texel blocks, one chroma texel each, encoded with the standard equations. Drawing them must give back the colors
+    // within rounding. The encoding of pure colors is checked against their well known limited range values.
+    pub fn yuv_textures_round_trip() {
+        let colors = [
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+            [255, 255, 255],
+            [0, 0, 0],
+            [128, 128, 128],
+            [255, 128, 0],
+            [0, 128, 128],
+            [200, 150, 100],
+            [30, 60, 220],
+        ];
+        let (blocks_x, blocks_y) = (5, 2);
+        let size = [blocks_x * 2, blocks_y * 2];
+        let encode = |format: YuvFormat, [r, g, b]: [u8; 3]| {
+            let (kr, kb) = match format.matrix {
+                YuvMatrix::Bt601 => (0.299, 0.114),
+                YuvMatrix::Bt709 => (0.2126, 0.0722),
+            };
+            let [r, g, b] = [r, g, b].map(|c| c as f32 / 255.0);
+            let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+            let (pb, pr) = ((b - y) / (2.0 * (1.0 - kb)), (r - y) / (2.0 * (1.0 - kr)));
+            let [y, u, v] = match format.range {
+                YuvRange::Limited => [16.0 + 219.0 * y, 128.0 + 224.0 * pb, 128.0 + 224.0 * pr],
+                YuvRange::Full => [255.0 * y, 128.0 + 255.0 * pb, 128.0 + 255.0 * pr],
+            };
+            [y, u, v].map(|x| x.round().clamp(0.0, 255.0) as u8)
+        };
+
+        let known = [
+            (
+                YuvMatrix::Bt601,
+                [[81, 90, 240], [145, 54, 34], [41, 240, 110]],
+            ),
+            (
+                YuvMatrix::Bt709,
+                [[63, 102, 240], [173, 42, 26], [32, 240, 118]],
+            ),
+        ];
+        for (matrix, yuv) in known {
+            let format = YuvFormat {
+                matrix,
+                range: YuvRange::Limited,
+                ..Default::default()
+            };
+            for (color, yuv) in colors.into_iter().zip(yuv) {
+                assert_eq!(encode(format, color), yuv, "{matrix:?} {color:?}");
+            }
+        }
+
+        for layout in [YuvLayout::Nv12, YuvLayout::I420] {
+            for format in yuv_formats() {
+                let format = YuvFormat { layout, ..format };
+                let block = |x: usize, y: usize| (y / 2) * blocks_x + x / 2;
+                let mut frame = vec![0u8; format.buffer_len(size)];
+                let (luma, chroma) = frame.split_at_mut(size[0] * size[1]);
+                for (i, y) in luma.iter_mut().enumerate() {
+                    *y = encode(format, colors[block(i % size[0], i / size[0])])[0];
+                }
+                for (i, color) in colors.into_iter().enumerate() {
+                    let [_, u, v] = encode(format, color);
+                    match layout {
+                        YuvLayout::Nv12 => chroma[i * 2..i * 2 + 2].copy_from_slice(&[u, v]),
+                        YuvLayout::I420 => {
+                            chroma[i] = u;
+                            chroma[colors.len() + i] = v;
+                        }
+                    }
+                }
+
+                for (field_order, order_name) in [
+                    (ColorFieldOrder::Rgba, "Rgba"),
+                    (ColorFieldOrder::Bgra, "Bgra"),
+                ] {
+                    let mut renderer = EguiSoftwareRender::new(field_order);
+                    let id =
+                        renderer.register_yuv_texture(size, format, egui::TextureOptions::NEAREST);
+                    renderer
+                        .yuv_texture_mut(id)
+                        .unwrap()
+                        .copy_from_slice(&frame);
+                    let mut buffer = vec![[0u8; 4]; size[0] * size[1]];
+                    renderer.render(
+                        &mut BufferMutRef::new(&mut buffer, size[0], size[1]),
+                        &[textured_rect(
+                            id,
+                            Rect::from_min_size(Pos2::ZERO, vec2(size[0] as f32, size[1] as f32)),
+                        )],
+                        &Default::default(),
+                        1.0,
+                    );
+
+                    for (i, pixel) in buffer.iter().enumerate() {
+                        let [r, g, b] = colors[block(i % size[0], i / size[0])];
+                        let expected = match field_order {
+                            ColorFieldOrder::Rgba => [r, g, b, 255],
+                            ColorFieldOrder::Bgra => [b, g, r, 255],
+                        };
+                        assert!(
+                            pixel.iter().zip(expected).all(|(a, b)| a.abs_diff(b) <= 2),
+                            "{format:?}, {order_name}: pixel {i} {pixel:?} isn't {expected:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Samples a texture along spans with every SIMD implementation available on this processor and compares them with
+    // the generic one. Covers every wrap mode with nearest and linear filtering, both field orders, uvs that are
+    // negative or beyond 1, steps in either direction and span lengths that leave tails of every size.
+    pub fn sample_bilinear_span_matches_generic() {
+        let size = [7, 5];
+        let mut bytes = random_bytes(0x9e37_79b9);
+        let pixels = (0..size[0] * size[1])
+            .map(|_| {
+                let [r, g, b, a] = [0; 4].map(|_| bytes.next().unwrap());
+                Color32::from_rgba_premultiplied(r.min(a), g.min(a), b.min(a), a)
+            })
+            .collect();
+        let image = egui::ColorImage::new(size, pixels);
+        let uv_starts = [
+            vec2(0.1, 0.2),
+            vec2(-1.3, -0.7),
+            vec2(1.6, 2.2),
+            vec2(-0.05, 0.95),
+        ];
+        let uv_steps = [
+            vec2(0.013, 0.0),
+            vec2(-0.021, 0.007),
+            vec2(0.37, -0.29),
+            vec2(1.0 / 7.0, 0.0),
+        ];
+        let simd_impls = simd_impls();
+        let generic = simd_impls.len() - 1;
+
+        for wrap_mode in [
+            egui::TextureWrapMode::ClampToEdge,
+            egui::TextureWrapMode::Repeat,
+            egui::TextureWrapMode::MirroredRepeat,
+        ] {
+            for filter in [egui::TextureFilter::Nearest, egui::TextureFilter::Linear] {
+                let options = egui::TextureOptions {
+                    magnification: filter,
+                    minification: filter,
+                    wrap_mode,
+                    ..egui::TextureOptions::LINEAR
+                };
+                for (field_order, order_name) in [
+                    (ColorFieldOrder::Rgba, "Rgba"),
+                    (ColorFieldOrder::Bgra, "Bgra"),
+                ] {
+                    for (uv_start, uv_step) in uv_starts
+                        .into_iter()
+                        .flat_map(|uv_start| uv_steps.map(|uv_step| (uv_start, uv_step)))
+                    {
+                        for len in (0..=19).chain([37]) {
+                            let sample = |simd_impl: usize| {
+                                let mut dst = vec![[0u8; 4]; len];
+                                sample_bilinear_span(
+                                    simd_impl,
+                                    &image,
+                                    options,
+                                    field_order,
+                                    uv_start,
+                                    uv_step,
+                                    &mut dst,
+                                );
+                                dst
+                            };
+                            let expected = sample(generic);
+                            for (simd_impl, name) in simd_impls.iter().enumerate().take(generic) {
+                                let dst = sample(simd_impl);
+                                assert!(
+                                    dst == expected,
+                                    "{name}: {options:?}, {order_name}, uv {uv_start:?} + {uv_step:?} * i, len {len}:\n{dst:?}\n{expected:?}",
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The texel along an axis of `size` texels that the unbounded texel `t` lands on with `wrap_mode`
+    fn wrap_texel(wrap_mode: egui::TextureWrapMode, t: i64, size: usize) -> usize {
+        let size = size as i64;
+        match wrap_mode {
+            egui::TextureWrapMode::ClampToEdge => t.clamp(0, size - 1) as usize,
+            egui::TextureWrapMode::Repeat => t.rem_euclid(size) as usize,
+            egui::TextureWrapMode::MirroredRepeat => {
+                let t = t.rem_euclid(size * 2);
+                (if t < size { t } else { size * 2 - 1 - t }) as usize
+            }
+        }
+    }
+
+    #[test]
+    // Samples the texel centers of a power of 2 sized texture over three periods either way with every SIMD
+    // implementation available on this processor. Repeat must give back texel k mod size, MirroredRepeat must reflect
+    // the texels on odd periods, negative ones included, and ClampToEdge must repeat the edge texels. Texel centers are
+    // exact in f32, so both filters must give the texels exactly.
+    pub fn wrapped_uvs_sample_texel_centers() {
+        let size = [8, 4];
+        let mut bytes = random_bytes(0x3c6e_f372);
+        let pixels = (0..size[0] * size[1])
+            .map(|_| {
+                let [r, g, b] = [0; 3].map(|_| bytes.next().unwrap());
+                Color32::from_rgb(r, g, b)
+            })
+            .collect::<Vec<_>>();
+        let image = egui::ColorImage::new(size, pixels.clone());
+        let periods = 3;
+        let len = size[0] * periods * 2;
+
+        for (simd_impl, name) in simd_impls().iter().enumerate() {
+            for wrap_mode in [
+                egui::TextureWrapMode::ClampToEdge,
+                egui::TextureWrapMode::Repeat,
+                egui::TextureWrapMode::MirroredRepeat,
+            ] {
+                for filter in [egui::TextureFilter::Nearest, egui::TextureFilter::Linear] {
+                    let options = egui::TextureOptions {
+                        magnification: filter,
+                        minification: filter,
+                        wrap_mode,
+                        ..egui::TextureOptions::LINEAR
+                    };
+                    for (field_order, order_name) in [
+                        (ColorFieldOrder::Rgba, "Rgba"),
+                        (ColorFieldOrder::Bgra, "Bgra"),
+                    ] {
+                        let first = -((size[0] * periods) as i64);
+                        for ty in -((size[1] * periods) as i64)..(size[1] * periods) as i64 {
+                            let uv_start = vec2(
+                                (first as f32 + 0.5) / size[0] as f32,
+                                (ty as f32 + 0.5) / size[1] as f32,
+                            );
+                            let mut dst = vec![[0u8; 4]; len];
+                            sample_bilinear_span(
+                                simd_impl,
+                                &image,
+                                options,
+                                field_order,
+                                uv_start,
+                                vec2(1.0 / size[0] as f32, 0.0),
+                                &mut dst,
+                            );
+                            let y = wrap_texel(wrap_mode, ty, size[1]);
+                            for (tx, pixel) in (first..).zip(&dst) {
+                                let x = wrap_texel(wrap_mode, tx, size[0]);
+                                let [r, g, b, a] = pixels[x + y * size[0]].to_array();
+                                let expected = match field_order {
+                                    ColorFieldOrder::Rgba => [r, g, b, a],
+                                    ColorFieldOrder::Bgra => [b, g, r, a],
+                                };
+                                assert_eq!(
+                                    *pixel, expected,
+                                    "{name}: {options:?}, {order_name}: texel [{tx}, {ty}] isn't [{x}, {y}]"
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Draws a textured rect whose texels line up 1:1 with pixels but whose uvs start before and end past the texture,
+    // for every wrap mode and filter, tinted or not. Allowing raster optimizations draws it with the wrapped nearest
+    // rect path, disallowing them samples it as tris. Both must give the wrapped texels. The tris are only checked with
+    // linear filtering, nearest sampling of tris takes the previous texel where the interpolated uvs fall just short
+    // of the texel centers.
+    pub fn wrapped_nearest_rect_matches_sampled() {
+        let (width, height) = (24, 16);
+        let size = [8, 4];
+        let texture_id = egui::TextureId::Managed(0);
+        let mut bytes = random_bytes(0xa54f_f53a);
+        let pixels = (0..size[0] * size[1])
+            .map(|_| {
+                let [r, g, b] = [0; 3].map(|_| bytes.next().unwrap());
+                Color32::from_rgb(r, g, b)
+            })
+            .collect::<Vec<_>>();
+        // Starts 10 texels left of and 3 texels above the texture, and spans 2.5 and 3 periods
+        let rect = Rect::from_min_size(pos2(2.0, 2.0), vec2(20.0, 12.0));
+        let tex_min = [-10, -3];
+        let uv = Rect::from_min_size(
+            pos2(
+                tex_min[0] as f32 / size[0] as f32,
+                tex_min[1] as f32 / size[1] as f32,
+            ),
+            vec2(20.0 / size[0] as f32, 12.0 / size[1] as f32),
+        );
+
+        for wrap_mode in [
+            egui::TextureWrapMode::ClampToEdge,
+            egui::TextureWrapMode::Repeat,
+            egui::TextureWrapMode::MirroredRepeat,
+        ] {
+            for filter in [egui::TextureFilter::Nearest, egui::TextureFilter::Linear] {
+                let options = egui::TextureOptions {
+                    magnification: filter,
+                    minification: filter,
+                    wrap_mode,
+                    ..egui::TextureOptions::LINEAR
+                };
+                let mut textures_delta = egui::TexturesDelta::default();
+                textures_delta.set.push((
+                    texture_id,
+                    egui::epaint::ImageDelta::full(
+                        egui::ColorImage::new(size, pixels.clone()),
+                        options,
+                    ),
+                ));
+                for tint in [
+                    Color32::WHITE,
+                    Color32::from_rgba_premultiplied(60, 120, 30, 160),
+                ] {
+                    let mut mesh = egui::Mesh::with_texture(texture_id);
+                    mesh.add_rect_with_uv(rect, uv, tint);
+                    let render = |allow_raster_opt: bool| {
+                        let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba)
+                            .with_allow_raster_opt(allow_raster_opt);
+                        let mut buffer = vec![[0u8; 4]; width * height];
+                        renderer.render(
+                            &mut BufferMutRef::new(&mut buffer, width, height),
+                            &[egui::ClippedPrimitive {
+                                clip_rect: Rect::EVERYTHING,
+                                primitive: egui::epaint::Primitive::Mesh(mesh.clone()),
+                            }],
+                            &textures_delta,
+                            1.0,
+                        );
+                        buffer
+                    };
+
+                    let mut paths = vec![("nearest rect", render(true))];
+                    if filter == egui::TextureFilter::Linear {
+                        paths.push(("sampled", render(false)));
+                    }
+                    for i in 0..width * height {
+                        let (x, y) = (i % width, i / width);
+                        let expected = if rect.contains(pos2(x as f32 + 0.5, y as f32 + 0.5)) {
+                            let tx = wrap_texel(wrap_mode, x as i64 - 2 + tex_min[0], size[0]);
+                            let ty = wrap_texel(wrap_mode, y as i64 - 2 + tex_min[1], size[1]);
+                            let texel = pixels[tx + ty * size[0]];
+                            (texel * tint).to_array()
+                        } else {
+                            [0; 4]
+                        };
+                        for (path, buffer) in &paths {
+                            let pixel = buffer[i];
+                            assert!(
+                                pixel.iter().zip(expected).all(|(a, b)| a.abs_diff(b) <= 1),
+                                "{options:?}, tint {tint:?}, {path}: pixel [{x}, {y}] {pixel:?} isn't \
+                                 {expected:?}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Fills gradient spans with every SIMD implementation available on this processor and compares them with the
+    // generic one. The gradients rise, fall and run past 0 and 1 so results are clamped, and span lengths up to 19 plus
+    // 37 leave tails of every size.
+    pub fn color_gradient_span_matches_generic() {
+        let gradients = [
+            ([0.0, 0.25, 0.5, 1.0], [0.01, 0.02, -0.005, 0.0]),
+            ([1.0, 0.9, 0.1, 0.5], [-0.031, 0.0, 0.017, -0.011]),
+            ([-0.2, 1.3, 0.5, 0.75], [0.07, -0.09, 0.003, 0.06]),
+            (
+                [0.3, 0.6, 0.2, 0.4],
+                [1.0 / 255.0, 0.5 / 255.0, -2.5 / 255.0, 0.0],
+            ),
+        ];
+        let simd_impls = simd_impls();
+        let generic = simd_impls.len() - 1;
+
+        for (col_start, col_step) in gradients {
+            for len in (0..=19).chain([37]) {
+                let gradient = |simd_impl: usize| {
+                    let mut dst = vec![[0u8; 4]; len];
+                    color_gradient_span(simd_impl, col_start, col_step, &mut dst);
+                    dst
+                };
+                let expected = gradient(generic);
+                for (simd_impl, name) in simd_impls.iter().enumerate().take(generic) {
+                    let dst = gradient(simd_impl);
+                    assert!(
+                        dst == expected,
+                        "{name}: {col_start:?} + {col_step:?} * i, len {len}:\n{dst:?}\n{expected:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Blends tinted texels with every SIMD implementation available on this processor and compares them with the
+    // generic one. Texels, tints and destinations are random premultiplied colors plus opaque, transparent and white
+    // ones, and span lengths up to 19 plus 37 leave tails of every size.
+    pub fn egui_blend_u8_slice_tinted_per_px_matches_generic() {
+        let mut bytes = random_bytes(0x6a09_e667);
+        let mut colors = |len: usize| {
+            let mut colors = vec![[0, 0, 0, 0], [255; 4], [0, 0, 0, 255], [40, 80, 120, 255]];
+            colors.extend((colors.len()..len).map(|_| {
+                let [r, g, b, a] = [0; 4].map(|_| bytes.next().unwrap());
+                [r.min(a), g.min(a), b.min(a), a]
+            }));
+            colors.truncate(len);
+            colors
+        };
+        let simd_impls = simd_impls();
+        let generic = simd_impls.len() - 1;
+
+        for round in 0..4 {
+            for len in (0..=19).chain([37]) {
+                let [src, mut tints, dst] = [(); 3].map(|_| colors(len));
+                // Shift the fixed tints between rounds so each meets every fixed texel
+                tints.rotate_left(round % len.max(1));
+                let blend = |simd_impl: usize| {
+                    let mut dst = dst.clone();
+                    egui_blend_u8_slice_tinted_per_px(simd_impl, &src, &tints, &mut dst);
+                    dst
+                };
+                let expected = blend(generic);
+                for (simd_impl, name) in simd_impls.iter().enumerate().take(generic) {
+                    let blended = blend(simd_impl);
+                    assert!(
+                        blended == expected,
+                        "{name}: len {len}:\nsrc {src:?}\ntints {tints:?}\ndst {dst:?}\n{blended:?}\n{expected:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Swaps the red and blue channels of random colors with every SIMD implementation available on this processor and
+    // compares them with the generic one, which must swap only those channels. Span lengths up to 19 plus 37 leave
+    // tails of every size.
+    pub fn swizzle_rgba_bgra_slice_matches_generic() {
+        let mut bytes = random_bytes(0xbb67_ae85);
+        let src = (0..37)
+            .map(|_| {
+                let [r, g, b, a] = [0; 4].map(|_| bytes.next().unwrap());
+                Color32::from_rgba_premultiplied(r, g, b, a)
+            })
+            .collect::<Vec<_>>();
+        let simd_impls = simd_impls();
+        let generic = simd_impls.len() - 1;
+
+        for len in (0..=19).chain([37]) {
+            let src = &src[..len];
+            let swizzle = |simd_impl: usize| {
+                let mut dst = vec![[0u8; 4]; len];
+                swizzle_rgba_bgra_slice(simd_impl, src, &mut dst);
+                dst
+            };
+            let expected = swizzle(generic);
+            assert!(
+                expected
+                    .iter()
+                    .zip(src)
+                    .all(|(&[b, g, r, a], src)| src.to_array() == [r, g, b, a]),
+                "Generic: len {len}:\n{expected:?}\n{src:?}"
+            );
+            for (simd_impl, name) in simd_impls.iter().enumerate().take(generic) {
+                let dst = swizzle(simd_impl);
+                assert!(dst == expected, "{name}: len {len}:\n{dst:?}\n{expected:?}");
+            }
+        }
+    }
+
+    #[test]
+    // Renders frames that load, partially update and free a texture drawn on a rect moving across tiles over a static
+    // background, with a ThreadedEguiSoftwareRender and with an EguiSoftwareRender on the calling thread. Each finished
+    // frame must match, so every texture delta is applied in order and a recycled canvas, which holds an older frame, is
+    // composited again where the frames since then changed it. Then all frames are queued while the worker is paused,
+    // the last one must still match. The serial frames are rendered first so the calling thread doesn't render on the
+    // rayon pool while the paused worker holds it.
+    pub fn threaded_frames_match_serial_render() {
+        let (width, height) = (320, 200);
+        let image = |seed: usize, size: usize| {
+            let pixels = (0..size * size)
+                .map(|i| {
+                    Color32::from_rgb((i * seed) as u8, (i / size * 8) as u8, (i % size * 8) as u8)
+                })
+                .collect();
+            egui::ColorImage::new([size, size], pixels)
+        };
+        let background_id = egui::TextureId::Managed(0);
+        let texture_id = egui::TextureId::Managed(1);
+        let options = egui::TextureOptions::NEAREST;
+        let textures_delta = |frame: usize| {
+            let mut delta = egui::TexturesDelta::default();
+            match frame {
+                0 => delta.set.push((
+                    background_id,
+                    egui::epaint::ImageDelta::full(image(5, 32), options),
+                )),
+                1 => delta.set.push((
+                    texture_id,
+                    egui::epaint::ImageDelta::full(image(3, 32), options),
+                )),
+                3 => delta.set.push((
+                    texture_id,
+                    egui::epaint::ImageDelta::partial([4, 8], image(7, 8), options),
+                )),
+                5 => delta.set.push((
+                    texture_id,
+                    egui::epaint::ImageDelta::full(image(11, 32), options),
+                )),
+                7 => delta.free.push(texture_id),
+                _ => {}
+            }
+            delta
+        };
+
+        let mut serial = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+        let control_id = serial.register_procedural_texture(Arc::new(WorkerControl::default()));
+        let frame_input = |frame: usize| {
+            let mut paint_jobs = vec![
+                textured_rect(
+                    background_id,
+                    Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32)),
+                ),
+                textured_rect(
+                    control_id,
+                    Rect::from_min_size(pos2(280.0, 160.0), vec2(16.0, 16.0)),
+                ),
+            ];
+            if (1..7).contains(&frame) {
+                paint_jobs.push(textured_rect(
+                    texture_id,
+                    Rect::from_min_size(
+                        pos2(frame as f32 * 40.0, (frame % 3) as f32 * 50.0),
+                        vec2(32.0, 32.0),
+                    ),
+                ));
+            }
+            (paint_jobs, textures_delta(frame))
+        };
+
+        let serial_buffers = (0..8)
+            .map(|frame| {
+                let (paint_jobs, textures_delta) = frame_input(frame);
+                let mut buffer = vec![[0u8; 4]; width * height];
+                serial.render(
+                    &mut BufferMutRef::new(&mut buffer, width, height),
+                    &paint_jobs,
+                    &textures_delta,
+                    1.0,
+                );
+                buffer
+            })
+            .collect::<Vec<_>>();
+
+        for queued in [false, true] {
+            let control = Arc::new(WorkerControl::default());
+            let mut threaded = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+            assert_eq!(
+                threaded.register_procedural_texture(control.clone()),
+                control_id
+            );
+            let mut threaded = ThreadedEguiSoftwareRender::new(threaded).with_max_latency(8);
+            if queued {
+                control.paused.store(true, Ordering::Release);
+            }
+
+            for (frame, serial_buffer) in serial_buffers.iter().enumerate() {
+                let (paint_jobs, textures_delta) = frame_input(frame);
+                threaded.submit(width, height, paint_jobs, textures_delta, 1.0);
+
+                if !queued {
+                    threaded.finish();
+                    let mut threaded_buffer = vec![[0u8; 4]; width * height];
+                    assert!(threaded.try_blit(&mut BufferMutRef::new(
+                        &mut threaded_buffer,
+                        width,
+                        height
+                    )));
+                    assert!(
+                        threaded_buffer == *serial_buffer,
+                        "frame {frame} of the threaded renderer doesn't match the serial one"
+                    );
+                }
+            }
+
+            if queued {
+                assert_eq!(threaded.frames_in_flight(), 8);
+                control.paused.store(false, Ordering::Release);
+                threaded.finish();
+                let mut threaded_buffer = vec![[0u8; 4]; width * height];
+                assert!(threaded.try_blit(&mut BufferMutRef::new(
+                    &mut threaded_buffer,
+                    width,
+                    height
+                )));
+                assert!(
+                    Some(&threaded_buffer) == serial_buffers.last(),
+                    "last queued frame of the threaded renderer doesn't match the serial one"
+                );
+            }
+        }
+    }
+
+    #[test]
+    // try_blit() only blits a finished frame within max_latency frames of the last submitted one, and with a
+    // max_latency of 0 submit() waits for the previous frame to finish. The worker is paused to hold frames in flight.
+    pub fn threaded_max_latency() {
+        let (width, height) = (64, 64);
+        let control = Arc::new(WorkerControl::default());
+        let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+        let id = renderer.register_procedural_texture(control.clone());
+        let paint_jobs = vec![textured_rect(
+            id,
+            Rect::from_min_size(pos2(8.0, 8.0), vec2(32.0, 32.0)),
+        )];
+        let submit = |threaded: &mut ThreadedEguiSoftwareRender| {
+            threaded.submit(width, height, paint_jobs.clone(), Default::default(), 1.0)
+        };
+        let try_blit = |threaded: &mut ThreadedEguiSoftwareRender| {
+            let mut buffer = vec![[0u8; 4]; width * height];
+            threaded.try_blit(&mut BufferMutRef::new(&mut buffer, width, height))
+        };
+
+        let mut threaded = ThreadedEguiSoftwareRender::new(renderer);
+        submit(&mut threaded);
+        threaded.finish();
+        assert!(try_blit(&mut threaded), "finished frame wasn't blitted");
+
+        control.paused.store(true, Ordering::Release);
+        submit(&mut threaded);
+        assert!(
+            try_blit(&mut threaded),
+            "frame 1 is within the default max_latency of 1 of frame 2"
+        );
+        submit(&mut threaded);
+        assert_eq!(threaded.frames_in_flight(), 2);
+        assert!(
+            !try_blit(&mut threaded),
+            "frame 1 is beyond the default max_latency of 1 of frame 3"
+        );
+        control.paused.store(false, Ordering::Release);
+        threaded.finish();
+        assert!(try_blit(&mut threaded), "frame 3 wasn't blitted");
+
+        let mut threaded =
+            ThreadedEguiSoftwareRender::new(threaded.into_inner()).with_max_latency(0);
+        control.paused.store(true, Ordering::Release);
+        submit(&mut threaded);
+        assert!(
+            !try_blit(&mut threaded),
+            "with max_latency 0 only the last submitted frame is blitted"
+        );
+        let released = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                released.store(true, Ordering::Release);
+                control.paused.store(false, Ordering::Release);
+            });
+            submit(&mut threaded);
+            assert!(
+                released.load(Ordering::Acquire),
+                "with max_latency 0 submit() didn't wait for the previous frame"
+            );
+        });
+        assert!(threaded.frames_in_flight() <= 1);
+        threaded.finish();
+        assert!(
+            try_blit(&mut threaded),
+            "last submitted frame wasn't blitted"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "render worker test panic")]
+    // A panic on the worker thread of a ThreadedEguiSoftwareRender is resumed on the calling thread.
+    pub fn threaded_worker_panic_reaches_caller() {
+        let control = Arc::new(WorkerControl::default());
+        let mut renderer = EguiSoftwareRender::new(ColorFieldOrder::Rgba);
+        let id = renderer.register_procedural_texture(control.clone());
+        let mut threaded = ThreadedEguiSoftwareRender::new(renderer);
+
+        control.panic.store(true, Ordering::Release);
+        threaded.submit(
+            64,
+            64,
+            vec![textured_rect(
+                id,
+                Rect::from_min_size(Pos2::ZERO, vec2(32.0, 32.0)),
+            )],
+            Default::default(),
+            1.0,
+        );
+        threaded.finish();
+    }
+
     // Returning none indicates no diff
     fn dify(
         gpu_render_image: &ImageBuffer<Rgba<u8>, Vec<u8>>,